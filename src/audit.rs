@@ -0,0 +1,475 @@
+//! Structured audit records for moderation and administrative events.
+//!
+//! Records come from the protocol layer ([`AuditRecord::from_event`],
+//! [`ModerationRecord`]) and from a frame's `AuditMessage` property, and are
+//! written to an [`AuditSink`].
+
+use std::{
+    fmt,
+    fs::{File, OpenOptions},
+    io::{self, BufWriter, Write},
+    path::Path,
+    str::FromStr,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::ircx::{Command, ModerationRecord, Prefix, SessionEvent, room::is_channel};
+
+/// Stands in for an absent field in the `AuditMessage` format.
+const ABSENT: &str = "-";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuditError {
+    /// Fewer than the four leading fields.
+    Truncated,
+    BadTimestamp(String),
+    /// A field that would not survive the round trip, e.g. one with a space.
+    BadField(String),
+}
+
+impl fmt::Display for AuditError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuditError::Truncated => f.write_str("truncated audit message"),
+            AuditError::BadTimestamp(s) => write!(f, "invalid audit timestamp `{s}`"),
+            AuditError::BadField(s) => write!(f, "invalid audit field `{s}`"),
+        }
+    }
+}
+
+impl std::error::Error for AuditError {}
+
+/// One audited event.
+///
+/// The `AuditMessage` form is
+/// `<unix-seconds> <action> <actor> <channel> <target> [:<reason>]`,
+/// with `-` for an absent channel or target.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditRecord {
+    pub timestamp: SystemTime,
+    /// Nick or mask of whoever acted.
+    pub actor: String,
+    /// Lower-case verb such as `kick`, `ban` or `prop`.
+    pub action: String,
+    pub channel: Option<String>,
+    /// Member, mask or property acted on.
+    pub target: Option<String>,
+    pub reason: Option<String>,
+}
+
+impl AuditRecord {
+    pub fn new(actor: impl Into<String>, action: impl Into<String>) -> Self {
+        Self {
+            timestamp: SystemTime::now(),
+            actor: actor.into(),
+            action: action.into(),
+            channel: None,
+            target: None,
+            reason: None,
+        }
+    }
+
+    pub fn with_channel(mut self, channel: impl Into<String>) -> Self {
+        self.channel = Some(channel.into());
+        self
+    }
+
+    pub fn with_target(mut self, target: impl Into<String>) -> Self {
+        self.target = Some(target.into());
+        self
+    }
+
+    pub fn with_reason(mut self, reason: impl Into<String>) -> Self {
+        self.reason = Some(reason.into());
+        self
+    }
+
+    pub fn with_timestamp(mut self, timestamp: SystemTime) -> Self {
+        self.timestamp = timestamp;
+        self
+    }
+
+    /// Audits a `KICK`, channel `MODE`, `TOPIC` or `PROP` change seen on the wire.
+    pub fn from_event(event: &SessionEvent) -> Option<Self> {
+        let SessionEvent::Message {
+            source: Some(source),
+            command,
+        } = event
+        else {
+            return None;
+        };
+        let actor = Prefix::parse(source).nick;
+        let record = match command {
+            Command::Kick {
+                channel,
+                nick,
+                reason,
+            } => AuditRecord {
+                reason: reason.clone(),
+                ..AuditRecord::new(actor, "kick")
+                    .with_channel(channel)
+                    .with_target(nick)
+            },
+            Command::Mode {
+                target,
+                modes: Some(modes),
+                args,
+            } if is_channel(target) => AuditRecord {
+                target: args.first().cloned(),
+                ..AuditRecord::new(actor, "mode")
+                    .with_channel(target)
+                    .with_reason(modes)
+            },
+            Command::Topic {
+                channel,
+                topic: Some(topic),
+            } => AuditRecord::new(actor, "topic")
+                .with_channel(channel)
+                .with_reason(topic),
+            Command::Prop {
+                target,
+                property,
+                value: Some(value),
+            } if is_channel(target) => AuditRecord::new(actor, "prop")
+                .with_channel(target)
+                .with_target(property)
+                .with_reason(value),
+            _ => return None,
+        };
+        Some(record)
+    }
+
+    pub fn parse(message: &str) -> Result<Self, AuditError> {
+        let (fields, reason) = match message.split_once(" :") {
+            Some((fields, reason)) => (fields, Some(reason.to_owned())),
+            None => (message, None),
+        };
+        let fields: Vec<&str> = fields.split(' ').collect();
+        let [timestamp, action, actor, channel, rest @ ..] = fields.as_slice() else {
+            return Err(AuditError::Truncated);
+        };
+        let seconds: u64 = timestamp
+            .parse()
+            .map_err(|_| AuditError::BadTimestamp(timestamp.to_string()))?;
+        let optional = |s: &str| (s != ABSENT).then(|| s.to_owned());
+        let target = match rest {
+            [] => None,
+            [target] => optional(target),
+            _ => return Err(AuditError::BadField(rest.join(" "))),
+        };
+        Ok(Self {
+            timestamp: UNIX_EPOCH + Duration::from_secs(seconds),
+            actor: actor.to_string(),
+            action: action.to_string(),
+            channel: optional(channel),
+            target,
+            reason,
+        })
+    }
+
+    /// Checks every field except the reason is a single non-empty word.
+    pub fn validate(&self) -> Result<(), AuditError> {
+        let fields = [
+            Some(&self.actor),
+            Some(&self.action),
+            self.channel.as_ref(),
+            self.target.as_ref(),
+        ];
+        match fields
+            .into_iter()
+            .flatten()
+            .find(|f| f.is_empty() || f.as_str() == ABSENT || f.contains([' ', '\r', '\n']))
+        {
+            Some(field) => Err(AuditError::BadField(field.clone())),
+            None => Ok(()),
+        }
+    }
+
+    pub fn unix_seconds(&self) -> u64 {
+        self.timestamp
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs())
+    }
+
+    /// One JSON object, without a trailing newline.
+    pub fn to_json(&self) -> String {
+        let mut json = format!("{{\"timestamp\":{}", self.unix_seconds());
+        for (key, value) in [
+            ("actor", Some(&self.actor)),
+            ("action", Some(&self.action)),
+            ("channel", self.channel.as_ref()),
+            ("target", self.target.as_ref()),
+            ("reason", self.reason.as_ref()),
+        ] {
+            json.push_str(",\"");
+            json.push_str(key);
+            json.push_str("\":");
+            match value {
+                Some(value) => json_string(&mut json, value),
+                None => json.push_str("null"),
+            }
+        }
+        json.push('}');
+        json
+    }
+}
+
+/// Renders the `AuditMessage` form.
+impl fmt::Display for AuditRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {} {} {}",
+            self.unix_seconds(),
+            self.action,
+            self.actor,
+            self.channel.as_deref().unwrap_or(ABSENT),
+            self.target.as_deref().unwrap_or(ABSENT)
+        )?;
+        if let Some(reason) = &self.reason {
+            write!(f, " :{reason}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for AuditRecord {
+    type Err = AuditError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl From<&ModerationRecord> for AuditRecord {
+    fn from(record: &ModerationRecord) -> Self {
+        Self {
+            timestamp: record.at,
+            actor: record.actor.clone(),
+            action: record.action.name().to_owned(),
+            channel: Some(record.channel.clone()),
+            target: record.action.target().map(str::to_owned),
+            reason: record.action.reason().map(str::to_owned),
+        }
+    }
+}
+
+/// Somewhere audit records go.
+pub trait AuditSink {
+    fn record(&mut self, record: &AuditRecord) -> io::Result<()>;
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Records `event` if it is auditable; returns whether it was.
+    fn record_event(&mut self, event: &SessionEvent) -> io::Result<bool> {
+        match AuditRecord::from_event(event) {
+            Some(record) => self.record(&record).map(|()| true),
+            None => Ok(false),
+        }
+    }
+
+    fn record_moderation(&mut self, record: &ModerationRecord) -> io::Result<()> {
+        self.record(&AuditRecord::from(record))
+    }
+}
+
+impl<S: AuditSink + ?Sized> AuditSink for &mut S {
+    fn record(&mut self, record: &AuditRecord) -> io::Result<()> {
+        (**self).record(record)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        (**self).flush()
+    }
+}
+
+impl<S: AuditSink + ?Sized> AuditSink for Box<S> {
+    fn record(&mut self, record: &AuditRecord) -> io::Result<()> {
+        (**self).record(record)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        (**self).flush()
+    }
+}
+
+/// Keeps records in memory, e.g. for tests or an in-app log view.
+#[derive(Debug, Clone, Default)]
+pub struct MemorySink {
+    records: Vec<AuditRecord>,
+}
+
+impl MemorySink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn records(&self) -> &[AuditRecord] {
+        &self.records
+    }
+
+    pub fn take(&mut self) -> Vec<AuditRecord> {
+        std::mem::take(&mut self.records)
+    }
+}
+
+impl AuditSink for MemorySink {
+    fn record(&mut self, record: &AuditRecord) -> io::Result<()> {
+        self.records.push(record.clone());
+        Ok(())
+    }
+}
+
+/// Writes one JSON object per line.
+#[derive(Debug)]
+pub struct JsonLinesSink<W: Write> {
+    writer: W,
+}
+
+impl JsonLinesSink<BufWriter<File>> {
+    /// Appends to `path`, creating it if needed.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self::new(BufWriter::new(file)))
+    }
+}
+
+impl<W: Write> JsonLinesSink<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: Write> AuditSink for JsonLinesSink<W> {
+    fn record(&mut self, record: &AuditRecord) -> io::Result<()> {
+        writeln!(self.writer, "{}", record.to_json())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+fn json_string(out: &mut String, value: &str) {
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if u32::from(c) < 0x20 => out.push_str(&format!("\\u{:04x}", u32::from(c))),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(seconds: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(seconds)
+    }
+
+    #[test]
+    fn audit_message_round_trips() {
+        let records = [
+            AuditRecord::new("Alice", "kick")
+                .with_timestamp(at(1_100_000_000))
+                .with_channel("%#Lobby")
+                .with_target("Bob")
+                .with_reason("no flooding : please"),
+            AuditRecord::new("Alice", "topic")
+                .with_timestamp(at(0))
+                .with_channel("%#Lobby")
+                .with_reason(""),
+            AuditRecord::new("*!*@staff", "kill").with_timestamp(at(42)),
+        ];
+        for record in records {
+            record.validate().unwrap();
+            let message = record.to_string();
+            assert_eq!(message.parse(), Ok(record), "{message:?}");
+        }
+        assert_eq!(
+            AuditRecord::new("Alice", "ban")
+                .with_timestamp(at(7))
+                .with_target("*!*@bad")
+                .to_string(),
+            "7 ban Alice - *!*@bad"
+        );
+    }
+
+    #[test]
+    fn rejects_bad_messages() {
+        assert_eq!(
+            AuditRecord::parse("1 kick Alice"),
+            Err(AuditError::Truncated)
+        );
+        assert_eq!(
+            AuditRecord::parse("soon kick Alice -"),
+            Err(AuditError::BadTimestamp("soon".into()))
+        );
+        assert_eq!(
+            AuditRecord::parse("1 kick Alice %#Lobby Bob Carol"),
+            Err(AuditError::BadField("Bob Carol".into()))
+        );
+        assert_eq!(
+            AuditRecord::new("Alice", "kick")
+                .with_target("Bob Carol")
+                .validate(),
+            Err(AuditError::BadField("Bob Carol".into()))
+        );
+        assert_eq!(
+            AuditRecord::new("Alice", "kick")
+                .with_channel("-")
+                .validate(),
+            Err(AuditError::BadField("-".into()))
+        );
+    }
+
+    #[test]
+    fn json_escaping() {
+        let record = AuditRecord::new("Al\"ice", "prop")
+            .with_timestamp(at(5))
+            .with_channel("%#Back\\slash")
+            .with_reason("line\nbreak\r\ttab\x01é");
+        assert_eq!(
+            record.to_json(),
+            r#"{"timestamp":5,"actor":"Al\"ice","action":"prop","channel":"%#Back\\slash","target":null,"reason":"line\nbreak\r\ttab\u0001é"}"#
+        );
+    }
+
+    #[test]
+    fn audits_channel_events_only() {
+        let event = |command| SessionEvent::Message {
+            source: Some("Alice!~a@host".to_owned()),
+            command,
+        };
+        let record = AuditRecord::from_event(&event(Command::Mode {
+            target: "%#Lobby".into(),
+            modes: Some("+o".into()),
+            args: vec!["Bob".into()],
+        }))
+        .unwrap();
+        assert_eq!(record.actor, "Alice");
+        assert_eq!(record.channel.as_deref(), Some("%#Lobby"));
+        assert_eq!(record.target.as_deref(), Some("Bob"));
+        assert_eq!(record.reason.as_deref(), Some("+o"));
+
+        let user_mode = event(Command::Mode {
+            target: "Alice".into(),
+            modes: Some("+i".into()),
+            args: Vec::new(),
+        });
+        assert_eq!(AuditRecord::from_event(&user_mode), None);
+    }
+}
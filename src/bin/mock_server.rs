@@ -0,0 +1,16 @@
+//! Runs the mock IRCX server until killed.
+//!
+//! Usage: `msnchat-mock-server [ADDR]` (default `127.0.0.1:6667`).
+
+use msnchat_bindings::mock::MockServer;
+
+fn main() -> std::io::Result<()> {
+    let addr = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "127.0.0.1:6667".to_owned());
+
+    let server = MockServer::builder().bind(addr.as_str())?.spawn()?;
+    println!("mock IRCX server listening on {}", server.address());
+    server.wait();
+    Ok(())
+}
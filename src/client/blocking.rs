@@ -0,0 +1,156 @@
+//! Blocking transport over `std::net::TcpStream`, for tools that don't want an async runtime.
+
+use std::{
+    io::{self, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    time::Instant,
+};
+
+use super::{ChatEvent, ClientOptions, Timers, is_keepalive_pong};
+use crate::{
+    error::Result,
+    ircx::{Command, Session, SessionConfig, SessionEvent},
+};
+
+/// A connected session driven on the calling thread.
+///
+/// Nothing is read or sent until [`Client::next_event`] (or the iterator from
+/// [`Client::events`]) is polled; commands are written immediately.
+#[derive(Debug)]
+pub struct Client {
+    session: Session,
+    stream: TcpStream,
+    timers: Timers,
+    buf: Vec<u8>,
+    done: bool,
+}
+
+impl Client {
+    /// Connects with default [`ClientOptions`].
+    pub fn connect(config: SessionConfig) -> Result<Self> {
+        Self::connect_with(config, ClientOptions::default())
+    }
+
+    /// Connects to `config.server` and sends the opening `IRCVERS`.
+    pub fn connect_with(config: SessionConfig, options: ClientOptions) -> Result<Self> {
+        let stream = connect_timeout(&config.server, &options)?;
+        stream.set_nodelay(true)?;
+
+        let mut client = Self {
+            session: Session::new(config),
+            stream,
+            timers: Timers::new(options, Instant::now()),
+            buf: vec![0u8; 4096],
+            done: false,
+        };
+        client.session.connect();
+        client.flush()?;
+        Ok(client)
+    }
+
+    pub fn session(&self) -> &Session {
+        &self.session
+    }
+
+    pub fn send(&mut self, command: &Command) -> Result<()> {
+        self.session.send(command)?;
+        Ok(self.flush()?)
+    }
+
+    /// Sends `QUIT`; keep polling events to see the server close the connection.
+    pub fn quit(&mut self, reason: Option<&str>) -> Result<()> {
+        self.timers.quit(&mut self.session, reason, Instant::now());
+        Ok(self.flush()?)
+    }
+
+    /// Blocks until the next event; `None` once the connection is finished.
+    pub fn next_event(&mut self) -> Option<ChatEvent> {
+        match self.poll() {
+            Ok(Some(event)) => {
+                self.done = matches!(event, SessionEvent::Closed { .. });
+                Some(event.into())
+            }
+            Ok(None) => None,
+            Err(err) => {
+                self.done = true;
+                Some(ChatEvent::Error(err))
+            }
+        }
+    }
+
+    /// Iterates over events until the connection closes.
+    pub fn events(&mut self) -> Events<'_> {
+        Events { client: self }
+    }
+
+    fn poll(&mut self) -> Result<Option<SessionEvent>> {
+        loop {
+            if self.done {
+                return Ok(None);
+            }
+            self.flush()?;
+            if let Some(event) = self.session.poll_event() {
+                if is_keepalive_pong(&event) {
+                    continue;
+                }
+                return Ok(Some(event));
+            }
+
+            let now = Instant::now();
+            let deadline = self.timers.next_deadline(&self.session);
+            if now >= deadline {
+                self.timers.expire(&mut self.session, now)?;
+                continue;
+            }
+
+            self.stream.set_read_timeout(Some(deadline - now))?;
+            match self.stream.read(&mut self.buf) {
+                Ok(0) => self.session.disconnected(None),
+                Ok(n) => {
+                    self.timers.received(Instant::now());
+                    self.session.feed(&self.buf[..n]);
+                }
+                Err(err)
+                    if matches!(
+                        err.kind(),
+                        io::ErrorKind::WouldBlock
+                            | io::ErrorKind::TimedOut
+                            | io::ErrorKind::Interrupted
+                    ) => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        while let Some(line) = self.session.poll_transmit() {
+            self.stream.write_all(&line)?;
+        }
+        Ok(())
+    }
+}
+
+/// Iterator returned by [`Client::events`].
+#[derive(Debug)]
+pub struct Events<'a> {
+    client: &'a mut Client,
+}
+
+impl Iterator for Events<'_> {
+    type Item = ChatEvent;
+
+    fn next(&mut self) -> Option<ChatEvent> {
+        self.client.next_event()
+    }
+}
+
+fn connect_timeout(server: &str, options: &ClientOptions) -> io::Result<TcpStream> {
+    let mut last_err = io::Error::new(io::ErrorKind::AddrNotAvailable, "no address for server");
+    for addr in server.to_socket_addrs()? {
+        match TcpStream::connect_timeout(&addr, options.connect_timeout) {
+            Ok(stream) => return Ok(stream),
+            Err(err) => last_err = err,
+        }
+    }
+    Err(last_err)
+}
@@ -0,0 +1,150 @@
+//! Socket transports for [`ircx::Session`](crate::ircx::Session).
+
+use std::{
+    io,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    Error,
+    error::Result,
+    ircx::{Command, Session, SessionEvent, SessionState},
+};
+
+pub mod blocking;
+#[cfg(feature = "tokio")]
+pub mod tokio;
+
+#[cfg(feature = "tokio")]
+pub use self::tokio::{Handle, connect, connect_with};
+pub use blocking::Client;
+
+/// Timeouts and keepalive settings shared by the transports.
+#[derive(Debug, Clone)]
+pub struct ClientOptions {
+    pub connect_timeout: Duration,
+    /// Time allowed from TCP connect until registration completes.
+    pub handshake_timeout: Duration,
+    /// Idle time after which we send our own `PING`.
+    pub ping_interval: Duration,
+    /// How long to wait for any data after a keepalive `PING`.
+    pub ping_timeout: Duration,
+    /// How long to wait for the server to close after `QUIT`.
+    pub quit_timeout: Duration,
+}
+
+impl Default for ClientOptions {
+    fn default() -> Self {
+        Self {
+            connect_timeout: Duration::from_secs(30),
+            handshake_timeout: Duration::from_secs(60),
+            ping_interval: Duration::from_secs(90),
+            ping_timeout: Duration::from_secs(60),
+            quit_timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+/// What a transport reports to the application.
+#[derive(Debug)]
+pub enum ChatEvent {
+    Session(SessionEvent),
+    /// The transport failed; no further events follow.
+    Error(Error),
+}
+
+impl From<SessionEvent> for ChatEvent {
+    fn from(event: SessionEvent) -> Self {
+        ChatEvent::Session(event)
+    }
+}
+
+/// Token used for keepalive `PING`s so their `PONG`s can be told apart.
+const KEEPALIVE_TOKEN: &str = "keepalive";
+
+/// Our keepalive replies are transport bookkeeping, not application events.
+pub(crate) fn is_keepalive_pong(event: &SessionEvent) -> bool {
+    matches!(
+        event,
+        SessionEvent::Message { command: Command::Pong(token), .. } if token == KEEPALIVE_TOKEN
+    )
+}
+
+/// Handshake, keepalive and quit deadlines, shared by both transports.
+#[derive(Debug)]
+pub(crate) struct Timers {
+    options: ClientOptions,
+    handshake_deadline: Instant,
+    last_received: Instant,
+    ping_sent: Option<Instant>,
+    quit_deadline: Option<Instant>,
+}
+
+impl Timers {
+    pub fn new(options: ClientOptions, now: Instant) -> Self {
+        Self {
+            handshake_deadline: now + options.handshake_timeout,
+            last_received: now,
+            ping_sent: None,
+            quit_deadline: None,
+            options,
+        }
+    }
+
+    pub fn received(&mut self, now: Instant) {
+        self.last_received = now;
+        self.ping_sent = None;
+    }
+
+    /// Queues `QUIT` unless one is already pending.
+    pub fn quit(&mut self, session: &mut Session, reason: Option<&str>, now: Instant) {
+        if self.quit_deadline.is_none() {
+            session.quit(reason);
+            self.quit_deadline = Some(now + self.options.quit_timeout);
+        }
+    }
+
+    pub fn next_deadline(&self, session: &Session) -> Instant {
+        let ping = match self.ping_sent {
+            Some(sent) => sent + self.options.ping_timeout,
+            None => self.last_received + self.options.ping_interval,
+        };
+        [
+            Some(ping),
+            self.quit_deadline,
+            (!registered(session)).then_some(self.handshake_deadline),
+        ]
+        .into_iter()
+        .flatten()
+        .min()
+        .unwrap_or(ping)
+    }
+
+    /// Fires whichever deadline has passed; an error ends the connection.
+    pub fn expire(&mut self, session: &mut Session, now: Instant) -> Result<()> {
+        if self.quit_deadline.is_some_and(|deadline| now >= deadline) {
+            session.disconnected(None);
+        } else if !registered(session) && now >= self.handshake_deadline {
+            return Err(timed_out("handshake timed out").into());
+        } else if let Some(sent) = self.ping_sent {
+            if now >= sent + self.options.ping_timeout {
+                return Err(timed_out("ping timeout").into());
+            }
+        } else if now >= self.last_received + self.options.ping_interval {
+            session.send(&Command::Ping(KEEPALIVE_TOKEN.to_owned()))?;
+            self.ping_sent = Some(now);
+        }
+        Ok(())
+    }
+}
+
+fn registered(session: &Session) -> bool {
+    matches!(
+        session.state(),
+        SessionState::Registered | SessionState::Joining | SessionState::Joined
+    )
+}
+
+pub(crate) fn timed_out(what: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::TimedOut, what)
+}
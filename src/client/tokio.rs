@@ -0,0 +1,186 @@
+//! Async transport: drives a [`Session`] over a tokio `TcpStream` on a spawned task.
+
+use std::io;
+
+use ::tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{
+        TcpStream,
+        tcp::{OwnedReadHalf, OwnedWriteHalf},
+    },
+    select,
+    sync::mpsc,
+    time::{Instant, sleep_until, timeout},
+};
+use tokio_stream::{Stream, wrappers::ReceiverStream};
+
+use super::{ChatEvent, ClientOptions, Timers, is_keepalive_pong, timed_out};
+use crate::{
+    Error,
+    error::Result,
+    ircx::{Command, Session, SessionConfig, SessionError},
+};
+
+/// Events buffered before the driver waits for the application to catch up.
+const EVENT_BUFFER: usize = 64;
+
+enum Request {
+    Send(Command),
+    Quit(Option<String>),
+}
+
+/// Sends commands to a connection started by [`connect`].
+///
+/// Dropping every handle quits the connection.
+#[derive(Debug, Clone)]
+pub struct Handle {
+    requests: mpsc::UnboundedSender<Request>,
+}
+
+impl Handle {
+    /// Queues `command`; fails if it cannot be encoded or the connection is gone.
+    pub fn send(&self, command: Command) -> Result<()> {
+        command.to_line()?;
+        self.request(Request::Send(command))
+    }
+
+    /// Sends `QUIT` and closes once the server hangs up or `quit_timeout` passes.
+    pub fn quit(&self, reason: Option<&str>) -> Result<()> {
+        self.request(Request::Quit(reason.map(str::to_owned)))
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.requests.is_closed()
+    }
+
+    fn request(&self, request: Request) -> Result<()> {
+        self.requests
+            .send(request)
+            .map_err(|_| SessionError::Closed(None).into())
+    }
+}
+
+/// Connects with default [`ClientOptions`].
+pub async fn connect(config: SessionConfig) -> Result<(Handle, impl Stream<Item = ChatEvent>)> {
+    connect_with(config, ClientOptions::default()).await
+}
+
+/// Connects to `config.server` and starts the handshake on a background task.
+///
+/// The stream ends after [`SessionEvent::Closed`] or [`ChatEvent::Error`].
+pub async fn connect_with(
+    config: SessionConfig,
+    options: ClientOptions,
+) -> Result<(Handle, impl Stream<Item = ChatEvent>)> {
+    let stream = timeout(
+        options.connect_timeout,
+        TcpStream::connect(config.server.as_str()),
+    )
+    .await
+    .map_err(|_| timed_out("connect timed out"))??;
+    stream.set_nodelay(true)?;
+
+    let (reader, writer) = stream.into_split();
+    let (requests, request_rx) = mpsc::unbounded_channel();
+    let (event_tx, events) = mpsc::channel(EVENT_BUFFER);
+
+    let driver = Driver {
+        session: Session::new(config),
+        reader,
+        writer,
+        requests: Some(request_rx),
+        events: event_tx,
+        timers: Timers::new(options, std::time::Instant::now()),
+    };
+    ::tokio::spawn(driver.run());
+
+    Ok((Handle { requests }, ReceiverStream::new(events)))
+}
+
+struct Driver {
+    session: Session,
+    reader: OwnedReadHalf,
+    writer: OwnedWriteHalf,
+    /// `None` once every [`Handle`] is gone.
+    requests: Option<mpsc::UnboundedReceiver<Request>>,
+    events: mpsc::Sender<ChatEvent>,
+    timers: Timers,
+}
+
+impl Driver {
+    async fn run(mut self) {
+        let mut buf = vec![0u8; 4096];
+        self.session.connect();
+
+        loop {
+            if let Err(err) = self.flush().await {
+                return self.fail(err.into()).await;
+            }
+            if !self.forward_events().await || self.session.is_closed() {
+                return;
+            }
+
+            let deadline = Instant::from_std(self.timers.next_deadline(&self.session));
+            select! {
+                read = self.reader.read(&mut buf) => match read {
+                    Ok(0) => self.session.disconnected(None),
+                    Ok(n) => {
+                        self.timers.received(std::time::Instant::now());
+                        self.session.feed(&buf[..n]);
+                    }
+                    Err(err) => return self.fail(err.into()).await,
+                },
+                request = recv(&mut self.requests) => match request {
+                    // Only fails once the session is closed, which ends the loop anyway.
+                    Some(Request::Send(command)) => { let _ = self.session.send(&command); }
+                    Some(Request::Quit(reason)) => self.quit(reason.as_deref()),
+                    None => {
+                        self.requests = None;
+                        self.quit(None);
+                    }
+                },
+                _ = sleep_until(deadline) => {
+                    if let Err(err) = self.timers.expire(&mut self.session, std::time::Instant::now()) {
+                        return self.fail(err).await;
+                    }
+                }
+            }
+        }
+    }
+
+    async fn flush(&mut self) -> io::Result<()> {
+        while let Some(line) = self.session.poll_transmit() {
+            self.writer.write_all(&line).await?;
+        }
+        Ok(())
+    }
+
+    /// Returns `false` once the application has dropped the event stream.
+    async fn forward_events(&mut self) -> bool {
+        while let Some(event) = self.session.poll_event() {
+            if is_keepalive_pong(&event) {
+                continue;
+            }
+            if self.events.send(event.into()).await.is_err() {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn quit(&mut self, reason: Option<&str>) {
+        self.timers
+            .quit(&mut self.session, reason, std::time::Instant::now());
+    }
+
+    async fn fail(&mut self, err: Error) {
+        let _ = self.events.send(ChatEvent::Error(err)).await;
+    }
+}
+
+async fn recv(requests: &mut Option<mpsc::UnboundedReceiver<Request>>) -> Option<Request> {
+    match requests {
+        Some(rx) => rx.recv().await,
+        None => std::future::pending().await,
+    }
+}
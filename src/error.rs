@@ -0,0 +1,102 @@
+use std::fmt;
+
+use crate::ircx::{
+    CommandError, EncodeError, Message, ParseError, SessionError, numeric::ServerError,
+};
+
+/// Errors raised by the protocol layer of this crate.
+#[derive(Debug)]
+pub enum Error {
+    /// A COM call on the control failed.
+    #[cfg(windows)]
+    Com(windows::core::Error),
+    Io(std::io::Error),
+    Parse(ParseError),
+    Command(CommandError),
+    Encode(EncodeError),
+    /// The server answered with an error numeric.
+    Server(ServerError),
+    Session(SessionError),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    /// Maps an error numeric onto [`Error::Server`]; `None` for any other message.
+    pub fn from_reply(msg: &Message<'_>) -> Option<Self> {
+        ServerError::from_message(msg).map(Error::Server)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(windows)]
+            Error::Com(err) => write!(f, "COM error: {err}"),
+            Error::Io(err) => write!(f, "I/O error: {err}"),
+            Error::Parse(err) => write!(f, "protocol error: {err}"),
+            Error::Command(err) => write!(f, "protocol error: {err}"),
+            Error::Encode(err) => write!(f, "cannot encode message: {err}"),
+            Error::Server(err) => write!(f, "server error: {err}"),
+            Error::Session(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            #[cfg(windows)]
+            Error::Com(err) => Some(err),
+            Error::Io(err) => Some(err),
+            Error::Parse(err) => Some(err),
+            Error::Command(err) => Some(err),
+            Error::Encode(err) => Some(err),
+            Error::Server(err) => Some(err),
+            Error::Session(err) => Some(err),
+        }
+    }
+}
+
+#[cfg(windows)]
+impl From<windows::core::Error> for Error {
+    fn from(err: windows::core::Error) -> Self {
+        Error::Com(err)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<ParseError> for Error {
+    fn from(err: ParseError) -> Self {
+        Error::Parse(err)
+    }
+}
+
+impl From<CommandError> for Error {
+    fn from(err: CommandError) -> Self {
+        Error::Command(err)
+    }
+}
+
+impl From<EncodeError> for Error {
+    fn from(err: EncodeError) -> Self {
+        Error::Encode(err)
+    }
+}
+
+impl From<ServerError> for Error {
+    fn from(err: ServerError) -> Self {
+        Error::Server(err)
+    }
+}
+
+impl From<SessionError> for Error {
+    fn from(err: SessionError) -> Self {
+        Error::Session(err)
+    }
+}
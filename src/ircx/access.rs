@@ -0,0 +1,597 @@
+use std::{
+    fmt,
+    str::FromStr,
+    time::{Duration, SystemTime},
+};
+
+use super::{Command, Numeric, SessionEvent};
+
+/// An `ACCESS` entry level, ordered from least to most privileged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum AccessLevel {
+    /// Banned.
+    Deny,
+    /// Exempt from `DENY` entries and `+i`.
+    Grant,
+    Voice,
+    Host,
+    Owner,
+}
+
+impl AccessLevel {
+    pub const ALL: [AccessLevel; 5] = [
+        AccessLevel::Deny,
+        AccessLevel::Grant,
+        AccessLevel::Voice,
+        AccessLevel::Host,
+        AccessLevel::Owner,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AccessLevel::Deny => "DENY",
+            AccessLevel::Grant => "GRANT",
+            AccessLevel::Voice => "VOICE",
+            AccessLevel::Host => "HOST",
+            AccessLevel::Owner => "OWNER",
+        }
+    }
+}
+
+impl fmt::Display for AccessLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for AccessLevel {
+    type Err = AccessError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|l| l.as_str().eq_ignore_ascii_case(s))
+            .ok_or_else(|| AccessError::UnknownLevel(s.to_string()))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AccessError {
+    UnknownLevel(String),
+    /// A mask that is empty or contains spaces or commas.
+    InvalidMask(String),
+}
+
+impl fmt::Display for AccessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AccessError::UnknownLevel(s) => write!(f, "unknown access level `{s}`"),
+            AccessError::InvalidMask(s) => write!(f, "invalid access mask `{s}`"),
+        }
+    }
+}
+
+impl std::error::Error for AccessError {}
+
+/// Expands a partial mask to the full `nick!user@host$server` form.
+///
+/// `Nick` becomes `Nick!*@*$*` and `user@host` becomes `*!user@host$*`.
+pub fn normalize_mask(mask: &str) -> String {
+    let (rest, server) = mask.split_once('$').unwrap_or((mask, "*"));
+    let (rest, host) = match rest.split_once('@') {
+        Some((rest, host)) => (rest, host),
+        None => (rest, "*"),
+    };
+    let (nick, user) = match rest.split_once('!') {
+        Some((nick, user)) => (nick, user),
+        None if mask.contains('@') => ("*", rest),
+        None => (rest, "*"),
+    };
+    let or_any = |s: &str| {
+        if s.is_empty() {
+            "*".to_owned()
+        } else {
+            s.to_owned()
+        }
+    };
+    format!(
+        "{}!{}@{}${}",
+        or_any(nick),
+        or_any(user),
+        or_any(host),
+        or_any(server)
+    )
+}
+
+/// Whether `mask` matches `identity` (`nick!user@host`, optionally `$server`).
+///
+/// Masks may be partial (see [`normalize_mask`]); `*` and `?` are wildcards
+/// and case is ignored. Without a `$server` the server part is not compared.
+pub fn mask_matches(mask: &str, identity: &str) -> bool {
+    let mask = normalize_mask(mask);
+    if identity.contains('$') {
+        return wildcard_match(&mask, identity);
+    }
+    let mask = mask.rsplit_once('$').map_or(mask.as_str(), |(m, _)| m);
+    wildcard_match(mask, identity)
+}
+
+/// Glob match with `*` and `?`, ignoring case.
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((bp, bt)) => {
+                    p = bp + 1;
+                    t = bt + 1;
+                    backtrack = Some((bp, bt + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// One `ACCESS` entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccessEntry {
+    pub level: AccessLevel,
+    pub mask: String,
+    /// Lifetime in whole minutes; `None` for a permanent entry.
+    pub timeout: Option<Duration>,
+    pub creator: Option<String>,
+    pub reason: Option<String>,
+    /// When the timeout started counting: when the entry was added or listed.
+    pub since: SystemTime,
+}
+
+impl AccessEntry {
+    pub fn new(level: AccessLevel, mask: impl Into<String>) -> Self {
+        Self {
+            level,
+            mask: mask.into(),
+            timeout: None,
+            creator: None,
+            reason: None,
+            since: SystemTime::now(),
+        }
+    }
+
+    /// Rounded up to whole minutes, the protocol's unit.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        let minutes = timeout.as_secs().div_ceil(60);
+        self.timeout = (minutes > 0).then(|| Duration::from_secs(minutes * 60));
+        self
+    }
+
+    pub fn with_reason(mut self, reason: impl Into<String>) -> Self {
+        self.reason = Some(reason.into());
+        self
+    }
+
+    /// Timeout in minutes as sent on the wire; 0 is permanent.
+    pub fn timeout_minutes(&self) -> u64 {
+        self.timeout.map_or(0, |t| t.as_secs() / 60)
+    }
+
+    pub fn expires_at(&self) -> Option<SystemTime> {
+        self.timeout.map(|t| self.since + t)
+    }
+
+    pub fn is_expired(&self, now: SystemTime) -> bool {
+        self.expires_at().is_some_and(|at| at <= now)
+    }
+
+    pub fn matches(&self, identity: &str) -> bool {
+        mask_matches(&self.mask, identity)
+    }
+
+    /// `ACCESS <target> ADD <level> <mask> <timeout> :<reason>`.
+    pub fn add_command(&self, target: impl Into<String>) -> Result<Command, AccessError> {
+        validate_mask(&self.mask)?;
+        let mut args = vec![
+            self.level.as_str().to_owned(),
+            self.mask.clone(),
+            self.timeout_minutes().to_string(),
+        ];
+        args.extend(self.reason.clone());
+        Ok(access(target, "ADD", args))
+    }
+
+    /// Parses the parameters of an 801 or 804 reply after our own nick:
+    /// `<target> <level> <mask> [<timeout> [<creator> [:<reason>]]]`.
+    fn from_reply(params: &[String]) -> Option<Self> {
+        let [_, level, mask, rest @ ..] = params else {
+            return None;
+        };
+        let minutes: u64 = rest.first().map_or(Some(0), |t| t.parse().ok())?;
+        let non_empty = |s: Option<&String>| s.filter(|s| !s.is_empty()).cloned();
+        Some(Self {
+            level: level.parse().ok()?,
+            mask: mask.clone(),
+            timeout: (minutes > 0).then(|| Duration::from_secs(minutes * 60)),
+            creator: non_empty(rest.get(1)),
+            reason: non_empty(rest.get(2)),
+            since: SystemTime::now(),
+        })
+    }
+}
+
+fn validate_mask(mask: &str) -> Result<(), AccessError> {
+    if mask.is_empty() || mask.contains([' ', ',']) {
+        return Err(AccessError::InvalidMask(mask.to_owned()));
+    }
+    Ok(())
+}
+
+fn access(target: impl Into<String>, operation: &str, args: Vec<String>) -> Command {
+    Command::Access {
+        target: target.into(),
+        operation: Some(operation.to_owned()),
+        args,
+    }
+}
+
+/// `ACCESS <target> DELETE <level> <mask>`.
+pub fn delete_command(
+    target: impl Into<String>,
+    level: AccessLevel,
+    mask: &str,
+) -> Result<Command, AccessError> {
+    validate_mask(mask)?;
+    Ok(access(
+        target,
+        "DELETE",
+        vec![level.as_str().to_owned(), mask.to_owned()],
+    ))
+}
+
+/// `ACCESS <target> LIST`.
+pub fn list_command(target: impl Into<String>) -> Command {
+    access(target, "LIST", Vec::new())
+}
+
+/// `ACCESS <target> CLEAR [<level>]`; without a level every entry goes.
+pub fn clear_command(target: impl Into<String>, level: Option<AccessLevel>) -> Command {
+    let args = level.map(|l| l.as_str().to_owned()).into_iter().collect();
+    access(target, "CLEAR", args)
+}
+
+/// The access entries of one channel, kept current from `ACCESS` replies.
+#[derive(Debug, Clone)]
+pub struct AccessList {
+    target: String,
+    entries: Vec<AccessEntry>,
+    /// Entries received since the last 803.
+    pending: Option<Vec<AccessEntry>>,
+}
+
+impl AccessList {
+    pub fn new(target: impl Into<String>) -> Self {
+        Self {
+            target: target.into(),
+            entries: Vec::new(),
+            pending: None,
+        }
+    }
+
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+
+    pub fn entries(&self) -> &[AccessEntry] {
+        &self.entries
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The highest unexpired level whose mask matches `identity`.
+    ///
+    /// A matching `GRANT` or better overrides `DENY`, so this is the level to act on.
+    pub fn level_for(&self, identity: &str, now: SystemTime) -> Option<AccessLevel> {
+        self.entries
+            .iter()
+            .filter(|e| !e.is_expired(now) && e.matches(identity))
+            .map(|e| e.level)
+            .max()
+    }
+
+    pub fn is_denied(&self, identity: &str, now: SystemTime) -> bool {
+        self.level_for(identity, now) == Some(AccessLevel::Deny)
+    }
+
+    /// Drops expired entries and returns them.
+    pub fn prune_expired(&mut self, now: SystemTime) -> Vec<AccessEntry> {
+        let (expired, kept) = self.entries.drain(..).partition(|e| e.is_expired(now));
+        self.entries = kept;
+        expired
+    }
+
+    /// The next time an entry expires, for scheduling [`AccessList::prune_expired`].
+    pub fn next_expiry(&self) -> Option<SystemTime> {
+        self.entries
+            .iter()
+            .filter_map(AccessEntry::expires_at)
+            .min()
+    }
+
+    pub fn handle_event(&mut self, event: &SessionEvent) -> bool {
+        match event {
+            SessionEvent::Message { command, .. } => self.handle(command),
+            _ => false,
+        }
+    }
+
+    /// Applies an `ACCESS` reply (801–805, 820); returns true if the entries changed.
+    pub fn handle(&mut self, command: &Command) -> bool {
+        let Command::Other { command, params } = command else {
+            return false;
+        };
+        let Ok(code) = command.parse() else {
+            return false;
+        };
+        if !params
+            .get(1)
+            .is_some_and(|t| t.eq_ignore_ascii_case(&self.target))
+        {
+            return false;
+        }
+        let params = &params[1..];
+
+        match Numeric::from_code(code) {
+            Numeric::AccessStart => {
+                self.pending = Some(Vec::new());
+                false
+            }
+            Numeric::AccessList => {
+                if let Some(entry) = AccessEntry::from_reply(params) {
+                    self.pending.get_or_insert_with(Vec::new).push(entry);
+                }
+                false
+            }
+            Numeric::AccessEnd => {
+                self.entries = self.pending.take().unwrap_or_default();
+                true
+            }
+            Numeric::AccessAdd => match AccessEntry::from_reply(params) {
+                Some(entry) => {
+                    self.remove(entry.level, &entry.mask);
+                    self.entries.push(entry);
+                    true
+                }
+                None => false,
+            },
+            Numeric::AccessDelete => match (params.get(1), params.get(2)) {
+                (Some(level), Some(mask)) => {
+                    level.parse().is_ok_and(|level| self.remove(level, mask))
+                }
+                _ => false,
+            },
+            Numeric::AccessClear => {
+                let level = params.get(1).and_then(|l| l.parse::<AccessLevel>().ok());
+                let before = self.entries.len();
+                self.entries.retain(|e| level.is_some_and(|l| l != e.level));
+                self.entries.len() != before
+            }
+            _ => false,
+        }
+    }
+
+    fn remove(&mut self, level: AccessLevel, mask: &str) -> bool {
+        let before = self.entries.len();
+        self.entries
+            .retain(|e| !(e.level == level && e.mask.eq_ignore_ascii_case(mask)));
+        self.entries.len() != before
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALICE: &str = "Alice!~alice@home.example.com";
+
+    fn reply(code: &str, params: &[&str]) -> Command {
+        Command::Other {
+            command: code.to_owned(),
+            params: std::iter::once("Me")
+                .chain(params.iter().copied())
+                .map(str::to_owned)
+                .collect(),
+        }
+    }
+
+    fn entry(level: AccessLevel, mask: &str, since: SystemTime, minutes: u64) -> AccessEntry {
+        AccessEntry {
+            since,
+            ..AccessEntry::new(level, mask).with_timeout(Duration::from_secs(minutes * 60))
+        }
+    }
+
+    #[test]
+    fn normalizes_partial_masks() {
+        assert_eq!(normalize_mask("Alice"), "Alice!*@*$*");
+        assert_eq!(normalize_mask("~alice@home"), "*!~alice@home$*");
+        assert_eq!(normalize_mask("@home"), "*!*@home$*");
+        assert_eq!(normalize_mask("Alice!~alice"), "Alice!~alice@*$*");
+        assert_eq!(
+            normalize_mask("*!*@*.example.com$TK2"),
+            "*!*@*.example.com$TK2"
+        );
+        assert_eq!(normalize_mask("Alice$TK2"), "Alice!*@*$TK2");
+        assert_eq!(normalize_mask(""), "*!*@*$*");
+    }
+
+    #[test]
+    fn matches_partial_masks() {
+        assert!(mask_matches("alice", ALICE));
+        assert!(mask_matches("*@*.EXAMPLE.com", ALICE));
+        assert!(mask_matches("Al?ce!*", ALICE));
+        assert!(mask_matches("*!~alice@*", ALICE));
+        assert!(!mask_matches("Bob", ALICE));
+        assert!(!mask_matches("Alic", ALICE));
+        assert!(!mask_matches("*@*.example.org", ALICE));
+    }
+
+    #[test]
+    fn server_part_is_compared_only_when_known() {
+        let on_server = format!("{ALICE}$TK2CHATCHATA01");
+        assert!(mask_matches("Alice$TK2CHATCHATA02", ALICE));
+        assert!(mask_matches("Alice$TK2*", &on_server));
+        assert!(mask_matches("Alice", &on_server));
+        assert!(!mask_matches("Alice$TK2CHATCHATA02", &on_server));
+    }
+
+    #[test]
+    fn wildcards() {
+        assert!(wildcard_match("*", ""));
+        assert!(wildcard_match("a*b*c", "aXXbYYc"));
+        assert!(wildcard_match("a*c", "abcbc"));
+        assert!(wildcard_match("??", "ÄB"));
+        assert!(!wildcard_match("a*b", "aXXc"));
+        assert!(!wildcard_match("?", ""));
+    }
+
+    #[test]
+    fn grant_overrides_deny() {
+        let now = SystemTime::now();
+        let mut list = AccessList::new("%#Lobby");
+        list.entries = vec![
+            AccessEntry::new(AccessLevel::Deny, "*@*.example.com"),
+            AccessEntry::new(AccessLevel::Grant, "Alice"),
+        ];
+        assert_eq!(list.level_for(ALICE, now), Some(AccessLevel::Grant));
+        assert!(!list.is_denied(ALICE, now));
+        assert!(list.is_denied("Bob!~bob@work.example.com", now));
+        assert_eq!(list.level_for("Carol!~c@elsewhere", now), None);
+
+        // An expired GRANT no longer shields the DENY.
+        list.entries[1] = entry(AccessLevel::Grant, "Alice", now, 5);
+        assert!(list.is_denied(ALICE, now + Duration::from_secs(300)));
+    }
+
+    #[test]
+    fn expiry() {
+        let now = SystemTime::now();
+        assert_eq!(
+            AccessEntry::new(AccessLevel::Deny, "x")
+                .with_timeout(Duration::from_secs(61))
+                .timeout_minutes(),
+            2
+        );
+        assert_eq!(
+            AccessEntry::new(AccessLevel::Deny, "x")
+                .with_timeout(Duration::ZERO)
+                .timeout,
+            None
+        );
+
+        let mut list = AccessList::new("%#Lobby");
+        assert_eq!(list.next_expiry(), None);
+        list.entries = vec![
+            entry(AccessLevel::Deny, "a", now, 10),
+            AccessEntry::new(AccessLevel::Host, "b"),
+            entry(AccessLevel::Voice, "c", now, 1),
+        ];
+        assert_eq!(list.next_expiry(), Some(now + Duration::from_secs(60)));
+        assert!(list.prune_expired(now).is_empty());
+
+        let expired = list.prune_expired(now + Duration::from_secs(60));
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].mask, "c");
+        assert_eq!(list.next_expiry(), Some(now + Duration::from_secs(600)));
+
+        let expired = list.prune_expired(now + Duration::from_secs(86_400));
+        assert_eq!(expired[0].mask, "a");
+        assert_eq!(list.len(), 1);
+        assert_eq!(list.next_expiry(), None);
+    }
+
+    #[test]
+    fn listing_replaces_entries() {
+        let mut list = AccessList::new("%#Lobby");
+        list.handle(&reply("801", &["%#Lobby", "OWNER", "Old!*@*$*", "0", "Me"]));
+        assert!(!list.handle(&reply("803", &["%#lobby", "Start of access entries"])));
+        assert!(!list.handle(&reply(
+            "804",
+            &["%#Lobby", "DENY", "*!*@bad$*", "15", "Alice", "spam"]
+        )));
+        assert!(!list.handle(&reply("804", &["%#Other", "HOST", "Mallory!*@*$*"])));
+        assert!(!list.handle(&reply("804", &["%#Lobby", "VOICE", "Bob!*@*$*"])));
+        assert_eq!(list.len(), 1);
+        assert!(list.handle(&reply("805", &["%#Lobby", "End of access entries"])));
+
+        let masks: Vec<_> = list.entries().iter().map(|e| e.mask.as_str()).collect();
+        assert_eq!(masks, ["*!*@bad$*", "Bob!*@*$*"]);
+        let deny = &list.entries()[0];
+        assert_eq!(deny.timeout_minutes(), 15);
+        assert_eq!(deny.creator.as_deref(), Some("Alice"));
+        assert_eq!(deny.reason.as_deref(), Some("spam"));
+        assert_eq!(list.entries()[1].timeout, None);
+    }
+
+    #[test]
+    fn add_delete_and_clear() {
+        let mut list = AccessList::new("%#Lobby");
+        assert!(list.handle(&reply(
+            "801",
+            &["%#Lobby", "DENY", "A!*@*$*", "0", "Me", ""]
+        )));
+        assert!(list.handle(&reply("801", &["%#Lobby", "HOST", "B!*@*$*", "0", "Me"])));
+        // Re-adding the same level and mask replaces the entry.
+        assert!(list.handle(&reply("801", &["%#Lobby", "DENY", "a!*@*$*", "30", "Me"])));
+        assert_eq!(list.len(), 2);
+        assert_eq!(list.entries()[1].timeout_minutes(), 30);
+        assert!(!list.handle(&reply("801", &["%#Lobby", "NOPE", "C", "0"])));
+
+        assert!(!list.handle(&reply("802", &["%#Lobby", "VOICE", "B!*@*$*"])));
+        assert!(list.handle(&reply("802", &["%#Lobby", "host", "b!*@*$*"])));
+        assert_eq!(list.len(), 1);
+
+        list.handle(&reply("801", &["%#Lobby", "VOICE", "V!*@*$*", "0", "Me"]));
+        assert!(list.handle(&reply("820", &["%#Lobby", "DENY", "Clear"])));
+        assert_eq!(list.entries()[0].level, AccessLevel::Voice);
+        assert!(list.handle(&reply("820", &["%#Lobby", "*", "Clear"])));
+        assert!(list.is_empty());
+        assert!(!list.handle(&reply("820", &["%#Lobby", "*", "Clear"])));
+    }
+
+    #[test]
+    fn commands() {
+        let entry = AccessEntry::new(AccessLevel::Deny, "*!*@bad")
+            .with_timeout(Duration::from_secs(600))
+            .with_reason("flooding");
+        assert_eq!(
+            entry.add_command("%#Lobby").unwrap().to_line().unwrap(),
+            "ACCESS %#Lobby ADD DENY *!*@bad 10 flooding\r\n"
+        );
+        assert_eq!(
+            AccessEntry::new(AccessLevel::Deny, "a b").add_command("%#Lobby"),
+            Err(AccessError::InvalidMask("a b".into()))
+        );
+        assert_eq!(
+            delete_command("%#Lobby", AccessLevel::Host, ""),
+            Err(AccessError::InvalidMask(String::new()))
+        );
+        assert_eq!("grant".parse(), Ok(AccessLevel::Grant));
+    }
+}
@@ -0,0 +1,508 @@
+use std::{cmp::Ordering, collections::BTreeMap};
+
+use super::{Command, Numeric, Prefix, SessionEvent, room::is_channel};
+use crate::types::{ChannelMode, ChannelModes, UserRole};
+
+/// MSN guest nicks start with `>`.
+pub const GUEST_PREFIX: char = '>';
+
+/// Gender from the first letter of an IRC8 profile code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Gender {
+    Male,
+    Female,
+    /// A public profile without a gender.
+    Unspecified,
+}
+
+/// The two-letter profile code of an IRC8 `NAMES` or `JOIN` entry, e.g. `FY`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Profile {
+    pub code: String,
+}
+
+impl Profile {
+    pub fn new(code: impl Into<String>) -> Self {
+        Self { code: code.into() }
+    }
+
+    /// `None` for `G`, meaning no public profile.
+    pub fn gender(&self) -> Option<Gender> {
+        match self.code.chars().next()? {
+            'M' | 'm' => Some(Gender::Male),
+            'F' | 'f' => Some(Gender::Female),
+            'P' | 'p' => Some(Gender::Unspecified),
+            _ => None,
+        }
+    }
+
+    pub fn has_picture(&self) -> bool {
+        matches!(self.code.chars().nth(1), Some('Y' | 'y'))
+    }
+}
+
+/// Someone in a channel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Member {
+    pub nick: String,
+    pub role: UserRole,
+    pub voice: bool,
+    pub away: bool,
+    /// Only IRC8 servers send profiles.
+    pub profile: Option<Profile>,
+}
+
+impl Member {
+    pub fn new(nick: impl Into<String>) -> Self {
+        Self {
+            nick: nick.into(),
+            role: UserRole::Participant,
+            voice: false,
+            away: false,
+            profile: None,
+        }
+    }
+
+    /// Parses a `NAMES` entry: `.Nick`, `@+Nick`, or IRC8's `H,U,GY,.Nick`.
+    pub fn parse(entry: &str) -> Option<Self> {
+        let mut fields = entry.rsplitn(4, ',');
+        let mut member = Self::parse_prefixed(fields.next()?)?;
+        let irc8: Vec<&str> = fields.collect();
+        if let [profile, mode, presence] = irc8.as_slice() {
+            member.apply_irc8(presence, mode, profile);
+        }
+        Some(member)
+    }
+
+    fn parse_prefixed(entry: &str) -> Option<Self> {
+        let nick = entry.trim_start_matches(['.', '@', '+']);
+        if nick.is_empty() {
+            return None;
+        }
+        let prefixes = &entry[..entry.len() - nick.len()];
+        let mut member = Member::new(nick);
+        if prefixes.contains('.') {
+            member.role = UserRole::Owner;
+        } else if prefixes.contains('@') {
+            member.role = UserRole::Host;
+        }
+        member.voice = prefixes.contains('+');
+        Some(member)
+    }
+
+    /// `presence` is `H`ere or `G`one; `mode` is `U`ser, `G`uide, `S`ysop or `A`dmin.
+    fn apply_irc8(&mut self, presence: &str, mode: &str, profile: &str) {
+        self.away = presence.eq_ignore_ascii_case("G");
+        match mode.to_ascii_uppercase().as_str() {
+            "G" => self.role = self.role.max(UserRole::Guide),
+            "S" | "A" => self.role = UserRole::Sysop,
+            _ => {}
+        }
+        if !profile.is_empty() {
+            self.profile = Some(Profile::new(profile));
+        }
+    }
+
+    pub fn is_guest(&self) -> bool {
+        self.nick.starts_with(GUEST_PREFIX)
+    }
+
+    /// The IRCX prefix shown in the user list: `.`, `@`, `+` or none.
+    pub fn prefix(&self) -> Option<char> {
+        self.role.prefix().or_else(|| self.voice.then_some('+'))
+    }
+
+    /// Order of the control's user list: staff and owners, hosts, voiced
+    /// members, participants, spectators; registered users before guests,
+    /// then by nick ignoring case.
+    pub fn list_order(&self, other: &Member) -> Ordering {
+        let rank = |m: &Member| {
+            (
+                m.prefix().map_or(3, |p| match p {
+                    '.' => 0,
+                    '@' => 1,
+                    _ => 2,
+                }),
+                m.role == UserRole::Spectator,
+            )
+        };
+        rank(self)
+            .cmp(&rank(other))
+            .then(self.is_guest().cmp(&other.is_guest()))
+            .then_with(|| {
+                self.nick
+                    .to_ascii_lowercase()
+                    .cmp(&other.nick.to_ascii_lowercase())
+            })
+    }
+}
+
+/// Membership and modes of one channel, kept current from the messages seen on it.
+#[derive(Debug, Clone)]
+pub struct Channel {
+    name: String,
+    modes: ChannelModes,
+    members: BTreeMap<String, Member>,
+    /// `NAMES` entries received since the last 366.
+    pending_names: Option<Vec<Member>>,
+}
+
+impl Channel {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            modes: ChannelModes::default(),
+            members: BTreeMap::new(),
+            pending_names: None,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn modes(&self) -> &ChannelModes {
+        &self.modes
+    }
+
+    pub fn member(&self, nick: &str) -> Option<&Member> {
+        self.members.get(&nick.to_ascii_lowercase())
+    }
+
+    pub fn contains(&self, nick: &str) -> bool {
+        self.member(nick).is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+
+    /// Members in the control's user list order; see [`Member::list_order`].
+    pub fn members(&self) -> Vec<&Member> {
+        let mut members: Vec<_> = self.members.values().collect();
+        members.sort_by(|a, b| a.list_order(b));
+        members
+    }
+
+    /// Applies `event`; returns true if membership or modes changed.
+    pub fn handle_event(&mut self, event: &SessionEvent) -> bool {
+        match event {
+            SessionEvent::Message { source, command } => self.handle(source.as_deref(), command),
+            _ => false,
+        }
+    }
+
+    /// Applies a message with prefix `source`; returns true if membership or modes changed.
+    pub fn handle(&mut self, source: Option<&str>, command: &Command) -> bool {
+        let nick = source.map(|s| Prefix::parse(s).nick);
+        match command {
+            Command::Join { channels, keys } => {
+                // IRC8 puts the member's flags first: `JOIN H,U,GY :%#Room`.
+                let (channel, flags) = match keys {
+                    Some(channel) if !is_channel(channels) => (channel, Some(channels)),
+                    _ => (channels, None),
+                };
+                let Some(nick) = nick.filter(|_| self.is(channel)) else {
+                    return false;
+                };
+                let entry = match flags {
+                    Some(flags) => format!("{flags},{nick}"),
+                    None => nick.to_owned(),
+                };
+                Member::parse(&entry).is_some_and(|m| self.insert(m))
+            }
+            Command::Part { channels, .. } if channels.split(',').any(|c| self.is(c)) => {
+                nick.is_some_and(|n| self.remove(n))
+            }
+            Command::Kick { channel, nick, .. } if self.is(channel) => self.remove(nick),
+            Command::Quit(_) => nick.is_some_and(|n| self.remove(n)),
+            Command::Nick(new) => nick.is_some_and(|old| self.rename(old, new)),
+            Command::Mode {
+                target,
+                modes: Some(modes),
+                args,
+            } if self.is(target) => self.apply_modes(modes, args),
+            Command::Other { command, params } => self.handle_numeric(command, params),
+            _ => false,
+        }
+    }
+
+    fn handle_numeric(&mut self, command: &str, params: &[String]) -> bool {
+        let Ok(code) = command.parse::<u16>() else {
+            return false;
+        };
+        // Every numeric starts with our own nick.
+        let arg = |i: usize| params.get(i + 1).map(String::as_str);
+        match Numeric::from_code(code) {
+            Numeric::NamReply if arg(1).is_some_and(|c| self.is(c)) => {
+                let names = arg(2).unwrap_or_default().split_whitespace();
+                self.pending_names
+                    .get_or_insert_with(Vec::new)
+                    .extend(names.filter_map(Member::parse));
+                false
+            }
+            Numeric::EndOfNames if arg(0).is_some_and(|c| self.is(c)) => {
+                let names = self.pending_names.take().unwrap_or_default();
+                self.members = names
+                    .into_iter()
+                    .map(|m| (m.nick.to_ascii_lowercase(), m))
+                    .collect();
+                true
+            }
+            Numeric::ChannelModeIs if arg(0).is_some_and(|c| self.is(c)) => {
+                let args = params.iter().skip(3).map(String::as_str);
+                match ChannelModes::parse_with_args(arg(1).unwrap_or("+"), args) {
+                    Ok((modes, _)) => {
+                        self.modes = modes;
+                        true
+                    }
+                    Err(_) => false,
+                }
+            }
+            _ => false,
+        }
+    }
+
+    /// Applies a `MODE` change such as `+o-v Alice Bob`.
+    fn apply_modes(&mut self, modes: &str, args: &[String]) -> bool {
+        let mut args = args.iter();
+        let mut adding = true;
+        let mut changed = false;
+        for c in modes.chars() {
+            match c {
+                '+' => adding = true,
+                '-' => adding = false,
+                'q' | 'o' | 'v' => {
+                    let Some(member) = args.next().and_then(|n| self.member_mut(n)) else {
+                        continue;
+                    };
+                    let before = (member.role, member.voice);
+                    match (c, adding) {
+                        ('q', true) => member.role = member.role.max(UserRole::Owner),
+                        ('o', true) => member.role = member.role.max(UserRole::Host),
+                        ('q', false) if member.role == UserRole::Owner => {
+                            member.role = UserRole::Participant
+                        }
+                        ('o', false) if member.role == UserRole::Host => {
+                            member.role = UserRole::Participant
+                        }
+                        ('v', voice) => member.voice = voice,
+                        _ => continue,
+                    }
+                    changed |= (member.role, member.voice) != before;
+                }
+                'k' => {
+                    // Servers echo the removed key with `-k`, so skip it either way.
+                    let key = args.next();
+                    self.modes.member_key = if adding { key.cloned() } else { None };
+                    changed = true;
+                }
+                'l' => {
+                    self.modes.user_limit = match adding {
+                        true => args.next().and_then(|l| l.parse().ok()),
+                        false => None,
+                    };
+                    changed = true;
+                }
+                c => {
+                    let Some(mode) = ChannelMode::from_letter(c) else {
+                        continue;
+                    };
+                    changed |= if adding {
+                        self.modes.flags.insert(mode)
+                    } else {
+                        self.modes.flags.remove(&mode)
+                    };
+                }
+            }
+        }
+        changed
+    }
+
+    fn is(&self, channel: &str) -> bool {
+        self.name.eq_ignore_ascii_case(channel)
+    }
+
+    fn member_mut(&mut self, nick: &str) -> Option<&mut Member> {
+        self.members.get_mut(&nick.to_ascii_lowercase())
+    }
+
+    fn insert(&mut self, member: Member) -> bool {
+        self.members
+            .insert(member.nick.to_ascii_lowercase(), member)
+            .is_none()
+    }
+
+    fn remove(&mut self, nick: &str) -> bool {
+        self.members.remove(&nick.to_ascii_lowercase()).is_some()
+    }
+
+    fn rename(&mut self, old: &str, new: &str) -> bool {
+        let Some(mut member) = self.members.remove(&old.to_ascii_lowercase()) else {
+            return false;
+        };
+        member.nick = new.to_owned();
+        self.insert(member);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn numeric(code: &str, params: &[&str]) -> Command {
+        Command::Other {
+            command: code.to_owned(),
+            params: std::iter::once("Me")
+                .chain(params.iter().copied())
+                .map(str::to_owned)
+                .collect(),
+        }
+    }
+
+    fn mode(modes: &str, args: &[&str]) -> Command {
+        Command::Mode {
+            target: "%#Lobby".to_owned(),
+            modes: Some(modes.to_owned()),
+            args: args.iter().map(|a| a.to_string()).collect(),
+        }
+    }
+
+    fn nicks(channel: &Channel) -> Vec<&str> {
+        channel.members().iter().map(|m| m.nick.as_str()).collect()
+    }
+
+    fn lobby(names: &str) -> Channel {
+        let mut channel = Channel::new("%#Lobby");
+        channel.handle(None, &numeric("353", &["=", "%#Lobby", names]));
+        channel.handle(None, &numeric("366", &["%#Lobby", "End of /NAMES list."]));
+        channel
+    }
+
+    #[test]
+    fn names_replace_members_on_366() {
+        let mut channel = lobby("Old");
+        assert!(!channel.handle(None, &numeric("353", &["=", "%#lobby", ".Alice @Bob"])));
+        assert!(!channel.handle(None, &numeric("353", &["=", "%#Other", "Mallory"])));
+        assert!(!channel.handle(None, &numeric("353", &["=", "%#LOBBY", "+Carol Dave"])));
+        assert_eq!(nicks(&channel), ["Old"]);
+
+        assert!(!channel.handle(None, &numeric("366", &["%#Other", "End of /NAMES list."])));
+        assert!(channel.handle(None, &numeric("366", &["%#Lobby", "End of /NAMES list."])));
+        assert_eq!(nicks(&channel), ["Alice", "Bob", "Carol", "Dave"]);
+        assert_eq!(channel.member("alice").unwrap().role, UserRole::Owner);
+        assert_eq!(channel.member("BOB").unwrap().role, UserRole::Host);
+        assert!(channel.member("Carol").unwrap().voice);
+
+        // A second listing starts from scratch.
+        assert!(channel.handle(None, &numeric("366", &["%#Lobby", "End of /NAMES list."])));
+        assert!(channel.is_empty());
+    }
+
+    #[test]
+    fn irc8_entries() {
+        let member = Member::parse("H,U,GY,.Nick").unwrap();
+        assert_eq!(member.nick, "Nick");
+        assert_eq!(member.role, UserRole::Owner);
+        assert!(!member.away);
+        let profile = member.profile.unwrap();
+        assert_eq!(profile.gender(), None);
+        assert!(profile.has_picture());
+
+        let member = Member::parse("G,G,FN,+Helper").unwrap();
+        assert_eq!(member.role, UserRole::Guide);
+        assert!(member.voice && member.away);
+        assert_eq!(member.profile.unwrap().gender(), Some(Gender::Female));
+
+        let member = Member::parse("H,S,,Oper").unwrap();
+        assert_eq!(member.role, UserRole::Sysop);
+        assert_eq!(member.profile, None);
+
+        assert_eq!(Member::parse("@Plain").unwrap().profile, None);
+        assert_eq!(Member::parse("H,U,MY,"), None);
+        assert_eq!(Member::parse(".@+"), None);
+    }
+
+    #[test]
+    fn irc8_join() {
+        let mut channel = Channel::new("%#Lobby");
+        let join = Command::Join {
+            channels: "H,U,MY".to_owned(),
+            keys: Some("%#Lobby".to_owned()),
+        };
+        assert!(channel.handle(Some("Bob!~b@host"), &join));
+        let bob = channel.member("Bob").unwrap();
+        assert_eq!(bob.profile.as_ref().unwrap().gender(), Some(Gender::Male));
+
+        let plain = Command::Join {
+            channels: "%#Lobby".to_owned(),
+            keys: None,
+        };
+        assert!(channel.handle(Some("Carol!~c@host"), &plain));
+        assert_eq!(channel.member("Carol").unwrap().profile, None);
+    }
+
+    #[test]
+    fn member_modes() {
+        let mut channel = lobby("Alice @Bob +Carol");
+        assert!(channel.handle(None, &mode("+q-o+v", &["Alice", "Bob", "Bob"])));
+        assert_eq!(channel.member("Alice").unwrap().role, UserRole::Owner);
+        let bob = channel.member("Bob").unwrap();
+        assert_eq!(bob.role, UserRole::Participant);
+        assert!(bob.voice);
+
+        // Neither -o nor +o touches an owner.
+        assert!(!channel.handle(None, &mode("-o", &["Alice"])));
+        assert!(!channel.handle(None, &mode("+o", &["Alice"])));
+        assert_eq!(channel.member("Alice").unwrap().role, UserRole::Owner);
+        assert!(channel.handle(None, &mode("-q", &["Alice"])));
+        assert_eq!(channel.member("Alice").unwrap().role, UserRole::Participant);
+
+        assert!(channel.handle(None, &mode("-v+kl", &["Carol", "secret", "50"])));
+        assert!(!channel.member("Carol").unwrap().voice);
+        assert_eq!(channel.modes().member_key.as_deref(), Some("secret"));
+        assert_eq!(channel.modes().user_limit, Some(50));
+
+        // Unknown nicks still consume their argument.
+        assert!(channel.handle(None, &mode("+o-v", &["Nobody", "Bob"])));
+        assert_eq!(channel.member("Bob").unwrap().role, UserRole::Participant);
+        assert!(!channel.member("Bob").unwrap().voice);
+    }
+
+    #[test]
+    fn removing_the_key_consumes_its_argument() {
+        let mut channel = lobby("Alice Bob");
+        assert!(channel.handle(None, &mode("+k", &["secret"])));
+        assert!(channel.handle(None, &mode("-k+o", &["secret", "Bob"])));
+        assert_eq!(channel.modes().member_key, None);
+        assert_eq!(channel.member("Bob").unwrap().role, UserRole::Host);
+
+        assert!(channel.handle(None, &mode("-k+l", &["*", "20"])));
+        assert_eq!(channel.modes().user_limit, Some(20));
+    }
+
+    #[test]
+    fn list_order_matches_the_control() {
+        let channel = lobby("zed >Guest .Owner @host +Voice Amy >amy2 @Able H,S,,sysop");
+        assert_eq!(
+            nicks(&channel),
+            [
+                "Owner", "sysop", "Able", "host", "Voice", "Amy", "zed", ">amy2", ">Guest"
+            ]
+        );
+
+        let mut spectator = Member::new("Aaron");
+        spectator.role = UserRole::Spectator;
+        let participant = Member::new("Zoe");
+        assert_eq!(spectator.list_order(&participant), Ordering::Greater);
+        assert_eq!(
+            participant.list_order(&participant.clone()),
+            Ordering::Equal
+        );
+    }
+}
@@ -0,0 +1,281 @@
+use std::fmt;
+
+use super::{Command, FormattedText};
+
+const DELIMITER: char = '\x01';
+
+/// Sound cues shipped with the chat control, without the `.wav` extension.
+pub const KNOWN_SOUNDS: &[&str] = &[
+    "applause", "bells", "boing", "boo", "cheer", "crickets", "doorbell", "drumroll", "giggle",
+    "kiss", "laugh", "ohno", "slurp", "whistle", "yawn",
+];
+
+/// The file named by a `SOUND` envelope, exactly as sent.
+///
+/// Senders choose the name, so it must never be used as a path: play only
+/// what [`SoundName::known`] resolves.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SoundName(String);
+
+impl SoundName {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self(name.into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// The matching [`KNOWN_SOUNDS`] entry; case and a `.wav` suffix are ignored.
+    pub fn known(&self) -> Option<&'static str> {
+        let name = self.0.to_ascii_lowercase();
+        let name = name.strip_suffix(".wav").unwrap_or(&name);
+        KNOWN_SOUNDS.iter().copied().find(|k| *k == name)
+    }
+
+    pub fn is_known(&self) -> bool {
+        self.known().is_some()
+    }
+}
+
+impl fmt::Display for SoundName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// The payload of a `PRIVMSG`, `NOTICE` or `WHISPER`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ChatMessage {
+    /// Ordinary text, possibly in font envelopes.
+    Text(FormattedText),
+    /// `/me`: `\x01ACTION <text>\x01`, or `\x01ACTION\x01` without text.
+    Action(Option<String>),
+    /// `\x01SOUND <file> <text>\x01`; the text, if any, is shown while the cue plays.
+    Sound {
+        file: SoundName,
+        text: Option<String>,
+    },
+    /// Any other CTCP request or reply, such as `VERSION` or `PING`.
+    Ctcp {
+        command: String,
+        params: Option<String>,
+    },
+}
+
+impl ChatMessage {
+    /// Never fails: anything that is not a well-formed CTCP envelope is text.
+    ///
+    /// Only the upper-case `ACTION` and `SOUND` are recognised, so the result
+    /// always renders back to `payload` byte for byte.
+    pub fn parse(payload: &str) -> Self {
+        Self::parse_ctcp(payload)
+            .unwrap_or_else(|| ChatMessage::Text(FormattedText::parse(payload)))
+    }
+
+    fn parse_ctcp(payload: &str) -> Option<Self> {
+        let inner = payload.strip_prefix(DELIMITER)?.strip_suffix(DELIMITER)?;
+        let (command, params) = match inner.split_once(' ') {
+            Some((command, params)) => (command, Some(params)),
+            None => (inner, None),
+        };
+        // A one-letter `S` is a font envelope, not a CTCP command.
+        if command.len() < 2 || !command.bytes().all(|b| b.is_ascii_alphanumeric()) {
+            return None;
+        }
+
+        Some(match command {
+            "ACTION" => ChatMessage::Action(params.map(str::to_owned)),
+            "SOUND" => {
+                let params = params?;
+                let (file, text) = match params.split_once(' ') {
+                    Some((file, text)) => (file, Some(text)),
+                    None => (params, None),
+                };
+                if file.is_empty() {
+                    return None;
+                }
+                ChatMessage::Sound {
+                    file: SoundName::new(file),
+                    text: text.map(str::to_owned),
+                }
+            }
+            _ => ChatMessage::Ctcp {
+                command: command.to_owned(),
+                params: params.map(str::to_owned),
+            },
+        })
+    }
+
+    /// The chat payload carried by `command`, if it carries one.
+    pub fn from_command(command: &Command) -> Option<Self> {
+        match command {
+            Command::Privmsg { text, .. }
+            | Command::Notice { text, .. }
+            | Command::Whisper { text, .. } => Some(ChatMessage::parse(text)),
+            _ => None,
+        }
+    }
+
+    pub fn text(text: impl Into<FormattedText>) -> Self {
+        ChatMessage::Text(text.into())
+    }
+
+    /// `/me`; empty text sends a bare `\x01ACTION\x01`.
+    pub fn action(text: impl Into<String>) -> Self {
+        let text = text.into();
+        ChatMessage::Action((!text.is_empty()).then_some(text))
+    }
+
+    /// Text for logs and notifications, without envelopes.
+    pub fn plain(&self) -> String {
+        match self {
+            ChatMessage::Text(text) => text.plain(),
+            ChatMessage::Action(text) | ChatMessage::Sound { text, .. } => {
+                text.clone().unwrap_or_default()
+            }
+            ChatMessage::Ctcp { command, params } => match params {
+                Some(params) => format!("{command} {params}"),
+                None => command.clone(),
+            },
+        }
+    }
+
+    pub fn to_privmsg(&self, target: impl Into<String>) -> Command {
+        Command::Privmsg {
+            target: target.into(),
+            text: self.to_string(),
+        }
+    }
+
+    pub fn to_notice(&self, target: impl Into<String>) -> Command {
+        Command::Notice {
+            target: target.into(),
+            text: self.to_string(),
+        }
+    }
+
+    pub fn to_whisper(&self, channel: impl Into<String>, targets: impl Into<String>) -> Command {
+        Command::Whisper {
+            channel: channel.into(),
+            targets: targets.into(),
+            text: self.to_string(),
+        }
+    }
+}
+
+/// Renders the wire payload.
+impl fmt::Display for ChatMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChatMessage::Text(text) => text.fmt(f),
+            ChatMessage::Action(text) => write_ctcp(f, "ACTION", text.as_deref()),
+            ChatMessage::Sound { file, text } => match text {
+                Some(text) => write!(f, "{DELIMITER}SOUND {file} {text}{DELIMITER}"),
+                None => write!(f, "{DELIMITER}SOUND {file}{DELIMITER}"),
+            },
+            ChatMessage::Ctcp { command, params } => write_ctcp(f, command, params.as_deref()),
+        }
+    }
+}
+
+/// `\x01<command> <params>\x01`, without the space when there are no params.
+fn write_ctcp(f: &mut fmt::Formatter<'_>, command: &str, params: Option<&str>) -> fmt::Result {
+    match params {
+        Some(params) => write!(f, "{DELIMITER}{command} {params}{DELIMITER}"),
+        None => write!(f, "{DELIMITER}{command}{DELIMITER}"),
+    }
+}
+
+impl From<FormattedText> for ChatMessage {
+    fn from(text: FormattedText) -> Self {
+        ChatMessage::Text(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses `wire`, checks the result, and checks it renders back unchanged.
+    fn round_trip(wire: &str, expected: ChatMessage) {
+        let message = ChatMessage::parse(wire);
+        assert_eq!(message, expected, "{wire:?}");
+        assert_eq!(message.to_string(), wire);
+    }
+
+    #[test]
+    fn text_round_trips() {
+        for wire in [
+            "hello",
+            "",
+            "\x01",
+            "\x01S\x01",
+            "\x01ACTION",
+            "a \x01ACTION hi\x01",
+        ] {
+            round_trip(wire, ChatMessage::Text(FormattedText::parse(wire)));
+        }
+        let styled = "\x01S \x01\x02Arial;0 text\x01";
+        assert!(matches!(ChatMessage::parse(styled), ChatMessage::Text(t) if t.is_formatted()));
+        assert_eq!(ChatMessage::parse(styled).to_string(), styled);
+    }
+
+    #[test]
+    fn action_round_trips() {
+        round_trip("\x01ACTION waves\x01", ChatMessage::action("waves"));
+        round_trip("\x01ACTION\x01", ChatMessage::Action(None));
+        round_trip("\x01ACTION \x01", ChatMessage::Action(Some(String::new())));
+        assert_eq!(ChatMessage::action("").to_string(), "\x01ACTION\x01");
+        assert_eq!(ChatMessage::action("waves").plain(), "waves");
+    }
+
+    #[test]
+    fn sound_round_trips() {
+        round_trip(
+            "\x01SOUND boing\x01",
+            ChatMessage::Sound {
+                file: SoundName::new("boing"),
+                text: None,
+            },
+        );
+        round_trip(
+            "\x01SOUND Laugh.wav ha ha\x01",
+            ChatMessage::Sound {
+                file: SoundName::new("Laugh.wav"),
+                text: Some("ha ha".into()),
+            },
+        );
+        // A sound needs a file; without one the envelope is plain text.
+        round_trip("\x01SOUND\x01", ChatMessage::text("\x01SOUND\x01"));
+
+        assert_eq!(SoundName::new("Laugh.wav").known(), Some("laugh"));
+        assert!(!SoundName::new("../../evil").is_known());
+    }
+
+    #[test]
+    fn ctcp_round_trips() {
+        round_trip(
+            "\x01VERSION\x01",
+            ChatMessage::Ctcp {
+                command: "VERSION".into(),
+                params: None,
+            },
+        );
+        round_trip(
+            "\x01PING 12345 678\x01",
+            ChatMessage::Ctcp {
+                command: "PING".into(),
+                params: Some("12345 678".into()),
+            },
+        );
+        // Lower-case names are kept as generic CTCP so they render unchanged.
+        round_trip(
+            "\x01action waves\x01",
+            ChatMessage::Ctcp {
+                command: "action".into(),
+                params: Some("waves".into()),
+            },
+        );
+    }
+}
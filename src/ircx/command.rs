@@ -0,0 +1,1025 @@
+use std::fmt;
+
+use super::message::{EncodeError, Message, ParseError};
+
+/// Why a [`Message`] could not be turned into a [`Command`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommandError {
+    Parse(ParseError),
+    /// The command needs at least `expected` parameters.
+    MissingParams {
+        command: String,
+        expected: usize,
+    },
+    /// A parameter is present but malformed.
+    InvalidParam {
+        command: String,
+        index: usize,
+    },
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CommandError::Parse(err) => err.fmt(f),
+            CommandError::MissingParams { command, expected } => {
+                write!(f, "{command} needs at least {expected} parameter(s)")
+            }
+            CommandError::InvalidParam { command, index } => {
+                write!(f, "{command} has an invalid parameter at position {index}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for CommandError {}
+
+impl From<ParseError> for CommandError {
+    fn from(err: ParseError) -> Self {
+        CommandError::Parse(err)
+    }
+}
+
+/// Step of an IRCX `AUTH` exchange.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AuthSequence {
+    /// `I`: the client's opening message.
+    Initial,
+    /// `S`: any following challenge or response.
+    Subsequent,
+    /// `*`: the server's success notification.
+    Complete,
+}
+
+impl AuthSequence {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AuthSequence::Initial => "I",
+            AuthSequence::Subsequent => "S",
+            AuthSequence::Complete => "*",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "I" | "i" => Some(AuthSequence::Initial),
+            "S" | "s" => Some(AuthSequence::Subsequent),
+            "*" => Some(AuthSequence::Complete),
+            _ => None,
+        }
+    }
+}
+
+/// A typed RFC 1459 / IRCX / MSN command.
+///
+/// Comma-separated target lists (e.g. `JOIN #a,#b`) are kept as a single string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    // RFC 1459
+    Pass(String),
+    Nick(String),
+    User {
+        username: String,
+        mode: String,
+        unused: String,
+        realname: String,
+    },
+    Oper {
+        name: String,
+        password: String,
+    },
+    Quit(Option<String>),
+    Join {
+        channels: String,
+        keys: Option<String>,
+    },
+    Part {
+        channels: String,
+        reason: Option<String>,
+    },
+    Mode {
+        target: String,
+        modes: Option<String>,
+        args: Vec<String>,
+    },
+    Topic {
+        channel: String,
+        topic: Option<String>,
+    },
+    Names(Option<String>),
+    List(Option<String>),
+    Invite {
+        nick: String,
+        channel: String,
+    },
+    Kick {
+        channel: String,
+        nick: String,
+        reason: Option<String>,
+    },
+    Privmsg {
+        target: String,
+        text: String,
+    },
+    Notice {
+        target: String,
+        text: String,
+    },
+    Who(Option<String>),
+    Whois(String),
+    Ping(String),
+    Pong(String),
+    Away(Option<String>),
+    Error(String),
+
+    // IRCX and MSN extensions
+    Auth {
+        package: String,
+        sequence: AuthSequence,
+        data: Vec<String>,
+    },
+    /// `IRCVERS IRC8 MSN-OCX!9.02.0310.2401`
+    Ircvers {
+        version: String,
+        client: String,
+    },
+    IsIrcx,
+    /// `MODE ISIRCX`, the IRCX capability probe.
+    ///
+    /// This is the canonical form: a [`Command::Mode`] on target `ISIRCX`
+    /// without modes renders the same line and parses back as this variant.
+    ModeIsIrcx,
+    Prop {
+        target: String,
+        property: String,
+        value: Option<String>,
+    },
+    Access {
+        target: String,
+        operation: Option<String>,
+        args: Vec<String>,
+    },
+    Whisper {
+        channel: String,
+        targets: String,
+        text: String,
+    },
+    Data {
+        target: String,
+        tag: String,
+        payload: String,
+    },
+    Request {
+        target: String,
+        tag: String,
+        payload: String,
+    },
+    Reply {
+        target: String,
+        tag: String,
+        payload: String,
+    },
+    /// Sent to hosts when someone is refused entry; `reason` is the numeric.
+    Knock {
+        channel: String,
+        reason: String,
+    },
+    Create {
+        channel: String,
+        args: Vec<String>,
+    },
+    Listx(Vec<String>),
+    Event {
+        action: String,
+        args: Vec<String>,
+    },
+
+    /// Any other command, including numeric replies.
+    Other {
+        command: String,
+        params: Vec<String>,
+    },
+}
+
+impl Command {
+    /// Parses a line (strict mode) straight into a command.
+    pub fn parse(line: &str) -> Result<Self, CommandError> {
+        Self::from_message(&Message::parse(line)?)
+    }
+
+    /// Converts a parsed message, ignoring its prefix.
+    pub fn from_message(msg: &Message<'_>) -> Result<Self, CommandError> {
+        let command = msg.command.to_ascii_uppercase();
+        let args: Vec<&str> = msg.args().collect();
+        let a = Args {
+            command: &command,
+            args: &args,
+        };
+
+        let cmd = match command.as_str() {
+            "PASS" => Command::Pass(a.req(0, 1)?),
+            "NICK" => Command::Nick(a.req(0, 1)?),
+            "USER" => Command::User {
+                username: a.req(0, 4)?,
+                mode: a.req(1, 4)?,
+                unused: a.req(2, 4)?,
+                realname: a.req(3, 4)?,
+            },
+            "OPER" => Command::Oper {
+                name: a.req(0, 2)?,
+                password: a.req(1, 2)?,
+            },
+            "QUIT" => Command::Quit(a.opt(0)),
+            "JOIN" => Command::Join {
+                channels: a.req(0, 1)?,
+                keys: a.opt(1),
+            },
+            "PART" => Command::Part {
+                channels: a.req(0, 1)?,
+                reason: a.opt(1),
+            },
+            "MODE" if args.len() == 1 && args[0].eq_ignore_ascii_case("ISIRCX") => {
+                Command::ModeIsIrcx
+            }
+            "MODE" => Command::Mode {
+                target: a.req(0, 1)?,
+                modes: a.opt(1),
+                args: a.rest(2),
+            },
+            "TOPIC" => Command::Topic {
+                channel: a.req(0, 1)?,
+                topic: a.opt(1),
+            },
+            "NAMES" => Command::Names(a.opt(0)),
+            "LIST" => Command::List(a.opt(0)),
+            "INVITE" => Command::Invite {
+                nick: a.req(0, 2)?,
+                channel: a.req(1, 2)?,
+            },
+            "KICK" => Command::Kick {
+                channel: a.req(0, 2)?,
+                nick: a.req(1, 2)?,
+                reason: a.opt(2),
+            },
+            "PRIVMSG" => Command::Privmsg {
+                target: a.req(0, 2)?,
+                text: a.req(1, 2)?,
+            },
+            "NOTICE" => Command::Notice {
+                target: a.req(0, 2)?,
+                text: a.req(1, 2)?,
+            },
+            "WHO" => Command::Who(a.opt(0)),
+            "WHOIS" => Command::Whois(a.req(0, 1)?),
+            "PING" => Command::Ping(a.last(1)?),
+            "PONG" => Command::Pong(a.last(1)?),
+            "AWAY" => Command::Away(a.opt(0)),
+            "ERROR" => Command::Error(a.req(0, 1)?),
+            "AUTH" => Command::Auth {
+                package: a.req(0, 2)?,
+                sequence: AuthSequence::parse(&a.req(1, 2)?).ok_or_else(|| a.invalid(1))?,
+                data: a.rest(2),
+            },
+            "IRCVERS" => Command::Ircvers {
+                version: a.req(0, 2)?,
+                client: a.req(1, 2)?,
+            },
+            "ISIRCX" => Command::IsIrcx,
+            "PROP" => Command::Prop {
+                target: a.req(0, 2)?,
+                property: a.req(1, 2)?,
+                value: a.opt(2),
+            },
+            "ACCESS" => Command::Access {
+                target: a.req(0, 1)?,
+                operation: a.opt(1),
+                args: a.rest(2),
+            },
+            "WHISPER" => Command::Whisper {
+                channel: a.req(0, 3)?,
+                targets: a.req(1, 3)?,
+                text: a.req(2, 3)?,
+            },
+            "DATA" | "REQUEST" | "REPLY" => {
+                let (target, tag, payload) = (a.req(0, 3)?, a.req(1, 3)?, a.req(2, 3)?);
+                match command.as_str() {
+                    "DATA" => Command::Data {
+                        target,
+                        tag,
+                        payload,
+                    },
+                    "REQUEST" => Command::Request {
+                        target,
+                        tag,
+                        payload,
+                    },
+                    _ => Command::Reply {
+                        target,
+                        tag,
+                        payload,
+                    },
+                }
+            }
+            "KNOCK" => Command::Knock {
+                channel: a.req(0, 2)?,
+                reason: a.req(1, 2)?,
+            },
+            "CREATE" => Command::Create {
+                channel: a.req(0, 1)?,
+                args: a.rest(1),
+            },
+            "LISTX" => Command::Listx(a.rest(0)),
+            "EVENT" => Command::Event {
+                action: a.req(0, 1)?,
+                args: a.rest(1),
+            },
+            _ => Command::Other {
+                command: msg.command.to_string(),
+                params: a.rest(0),
+            },
+        };
+
+        Ok(cmd)
+    }
+
+    /// The command verb as sent on the wire.
+    pub fn name(&self) -> &str {
+        match self {
+            Command::Pass(_) => "PASS",
+            Command::Nick(_) => "NICK",
+            Command::User { .. } => "USER",
+            Command::Oper { .. } => "OPER",
+            Command::Quit(_) => "QUIT",
+            Command::Join { .. } => "JOIN",
+            Command::Part { .. } => "PART",
+            Command::Mode { .. } | Command::ModeIsIrcx => "MODE",
+            Command::Topic { .. } => "TOPIC",
+            Command::Names(_) => "NAMES",
+            Command::List(_) => "LIST",
+            Command::Invite { .. } => "INVITE",
+            Command::Kick { .. } => "KICK",
+            Command::Privmsg { .. } => "PRIVMSG",
+            Command::Notice { .. } => "NOTICE",
+            Command::Who(_) => "WHO",
+            Command::Whois(_) => "WHOIS",
+            Command::Ping(_) => "PING",
+            Command::Pong(_) => "PONG",
+            Command::Away(_) => "AWAY",
+            Command::Error(_) => "ERROR",
+            Command::Auth { .. } => "AUTH",
+            Command::Ircvers { .. } => "IRCVERS",
+            Command::IsIrcx => "ISIRCX",
+            Command::Prop { .. } => "PROP",
+            Command::Access { .. } => "ACCESS",
+            Command::Whisper { .. } => "WHISPER",
+            Command::Data { .. } => "DATA",
+            Command::Request { .. } => "REQUEST",
+            Command::Reply { .. } => "REPLY",
+            Command::Knock { .. } => "KNOCK",
+            Command::Create { .. } => "CREATE",
+            Command::Listx(_) => "LISTX",
+            Command::Event { .. } => "EVENT",
+            Command::Other { command, .. } => command,
+        }
+    }
+
+    /// The reply code when this is a numeric reply.
+    pub fn numeric(&self) -> Option<u16> {
+        match self {
+            Command::Other { command, .. } => Message::new(command).numeric(),
+            _ => None,
+        }
+    }
+
+    /// Renders the command as a message borrowing from `self`.
+    ///
+    /// Free-text fields (message text, reasons, topics, AUTH data) are always sent
+    /// as the trailing parameter; other final parameters only when they need it.
+    pub fn to_message(&self) -> Message<'_> {
+        let msg = Message::new(self.name());
+        match self {
+            Command::Pass(v) | Command::Nick(v) | Command::Whois(v) => msg.param(v),
+            Command::User {
+                username,
+                mode,
+                unused,
+                realname,
+            } => msg
+                .param(username)
+                .param(mode)
+                .param(unused)
+                .trailing(realname),
+            Command::Oper { name, password } => msg.param(name).param(password),
+            Command::Quit(reason) | Command::Away(reason) => with_trailing(msg, reason),
+            Command::Join { channels, keys } => with_last(msg.param(channels), keys),
+            Command::Part { channels, reason } => with_trailing(msg.param(channels), reason),
+            Command::Mode {
+                target,
+                modes,
+                args,
+            } => {
+                let msg = with_last(msg.param(target), modes);
+                with_list(msg, args)
+            }
+            Command::ModeIsIrcx => msg.param("ISIRCX"),
+            Command::Topic { channel, topic } => with_trailing(msg.param(channel), topic),
+            Command::Names(target) | Command::List(target) | Command::Who(target) => {
+                with_last(msg, target)
+            }
+            Command::Invite { nick, channel } => msg.param(nick).param(channel),
+            Command::Kick {
+                channel,
+                nick,
+                reason,
+            } => with_trailing(msg.param(channel).param(nick), reason),
+            Command::Privmsg { target, text } | Command::Notice { target, text } => {
+                msg.param(target).trailing(text)
+            }
+            Command::Ping(token) | Command::Pong(token) => last(msg, token),
+            Command::Error(text) => msg.trailing(text),
+            Command::Auth {
+                package,
+                sequence,
+                data,
+            } => {
+                let msg = msg.param(package).param(sequence.as_str());
+                match (sequence, data.as_slice()) {
+                    (AuthSequence::Complete, _) => with_list(msg, data),
+                    (_, [rest @ .., blob]) => {
+                        rest.iter().fold(msg, |m, p| m.param(p)).trailing(blob)
+                    }
+                    (_, []) => msg,
+                }
+            }
+            Command::Ircvers { version, client } => msg.param(version).param(client),
+            Command::IsIrcx => msg,
+            Command::Prop {
+                target,
+                property,
+                value,
+            } => with_trailing(msg.param(target).param(property), value),
+            Command::Access {
+                target,
+                operation,
+                args,
+            } => with_list(with_last(msg.param(target), operation), args),
+            Command::Whisper {
+                channel,
+                targets,
+                text,
+            } => msg.param(channel).param(targets).trailing(text),
+            Command::Data {
+                target,
+                tag,
+                payload,
+            }
+            | Command::Request {
+                target,
+                tag,
+                payload,
+            }
+            | Command::Reply {
+                target,
+                tag,
+                payload,
+            } => msg.param(target).param(tag).trailing(payload),
+            Command::Knock { channel, reason } => msg.param(channel).param(reason),
+            Command::Create { channel, args } => with_list(msg.param(channel), args),
+            Command::Listx(args) => with_list(msg, args),
+            Command::Event { action, args } => with_list(msg.param(action), args),
+            Command::Other { params, .. } => with_list(msg, params),
+        }
+    }
+
+    /// Encodes the command as a CRLF-terminated line.
+    pub fn to_line(&self) -> Result<String, EncodeError> {
+        self.to_message().to_line()
+    }
+}
+
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.to_message().fmt(f)
+    }
+}
+
+impl TryFrom<&Message<'_>> for Command {
+    type Error = CommandError;
+
+    fn try_from(msg: &Message<'_>) -> Result<Self, CommandError> {
+        Command::from_message(msg)
+    }
+}
+
+/// True when `param` can only be sent as the trailing parameter.
+pub(crate) fn needs_trailing(param: &str) -> bool {
+    param.is_empty() || param.starts_with(':') || param.contains(' ')
+}
+
+fn last<'a>(msg: Message<'a>, param: &'a str) -> Message<'a> {
+    if needs_trailing(param) {
+        msg.trailing(param)
+    } else {
+        msg.param(param)
+    }
+}
+
+fn with_last<'a>(msg: Message<'a>, param: &'a Option<String>) -> Message<'a> {
+    match param {
+        Some(p) => last(msg, p),
+        None => msg,
+    }
+}
+
+fn with_trailing<'a>(msg: Message<'a>, param: &'a Option<String>) -> Message<'a> {
+    match param {
+        Some(p) => msg.trailing(p),
+        None => msg,
+    }
+}
+
+fn with_list<'a>(msg: Message<'a>, params: &'a [String]) -> Message<'a> {
+    match params {
+        [rest @ .., tail] => last(rest.iter().fold(msg, |m, p| m.param(p)), tail),
+        [] => msg,
+    }
+}
+
+struct Args<'c, 'a> {
+    command: &'c str,
+    args: &'c [&'a str],
+}
+
+impl Args<'_, '_> {
+    /// Required parameter `index` of a command taking at least `expected`.
+    fn req(&self, index: usize, expected: usize) -> Result<String, CommandError> {
+        self.args
+            .get(index)
+            .map(|s| s.to_string())
+            .ok_or_else(|| CommandError::MissingParams {
+                command: self.command.to_string(),
+                expected,
+            })
+    }
+
+    fn opt(&self, index: usize) -> Option<String> {
+        self.args.get(index).map(|s| s.to_string())
+    }
+
+    /// The final parameter; `PONG server :token` carries the token last.
+    fn last(&self, expected: usize) -> Result<String, CommandError> {
+        self.req(self.args.len().max(1) - 1, expected)
+    }
+
+    fn rest(&self, from: usize) -> Vec<String> {
+        self.args.iter().skip(from).map(|s| s.to_string()).collect()
+    }
+
+    fn invalid(&self, index: usize) -> CommandError {
+        CommandError::InvalidParam {
+            command: self.command.to_string(),
+            index,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Free-text values that exercise trailing-parameter encoding.
+    const TEXTS: [&str; 5] = ["hello", "two words", ":colon", "", "trailing space "];
+    /// Values that are always middle parameters.
+    const WORDS: [&str; 3] = ["%#Lobby", "Alice", "a\\bb"];
+
+    fn some(s: &str) -> Option<String> {
+        Some(s.to_owned())
+    }
+
+    fn list(items: &[&str]) -> Vec<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    /// At least one value of every variant, crossed with the sample strings.
+    fn samples() -> Vec<Command> {
+        let mut commands = vec![
+            Command::Quit(None),
+            Command::Away(None),
+            Command::Names(None),
+            Command::List(None),
+            Command::Who(None),
+            Command::IsIrcx,
+            Command::ModeIsIrcx,
+            Command::Join {
+                channels: "%#A,%#B".into(),
+                keys: some("k1,k2"),
+            },
+            Command::Auth {
+                package: "GateKeeper".into(),
+                sequence: AuthSequence::Initial,
+                data: Vec::new(),
+            },
+            Command::Auth {
+                package: "GateKeeper".into(),
+                sequence: AuthSequence::Complete,
+                data: list(&["ABCD@GateKeeper", "0"]),
+            },
+            Command::Ircvers {
+                version: "IRC8".into(),
+                client: "MSN-OCX!9.02.0310.2401".into(),
+            },
+            Command::Knock {
+                channel: "%#Lobby".into(),
+                reason: "473".into(),
+            },
+            Command::Listx(Vec::new()),
+            Command::Other {
+                command: "001".into(),
+                params: list(&["Alice", "Welcome to the server"]),
+            },
+        ];
+        for word in WORDS {
+            commands.extend([
+                Command::Pass(word.into()),
+                Command::Nick(word.into()),
+                Command::Whois(word.into()),
+                Command::Oper {
+                    name: word.into(),
+                    password: "secret".into(),
+                },
+                Command::Invite {
+                    nick: word.into(),
+                    channel: "%#Lobby".into(),
+                },
+                Command::Mode {
+                    target: word.into(),
+                    modes: None,
+                    args: Vec::new(),
+                },
+                Command::Mode {
+                    target: word.into(),
+                    modes: some("+ol"),
+                    args: list(&["Bob", "50"]),
+                },
+                Command::Names(some(word)),
+                Command::Access {
+                    target: word.into(),
+                    operation: some("ADD"),
+                    args: list(&["DENY", "*!*@host", "0", "spam"]),
+                },
+                Command::Access {
+                    target: word.into(),
+                    operation: None,
+                    args: Vec::new(),
+                },
+                Command::Create {
+                    channel: word.into(),
+                    args: list(&["+ml", "50", "1"]),
+                },
+                Command::Listx(list(&[word, "<50"])),
+                Command::Event {
+                    action: "ADD".into(),
+                    args: list(&["CHANNEL", word]),
+                },
+            ]);
+        }
+        for text in TEXTS {
+            commands.extend([
+                Command::User {
+                    username: "msnchat".into(),
+                    mode: "0".into(),
+                    unused: "*".into(),
+                    realname: text.into(),
+                },
+                Command::Quit(some(text)),
+                Command::Away(some(text)),
+                Command::Part {
+                    channels: "%#Lobby".into(),
+                    reason: some(text),
+                },
+                Command::Topic {
+                    channel: "%#Lobby".into(),
+                    topic: some(text),
+                },
+                Command::Kick {
+                    channel: "%#Lobby".into(),
+                    nick: "Bob".into(),
+                    reason: some(text),
+                },
+                Command::Privmsg {
+                    target: "%#Lobby".into(),
+                    text: text.into(),
+                },
+                Command::Notice {
+                    target: "Bob".into(),
+                    text: text.into(),
+                },
+                Command::Ping(text.into()),
+                Command::Pong(text.into()),
+                Command::Error(text.into()),
+                Command::Auth {
+                    package: "GateKeeper".into(),
+                    sequence: AuthSequence::Subsequent,
+                    data: list(&[text]),
+                },
+                Command::Prop {
+                    target: "%#Lobby".into(),
+                    property: "TOPIC".into(),
+                    value: some(text),
+                },
+                Command::Whisper {
+                    channel: "%#Lobby".into(),
+                    targets: "Bob,Carol".into(),
+                    text: text.into(),
+                },
+                Command::Data {
+                    target: "Bob".into(),
+                    tag: "CTCP".into(),
+                    payload: text.into(),
+                },
+                Command::Request {
+                    target: "Bob".into(),
+                    tag: "CTCP".into(),
+                    payload: text.into(),
+                },
+                Command::Reply {
+                    target: "Bob".into(),
+                    tag: "CTCP".into(),
+                    payload: text.into(),
+                },
+                Command::Join {
+                    channels: "%#Lobby".into(),
+                    keys: some(text),
+                },
+                Command::Other {
+                    command: "SILENCE".into(),
+                    params: list(&["Bob", text]),
+                },
+            ]);
+        }
+        commands
+    }
+
+    #[test]
+    fn every_variant_round_trips() {
+        for command in samples() {
+            let line = command
+                .to_line()
+                .unwrap_or_else(|err| panic!("{command:?} failed to encode: {err}"));
+            let parsed = Command::parse(line.trim_end_matches("\r\n"))
+                .unwrap_or_else(|err| panic!("{line:?} failed to parse: {err}"));
+            assert_eq!(parsed, command, "{line:?}");
+        }
+    }
+
+    /// Deterministic linear congruential generator for the property test.
+    struct Lcg(u64);
+
+    impl Lcg {
+        fn below(&mut self, n: usize) -> usize {
+            self.0 = self
+                .0
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (self.0 >> 33) as usize % n
+        }
+
+        fn chance(&mut self) -> bool {
+            self.below(2) == 0
+        }
+
+        fn string(&mut self, alphabet: &[char], min: usize) -> String {
+            let len = min + self.below(12);
+            (0..len)
+                .map(|_| alphabet[self.below(alphabet.len())])
+                .collect()
+        }
+
+        /// A middle parameter: non-empty, no spaces, no leading `:`.
+        fn word(&mut self) -> String {
+            const CHARS: &[char] = &['a', 'Z', '7', '%', '#', '\\', ',', '!', '@', '*', 'é'];
+            let word = self.string(CHARS, 1);
+            if self.chance() {
+                word
+            } else {
+                format!("{word}:x")
+            }
+        }
+
+        /// Free text, which may be empty, spaced or start with `:`.
+        fn text(&mut self) -> String {
+            const CHARS: &[char] = &['a', 'Z', ' ', ':', '\\', '\x01', '☺'];
+            self.string(CHARS, 0)
+        }
+
+        fn opt_text(&mut self) -> Option<String> {
+            self.chance().then(|| self.text())
+        }
+
+        /// Middle parameters, the last of which may be free text.
+        fn list(&mut self) -> Vec<String> {
+            let mut items: Vec<_> = (0..self.below(5)).map(|_| self.word()).collect();
+            if !items.is_empty() && self.chance() {
+                *items.last_mut().unwrap() = self.text();
+            }
+            items
+        }
+
+        fn command(&mut self) -> Command {
+            match self.below(36) {
+                0 => Command::Pass(self.word()),
+                1 => Command::Nick(self.word()),
+                2 => Command::User {
+                    username: self.word(),
+                    mode: self.word(),
+                    unused: self.word(),
+                    realname: self.text(),
+                },
+                3 => Command::Oper {
+                    name: self.word(),
+                    password: self.word(),
+                },
+                4 => Command::Quit(self.opt_text()),
+                5 => Command::Join {
+                    channels: self.word(),
+                    keys: self.opt_text(),
+                },
+                6 => Command::Part {
+                    channels: self.word(),
+                    reason: self.opt_text(),
+                },
+                7 => {
+                    // A bare mode query on `ISIRCX` is `ModeIsIrcx`; `word` never yields it.
+                    let modes = self.chance().then(|| self.word());
+                    let args = if modes.is_some() {
+                        self.list()
+                    } else {
+                        Vec::new()
+                    };
+                    Command::Mode {
+                        target: self.word(),
+                        modes,
+                        args,
+                    }
+                }
+                8 => Command::Topic {
+                    channel: self.word(),
+                    topic: self.opt_text(),
+                },
+                9 => Command::Names(self.opt_text()),
+                10 => Command::List(self.opt_text()),
+                11 => Command::Invite {
+                    nick: self.word(),
+                    channel: self.word(),
+                },
+                12 => Command::Kick {
+                    channel: self.word(),
+                    nick: self.word(),
+                    reason: self.opt_text(),
+                },
+                13 => Command::Privmsg {
+                    target: self.word(),
+                    text: self.text(),
+                },
+                14 => Command::Notice {
+                    target: self.word(),
+                    text: self.text(),
+                },
+                15 => Command::Who(self.opt_text()),
+                16 => Command::Whois(self.word()),
+                17 => Command::Ping(self.text()),
+                18 => Command::Pong(self.text()),
+                19 => Command::Away(self.opt_text()),
+                20 => Command::Error(self.text()),
+                21 => {
+                    let sequence = [
+                        AuthSequence::Initial,
+                        AuthSequence::Subsequent,
+                        AuthSequence::Complete,
+                    ][self.below(3)];
+                    let data = match sequence {
+                        AuthSequence::Complete => self.list(),
+                        _ => {
+                            let mut data: Vec<_> =
+                                (0..self.below(3)).map(|_| self.word()).collect();
+                            data.push(self.text());
+                            data
+                        }
+                    };
+                    Command::Auth {
+                        package: self.word(),
+                        sequence,
+                        data,
+                    }
+                }
+                22 => Command::Ircvers {
+                    version: self.word(),
+                    client: self.word(),
+                },
+                23 => Command::IsIrcx,
+                24 => Command::ModeIsIrcx,
+                25 => Command::Prop {
+                    target: self.word(),
+                    property: self.word(),
+                    value: self.opt_text(),
+                },
+                26 => {
+                    let operation = self.chance().then(|| self.word());
+                    let args = if operation.is_some() {
+                        self.list()
+                    } else {
+                        Vec::new()
+                    };
+                    Command::Access {
+                        target: self.word(),
+                        operation,
+                        args,
+                    }
+                }
+                27 => Command::Whisper {
+                    channel: self.word(),
+                    targets: self.word(),
+                    text: self.text(),
+                },
+                28 => Command::Data {
+                    target: self.word(),
+                    tag: self.word(),
+                    payload: self.text(),
+                },
+                29 => Command::Request {
+                    target: self.word(),
+                    tag: self.word(),
+                    payload: self.text(),
+                },
+                30 => Command::Reply {
+                    target: self.word(),
+                    tag: self.word(),
+                    payload: self.text(),
+                },
+                31 => Command::Knock {
+                    channel: self.word(),
+                    reason: self.word(),
+                },
+                32 => Command::Create {
+                    channel: self.word(),
+                    args: self.list(),
+                },
+                33 => Command::Listx(self.list()),
+                34 => Command::Event {
+                    action: self.word(),
+                    args: self.list(),
+                },
+                _ => Command::Other {
+                    command: ["SILENCE", "001", "353", "818"][self.below(4)].into(),
+                    params: self.list(),
+                },
+            }
+        }
+    }
+
+    #[test]
+    fn generated_commands_round_trip() {
+        for seed in 0..8 {
+            let mut rng = Lcg(seed);
+            for _ in 0..500 {
+                let command = rng.command();
+                let line = command.to_line().unwrap_or_else(|err| {
+                    panic!("seed {seed}: {command:?} failed to encode: {err}")
+                });
+                let parsed = Command::parse(line.trim_end_matches("\r\n"))
+                    .unwrap_or_else(|err| panic!("seed {seed}: {line:?} failed to parse: {err}"));
+                assert_eq!(parsed, command, "seed {seed}: {line:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn mode_isircx_is_canonical() {
+        let probe = Command::Mode {
+            target: "ISIRCX".into(),
+            modes: None,
+            args: Vec::new(),
+        };
+        assert_eq!(probe.to_string(), Command::ModeIsIrcx.to_string());
+        assert_eq!(Command::parse(&probe.to_string()), Ok(Command::ModeIsIrcx));
+        assert_eq!(Command::parse("mode isircx"), Ok(Command::ModeIsIrcx));
+    }
+
+    #[test]
+    fn missing_and_invalid_params() {
+        assert_eq!(
+            Command::parse("KICK %#Lobby"),
+            Err(CommandError::MissingParams {
+                command: "KICK".into(),
+                expected: 2,
+            })
+        );
+        assert_eq!(
+            Command::parse("AUTH GateKeeper X :blob"),
+            Err(CommandError::InvalidParam {
+                command: "AUTH".into(),
+                index: 1,
+            })
+        );
+    }
+}
@@ -0,0 +1,69 @@
+use std::fmt;
+
+/// An unknown `\x` sequence or a dangling backslash in escaped IRCX data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EscapeError {
+    pub offset: usize,
+}
+
+impl fmt::Display for EscapeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid escape sequence at byte {}", self.offset)
+    }
+}
+
+impl std::error::Error for EscapeError {}
+
+/// Escapes binary data for use as an IRCX parameter (`AUTH` blobs, `PROP` values).
+///
+/// `\` → `\\`, NUL → `\0`, TAB → `\t`, LF → `\n`, CR → `\r`, space → `\b`, `,` → `\c`.
+pub fn escape(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+    for &b in data {
+        match b {
+            b'\\' => out.extend_from_slice(b"\\\\"),
+            b'\0' => out.extend_from_slice(b"\\0"),
+            b'\t' => out.extend_from_slice(b"\\t"),
+            b'\n' => out.extend_from_slice(b"\\n"),
+            b'\r' => out.extend_from_slice(b"\\r"),
+            b' ' => out.extend_from_slice(b"\\b"),
+            b',' => out.extend_from_slice(b"\\c"),
+            b => out.push(b),
+        }
+    }
+    out
+}
+
+/// Reverses [`escape`].
+pub fn unescape(data: &[u8]) -> Result<Vec<u8>, EscapeError> {
+    let mut out = Vec::with_capacity(data.len());
+    let mut bytes = data.iter().enumerate();
+    while let Some((offset, &b)) = bytes.next() {
+        if b != b'\\' {
+            out.push(b);
+            continue;
+        }
+        let decoded = match bytes.next() {
+            Some((_, b'\\')) => b'\\',
+            Some((_, b'0')) => b'\0',
+            Some((_, b't')) => b'\t',
+            Some((_, b'n')) => b'\n',
+            Some((_, b'r')) => b'\r',
+            Some((_, b'b')) => b' ',
+            Some((_, b'c')) => b',',
+            _ => return Err(EscapeError { offset }),
+        };
+        out.push(decoded);
+    }
+    Ok(out)
+}
+
+/// [`escape`] for text values.
+pub fn escape_str(text: &str) -> String {
+    String::from_utf8(escape(text.as_bytes())).expect("escaping only replaces ASCII bytes")
+}
+
+/// [`unescape`] for text values; invalid UTF-8 is replaced.
+pub fn unescape_str(text: &str) -> Result<String, EscapeError> {
+    Ok(String::from_utf8_lossy(&unescape(text.as_bytes())?).into_owned())
+}
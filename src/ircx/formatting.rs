@@ -0,0 +1,541 @@
+use std::fmt::{self, Write};
+
+/// Opens a font envelope: `\x01S <color><style><face>;<charset> <text>\x01`.
+///
+/// The color byte is the palette index plus one and the style byte is the
+/// [`Style`] bits plus one, so neither can be NUL on the wire. Black is the
+/// byte `\x01`, the same as the delimiter, so both are read by position.
+pub const FONT_ENVELOPE: &str = "\x01S ";
+
+const DELIMITER: char = '\x01';
+
+/// Why a span cannot be put in an envelope.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FormatError {
+    /// Empty, or contains `;` or a control character.
+    InvalidFace(String),
+    /// Styled text containing the `\x01` delimiter.
+    DelimiterInText(String),
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FormatError::InvalidFace(face) => write!(f, "invalid font face `{face}`"),
+            FormatError::DelimiterInText(text) => {
+                write!(f, "styled text {text:?} contains the envelope delimiter")
+            }
+        }
+    }
+}
+
+impl std::error::Error for FormatError {}
+
+/// One of the 16 colors of the chat control's palette.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Color(u8);
+
+impl Color {
+    pub const BLACK: Color = Color(0);
+    pub const WHITE: Color = Color(1);
+    pub const MAROON: Color = Color(2);
+    pub const GREEN: Color = Color(3);
+    pub const NAVY: Color = Color(4);
+    pub const OLIVE: Color = Color(5);
+    pub const PURPLE: Color = Color(6);
+    pub const TEAL: Color = Color(7);
+    pub const SILVER: Color = Color(8);
+    pub const GRAY: Color = Color(9);
+    pub const RED: Color = Color(10);
+    pub const LIME: Color = Color(11);
+    pub const BLUE: Color = Color(12);
+    pub const YELLOW: Color = Color(13);
+    pub const FUCHSIA: Color = Color(14);
+    pub const AQUA: Color = Color(15);
+
+    const PALETTE: [(u32, u8); 16] = [
+        (0x000000, 30),
+        (0xFFFFFF, 97),
+        (0x800000, 31),
+        (0x008000, 32),
+        (0x000080, 34),
+        (0x808000, 33),
+        (0x800080, 35),
+        (0x008080, 36),
+        (0xC0C0C0, 37),
+        (0x808080, 90),
+        (0xFF0000, 91),
+        (0x00FF00, 92),
+        (0x0000FF, 94),
+        (0xFFFF00, 93),
+        (0xFF00FF, 95),
+        (0x00FFFF, 96),
+    ];
+
+    /// `None` outside the palette (`0..16`).
+    pub fn new(index: u8) -> Option<Color> {
+        (usize::from(index) < Self::PALETTE.len()).then_some(Color(index))
+    }
+
+    pub fn index(self) -> u8 {
+        self.0
+    }
+
+    /// `0xRRGGBB`.
+    pub fn rgb(self) -> u32 {
+        Self::PALETTE[usize::from(self.0)].0
+    }
+
+    /// Nearest SGR foreground code of the 16-color terminal palette.
+    pub fn ansi_code(self) -> u8 {
+        Self::PALETTE[usize::from(self.0)].1
+    }
+
+    fn to_wire(self) -> char {
+        char::from(self.0 + 1)
+    }
+
+    fn from_wire(c: char) -> Option<Color> {
+        Color::new(u8::try_from(c).ok()?.checked_sub(1)?)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Style {
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+}
+
+impl Style {
+    pub fn is_plain(self) -> bool {
+        self == Style::default()
+    }
+
+    fn to_wire(self) -> char {
+        let bits = u8::from(self.bold) | u8::from(self.italic) << 1 | u8::from(self.underline) << 2;
+        char::from(bits + 1)
+    }
+
+    fn from_wire(c: char) -> Option<Style> {
+        let bits = u8::try_from(c).ok()?.checked_sub(1)?;
+        (bits < 8).then_some(Style {
+            bold: bits & 1 != 0,
+            italic: bits & 2 != 0,
+            underline: bits & 4 != 0,
+        })
+    }
+}
+
+/// The formatting carried by one envelope.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Font {
+    pub face: String,
+    /// Windows `LOGFONT` character set; 0 is ANSI.
+    pub charset: u8,
+    pub color: Color,
+    pub style: Style,
+}
+
+impl Font {
+    pub fn new(face: impl Into<String>) -> Self {
+        Self {
+            face: face.into(),
+            charset: 0,
+            color: Color::BLACK,
+            style: Style::default(),
+        }
+    }
+
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn with_style(mut self, style: Style) -> Self {
+        self.style = style;
+        self
+    }
+
+    pub fn with_charset(mut self, charset: u8) -> Self {
+        self.charset = charset;
+        self
+    }
+
+    pub fn validate(&self) -> Result<(), FormatError> {
+        if self.face.is_empty() || self.face.contains(|c: char| c == ';' || c.is_control()) {
+            return Err(FormatError::InvalidFace(self.face.clone()));
+        }
+        Ok(())
+    }
+}
+
+/// A run of text, inside an envelope when `font` is set.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Span {
+    pub font: Option<Font>,
+    pub text: String,
+}
+
+impl Span {
+    pub fn plain(text: impl Into<String>) -> Self {
+        Self {
+            font: None,
+            text: text.into(),
+        }
+    }
+
+    pub fn styled(font: Font, text: impl Into<String>) -> Self {
+        Self {
+            font: Some(font),
+            text: text.into(),
+        }
+    }
+}
+
+/// Message text split into font envelopes and the plain text between them.
+///
+/// Parsing never fails: anything that is not a well-formed envelope stays
+/// plain text, so [`Display`](fmt::Display) reproduces the input exactly.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct FormattedText {
+    spans: Vec<Span>,
+}
+
+impl FormattedText {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn parse(text: &str) -> Self {
+        let mut formatted = FormattedText::new();
+        let mut rest = text;
+        while !rest.is_empty() {
+            let Some(start) = rest.find(FONT_ENVELOPE) else {
+                formatted.push(Span::plain(rest));
+                break;
+            };
+            formatted.push(Span::plain(&rest[..start]));
+            rest = &rest[start..];
+            match parse_envelope(rest) {
+                Some((span, len)) => {
+                    formatted.push(span);
+                    rest = &rest[len..];
+                }
+                None => {
+                    // Keep the delimiter as text and look for the next envelope.
+                    formatted.push(Span::plain(&rest[..1]));
+                    rest = &rest[1..];
+                }
+            }
+        }
+        formatted
+    }
+
+    pub fn spans(&self) -> &[Span] {
+        &self.spans
+    }
+
+    /// Appends a span, merging adjacent plain text.
+    pub fn push(&mut self, span: Span) {
+        if span.font.is_none() {
+            if span.text.is_empty() {
+                return;
+            }
+            if let Some(last) = self.spans.last_mut().filter(|s| s.font.is_none()) {
+                last.text.push_str(&span.text);
+                return;
+            }
+        }
+        self.spans.push(span);
+    }
+
+    /// The wire form, after checking every envelope renders unambiguously.
+    ///
+    /// [`Display`](fmt::Display) writes the same bytes without the checks.
+    pub fn render(&self) -> Result<String, FormatError> {
+        for span in &self.spans {
+            if let Some(font) = &span.font {
+                font.validate()?;
+                if span.text.contains(DELIMITER) {
+                    return Err(FormatError::DelimiterInText(span.text.clone()));
+                }
+            }
+        }
+        Ok(self.to_string())
+    }
+
+    pub fn is_formatted(&self) -> bool {
+        self.spans.iter().any(|s| s.font.is_some())
+    }
+
+    /// The text with every envelope removed.
+    pub fn plain(&self) -> String {
+        self.spans.iter().map(|s| s.text.as_str()).collect()
+    }
+
+    /// HTML with each envelope as a styled `<span>`; text is escaped.
+    pub fn to_html(&self) -> String {
+        let mut html = String::new();
+        for span in &self.spans {
+            let Some(font) = &span.font else {
+                html_escape(&mut html, &span.text);
+                continue;
+            };
+            let face: String = font
+                .face
+                .chars()
+                .filter(|c| c.is_alphanumeric() || matches!(c, ' ' | '-' | '_'))
+                .collect();
+            let _ = write!(
+                html,
+                "<span style=\"font-family:&#39;{face}&#39;;color:#{:06X}",
+                font.color.rgb()
+            );
+            if font.style.bold {
+                html.push_str(";font-weight:bold");
+            }
+            if font.style.italic {
+                html.push_str(";font-style:italic");
+            }
+            if font.style.underline {
+                html.push_str(";text-decoration:underline");
+            }
+            html.push_str("\">");
+            html_escape(&mut html, &span.text);
+            html.push_str("</span>");
+        }
+        html
+    }
+
+    /// Text with SGR escape codes for a 16-color terminal.
+    pub fn to_ansi(&self) -> String {
+        let mut ansi = String::new();
+        for span in &self.spans {
+            let Some(font) = &span.font else {
+                ansi.push_str(&span.text);
+                continue;
+            };
+            let _ = write!(ansi, "\x1b[{}", font.color.ansi_code());
+            if font.style.bold {
+                ansi.push_str(";1");
+            }
+            if font.style.italic {
+                ansi.push_str(";3");
+            }
+            if font.style.underline {
+                ansi.push_str(";4");
+            }
+            ansi.push('m');
+            ansi.push_str(&span.text);
+            ansi.push_str("\x1b[0m");
+        }
+        ansi
+    }
+}
+
+/// Renders the wire form, envelopes included; see [`FormattedText::render`].
+impl fmt::Display for FormattedText {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for span in &self.spans {
+            match &span.font {
+                Some(font) => write!(
+                    f,
+                    "{FONT_ENVELOPE}{}{}{};{} {}{DELIMITER}",
+                    font.color.to_wire(),
+                    font.style.to_wire(),
+                    font.face,
+                    font.charset,
+                    span.text
+                )?,
+                None => f.write_str(&span.text)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl From<&str> for FormattedText {
+    fn from(text: &str) -> Self {
+        FormattedText::parse(text)
+    }
+}
+
+impl From<Span> for FormattedText {
+    fn from(span: Span) -> Self {
+        let mut formatted = FormattedText::new();
+        formatted.push(span);
+        formatted
+    }
+}
+
+impl FromIterator<Span> for FormattedText {
+    fn from_iter<I: IntoIterator<Item = Span>>(spans: I) -> Self {
+        let mut formatted = FormattedText::new();
+        spans.into_iter().for_each(|s| formatted.push(s));
+        formatted
+    }
+}
+
+/// Parses the envelope at the start of `text`, returning it and its length.
+///
+/// Envelopes that would not render back to the same bytes are rejected.
+fn parse_envelope(text: &str) -> Option<(Span, usize)> {
+    let mut chars = text.strip_prefix(FONT_ENVELOPE)?.chars();
+    // The color and style bytes may be `\x01`, so only look for the closing
+    // delimiter after them.
+    let color = Color::from_wire(chars.next()?)?;
+    let style = Style::from_wire(chars.next()?)?;
+    let rest = chars.as_str();
+    let end = rest.find(DELIMITER)?;
+
+    let (face, rest) = rest[..end].split_once(';')?;
+    let (charset, content) = rest.split_once(' ')?;
+    let parsed_charset = charset.parse::<u8>().ok()?;
+    let font = Font {
+        face: face.to_owned(),
+        charset: parsed_charset,
+        color,
+        style,
+    };
+    if font.validate().is_err() || parsed_charset.to_string() != charset {
+        return None;
+    }
+    let len = text.len() - chars.as_str().len() + end + DELIMITER.len_utf8();
+    Some((Span::styled(font, content), len))
+}
+
+fn html_escape(out: &mut String, text: &str) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn styles() -> impl Iterator<Item = Style> {
+        (0..8u8).map(|bits| Style {
+            bold: bits & 1 != 0,
+            italic: bits & 2 != 0,
+            underline: bits & 4 != 0,
+        })
+    }
+
+    #[test]
+    fn every_color_and_style_round_trips() {
+        for index in 0..16 {
+            let color = Color::new(index).unwrap();
+            for style in styles() {
+                let font = Font::new("Comic Sans MS")
+                    .with_color(color)
+                    .with_style(style)
+                    .with_charset(204);
+                let text: FormattedText = [
+                    Span::plain("before "),
+                    Span::styled(font.clone(), "hi there"),
+                    Span::plain(" after"),
+                ]
+                .into_iter()
+                .collect();
+
+                let wire = text.render().unwrap();
+                let header = &wire.as_bytes()["before ".len() + FONT_ENVELOPE.len()..][..2];
+                assert_eq!(header, [index + 1, style.to_wire() as u8], "{wire:?}");
+                assert_eq!(FormattedText::parse(&wire), text, "{wire:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn wire_encoding() {
+        let font = Font::new("Arial")
+            .with_color(Color::AQUA)
+            .with_style(Style {
+                bold: true,
+                italic: false,
+                underline: true,
+            });
+        let text = FormattedText::from(Span::styled(font, "hello"));
+        assert_eq!(text.render().unwrap(), "\x01S \x10\x06Arial;0 hello\x01");
+        assert_eq!(
+            FormattedText::from(Span::styled(Font::new("Arial"), "x")).to_string(),
+            "\x01S \x01\x01Arial;0 x\x01"
+        );
+    }
+
+    #[test]
+    fn black_on_bold_round_trips() {
+        // The color byte of black is the delimiter itself.
+        let wire = "\x01S \x01\x02Arial;0 text\x01";
+        let bold = Style {
+            bold: true,
+            ..Style::default()
+        };
+        let text = FormattedText::parse(wire);
+        assert_eq!(
+            text.spans(),
+            [Span::styled(
+                Font::new("Arial").with_color(Color::BLACK).with_style(bold),
+                "text"
+            )]
+        );
+        assert_eq!(text.render().unwrap(), wire);
+
+        let wire = format!("hi {wire} there");
+        let text = FormattedText::parse(&wire);
+        assert_eq!(text.spans().len(), 3);
+        assert_eq!(text.plain(), "hi text there");
+        assert_eq!(text.to_string(), wire);
+    }
+
+    #[test]
+    fn render_rejects_ambiguous_envelopes() {
+        let styled =
+            |face: &str, text: &str| FormattedText::from(Span::styled(Font::new(face), text));
+        assert_eq!(
+            styled("Ari;al", "x").render(),
+            Err(FormatError::InvalidFace("Ari;al".into()))
+        );
+        assert_eq!(
+            styled("", "x").render(),
+            Err(FormatError::InvalidFace(String::new()))
+        );
+        assert_eq!(
+            styled("Arial", "a\x01b").render(),
+            Err(FormatError::DelimiterInText("a\x01b".into()))
+        );
+        assert_eq!(
+            FormattedText::from(Span::plain("a\x01b")).render().unwrap(),
+            "a\x01b"
+        );
+    }
+
+    #[test]
+    fn malformed_envelopes_stay_text() {
+        for wire in [
+            "\x01S \x00\x01Arial;0 x\x01",
+            "\x01S \x11\x01Arial;0 x\x01",
+            "\x01S \x01\x00Arial;0 x\x01",
+            "\x01S \x01\x09Arial;0 x\x01",
+            "\x01S 00Arial;0 x\x01",
+            "\x01S \x01\x01;0 x\x01",
+            "\x01S \x01\x01Arial;00 x\x01",
+            "\x01S \x01\x01Arial 0 x\x01",
+            "\x01S \x01\x01Arial;0 x",
+            "\x01S \x01",
+        ] {
+            let text = FormattedText::parse(wire);
+            assert!(!text.is_formatted(), "{wire:?}");
+            assert_eq!(text.to_string(), wire);
+        }
+    }
+}
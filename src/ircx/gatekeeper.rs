@@ -0,0 +1,550 @@
+//! Sans-IO implementation of the GateKeeper (GKSSP) security package.
+//!
+//! The exchange is:
+//!
+//! ```text
+//! C: AUTH GateKeeper I :GKSSP\0\0\0<version>\0\0\0<seq=1>\0\0\0
+//! S: AUTH GateKeeper S :GKSSP\0...<seq=2><8-byte challenge>
+//! C: AUTH GateKeeper S :GKSSP\0...<seq=3><16-byte HMAC-MD5><16-byte GUID>
+//! S: AUTH GateKeeper * <GUID>@GateKeeper 0
+//! ```
+//!
+//! `GateKeeperPassport` inserts an `S :OK` / ticket-and-profile round trip
+//! before the final `*` (see [`passport`](super::passport)).
+//!
+//! Payloads are binary and travel IRCX-escaped, so this module works on raw
+//! line bytes rather than on [`Message`](super::Message).
+
+use std::{fmt, io, path::Path, str::FromStr};
+
+use hmac::{Hmac, Mac};
+use md5::Md5;
+
+use super::{
+    escape::{EscapeError, escape, unescape},
+    passport::PassportCredentials,
+};
+
+pub const GATEKEEPER: &str = "GateKeeper";
+pub const GATEKEEPER_PASSPORT: &str = "GateKeeperPassport";
+
+/// Protocol version sent by the 4.5-era OCX.
+pub const DEFAULT_VERSION: u32 = 3;
+
+const SIGNATURE: &[u8; 8] = b"GKSSP\0\0\0";
+const HEADER_LEN: usize = 16;
+const CHALLENGE_LEN: usize = 8;
+const HMAC_KEY: &[u8; 16] = b"SRFMKSJANDRESKKC";
+
+/// Errors raised while running the GateKeeper exchange.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GatekeeperError {
+    BadSignature,
+    Truncated,
+    UnsupportedVersion(u32),
+    UnexpectedSequence {
+        expected: u32,
+        got: u32,
+    },
+    /// The line is not an `AUTH` reply for this package.
+    UnexpectedLine,
+    Escape(EscapeError),
+    /// `handle_line` was called before `start` or after completion.
+    InvalidState,
+    /// Not 32 hex digits, optionally followed by `@<package>`.
+    InvalidId(String),
+}
+
+impl fmt::Display for GatekeeperError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GatekeeperError::BadSignature => write!(f, "missing GKSSP signature"),
+            GatekeeperError::Truncated => write!(f, "GKSSP message truncated"),
+            GatekeeperError::UnsupportedVersion(v) => write!(f, "unsupported GKSSP version {v}"),
+            GatekeeperError::UnexpectedSequence { expected, got } => {
+                write!(f, "expected GKSSP sequence {expected}, got {got}")
+            }
+            GatekeeperError::UnexpectedLine => write!(f, "unexpected AUTH line"),
+            GatekeeperError::Escape(err) => err.fmt(f),
+            GatekeeperError::InvalidState => write!(f, "GateKeeper exchange is not in progress"),
+            GatekeeperError::InvalidId(s) => write!(f, "malformed GateKeeper id `{s}`"),
+        }
+    }
+}
+
+impl std::error::Error for GatekeeperError {}
+
+impl From<EscapeError> for GatekeeperError {
+    fn from(err: EscapeError) -> Self {
+        GatekeeperError::Escape(err)
+    }
+}
+
+/// The persistent GUID that identifies a GateKeeper user across sessions.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GatekeeperId([u8; 16]);
+
+impl GatekeeperId {
+    pub fn from_bytes(bytes: [u8; 16]) -> Self {
+        Self(bytes)
+    }
+
+    /// Generates a fresh random identity.
+    pub fn generate() -> Self {
+        let mut bytes = [0u8; 16];
+        getrandom::getrandom(&mut bytes).expect("system random number generator unavailable");
+        // RFC 4122 version 4 / variant 1, as CoCreateGuid produces.
+        bytes[7] = (bytes[7] & 0x0f) | 0x40;
+        bytes[8] = (bytes[8] & 0x3f) | 0x80;
+        Self(bytes)
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 16] {
+        &self.0
+    }
+
+    /// Reads an identity saved by [`GatekeeperId::save`], creating and saving one if absent.
+    pub fn load_or_create(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        match std::fs::read_to_string(path) {
+            Ok(text) => text
+                .trim()
+                .parse()
+                .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "malformed GateKeeper id")),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                let id = Self::generate();
+                id.save(path)?;
+                Ok(id)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        std::fs::write(path, self.to_string())
+    }
+}
+
+impl fmt::Display for GatekeeperId {
+    /// 32 upper-case hex digits, as the server echoes in `<id>@GateKeeper`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.iter().try_for_each(|b| write!(f, "{b:02X}"))
+    }
+}
+
+impl fmt::Debug for GatekeeperId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "GatekeeperId({self})")
+    }
+}
+
+impl FromStr for GatekeeperId {
+    type Err = GatekeeperError;
+
+    /// Accepts the bare hex form or an account such as `<id>@GateKeeper` or `<id>@passport`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hex = s.split_once('@').map_or(s, |(hex, _)| hex);
+        let invalid = || GatekeeperError::InvalidId(s.to_owned());
+        if hex.len() != 32 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(invalid());
+        }
+        let mut bytes = [0u8; 16];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).map_err(|_| invalid())?;
+        }
+        Ok(Self(bytes))
+    }
+}
+
+/// Builds the 16-byte GKSSP header.
+pub fn encode_header(version: u32, sequence: u32) -> [u8; HEADER_LEN] {
+    let mut header = [0u8; HEADER_LEN];
+    header[..8].copy_from_slice(SIGNATURE);
+    header[8..12].copy_from_slice(&version.to_le_bytes());
+    header[12..16].copy_from_slice(&sequence.to_le_bytes());
+    header
+}
+
+/// Splits an unescaped GKSSP message into `(version, sequence, payload)`.
+pub fn decode_header(data: &[u8]) -> Result<(u32, u32, &[u8]), GatekeeperError> {
+    if data.len() < HEADER_LEN {
+        return Err(GatekeeperError::Truncated);
+    }
+    if &data[..5] != b"GKSSP" {
+        return Err(GatekeeperError::BadSignature);
+    }
+    let version = u32::from_le_bytes(data[8..12].try_into().unwrap());
+    let sequence = u32::from_le_bytes(data[12..16].try_into().unwrap());
+    Ok((version, sequence, &data[HEADER_LEN..]))
+}
+
+/// HMAC-MD5 of the server challenge.
+///
+/// From version 3 on, the server address the client connected to (the frame's
+/// `Server` value, e.g. `207.68.167.253:6667`) is appended to the challenge.
+pub fn challenge_response(challenge: &[u8], version: u32, server_address: &str) -> [u8; 16] {
+    let mut mac = Hmac::<Md5>::new_from_slice(HMAC_KEY).expect("HMAC accepts any key length");
+    mac.update(challenge);
+    if version >= 3 {
+        mac.update(server_address.as_bytes());
+    }
+    mac.finalize().into_bytes().into()
+}
+
+/// Result of feeding a server line to [`Gatekeeper::handle_line`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GatekeeperStep {
+    /// A line (CRLF-terminated) to send to the server.
+    Send(Vec<u8>),
+    /// The server accepted the identity; `account` is e.g. `<GUID>@GateKeeper`.
+    Authenticated { account: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GatekeeperState {
+    Idle,
+    AwaitingChallenge,
+    /// Passport only: waiting for the server's `OK` before sending credentials.
+    AwaitingPassportRequest,
+    AwaitingResult,
+    Authenticated {
+        account: String,
+    },
+}
+
+/// Client side of a GateKeeper exchange.
+#[derive(Debug, Clone)]
+pub struct Gatekeeper {
+    package: &'static str,
+    version: u32,
+    id: GatekeeperId,
+    server_address: String,
+    passport: Option<PassportCredentials>,
+    state: GatekeeperState,
+}
+
+impl Gatekeeper {
+    /// An anonymous (`GateKeeper`) exchange for `id`.
+    pub fn new(id: GatekeeperId, server_address: impl Into<String>) -> Self {
+        Self {
+            package: GATEKEEPER,
+            version: DEFAULT_VERSION,
+            id,
+            server_address: server_address.into(),
+            passport: None,
+            state: GatekeeperState::Idle,
+        }
+    }
+
+    /// A `GateKeeperPassport` exchange that presents `credentials` after the challenge.
+    pub fn passport(
+        id: GatekeeperId,
+        server_address: impl Into<String>,
+        credentials: PassportCredentials,
+    ) -> Self {
+        Self {
+            package: GATEKEEPER_PASSPORT,
+            passport: Some(credentials),
+            ..Self::new(id, server_address)
+        }
+    }
+
+    pub fn with_version(mut self, version: u32) -> Self {
+        self.version = version;
+        self
+    }
+
+    pub fn package(&self) -> &'static str {
+        self.package
+    }
+
+    pub fn id(&self) -> GatekeeperId {
+        self.id
+    }
+
+    pub fn state(&self) -> &GatekeeperState {
+        &self.state
+    }
+
+    /// The opening `AUTH <package> I` line.
+    pub fn start(&mut self) -> Vec<u8> {
+        self.state = GatekeeperState::AwaitingChallenge;
+        auth_line(self.package, b'I', &encode_header(self.version, 1))
+    }
+
+    /// Feeds one raw server line (with or without CRLF).
+    pub fn handle_line(&mut self, line: &[u8]) -> Result<GatekeeperStep, GatekeeperError> {
+        let auth = AuthLine::parse(line)
+            .filter(|a| a.package.eq_ignore_ascii_case(self.package.as_bytes()))
+            .ok_or(GatekeeperError::UnexpectedLine)?;
+
+        match (&self.state, auth.sequence) {
+            (GatekeeperState::AwaitingChallenge, b'S') => {
+                let data = unescape(auth.data)?;
+                let (version, sequence, challenge) = decode_header(&data)?;
+                if sequence != 2 {
+                    return Err(GatekeeperError::UnexpectedSequence {
+                        expected: 2,
+                        got: sequence,
+                    });
+                }
+                if version == 0 || version > DEFAULT_VERSION {
+                    return Err(GatekeeperError::UnsupportedVersion(version));
+                }
+                let challenge = challenge
+                    .get(..CHALLENGE_LEN)
+                    .ok_or(GatekeeperError::Truncated)?;
+
+                let mut response = encode_header(version, 3).to_vec();
+                response.extend_from_slice(&challenge_response(
+                    challenge,
+                    version,
+                    &self.server_address,
+                ));
+                response.extend_from_slice(self.id.as_bytes());
+
+                self.state = match self.passport {
+                    Some(_) => GatekeeperState::AwaitingPassportRequest,
+                    None => GatekeeperState::AwaitingResult,
+                };
+                Ok(GatekeeperStep::Send(auth_line(
+                    self.package,
+                    b'S',
+                    &response,
+                )))
+            }
+            (GatekeeperState::AwaitingPassportRequest, b'S') if auth.data == b"OK" => {
+                let payload = self
+                    .passport
+                    .as_ref()
+                    .map(PassportCredentials::encode_payload)
+                    .ok_or(GatekeeperError::InvalidState)?;
+                self.state = GatekeeperState::AwaitingResult;
+                Ok(GatekeeperStep::Send(auth_line(
+                    self.package,
+                    b'S',
+                    &payload,
+                )))
+            }
+            (GatekeeperState::AwaitingResult, b'*') => {
+                let account = String::from_utf8_lossy(split_word(auth.data).0).into_owned();
+                self.state = GatekeeperState::Authenticated {
+                    account: account.clone(),
+                };
+                Ok(GatekeeperStep::Authenticated { account })
+            }
+            (GatekeeperState::Idle | GatekeeperState::Authenticated { .. }, _) => {
+                Err(GatekeeperError::InvalidState)
+            }
+            _ => Err(GatekeeperError::UnexpectedLine),
+        }
+    }
+}
+
+/// Formats `AUTH <package> <sequence> :<escaped payload>\r\n`.
+pub(crate) fn auth_line(package: &str, sequence: u8, payload: &[u8]) -> Vec<u8> {
+    let mut line = format!("AUTH {package} ").into_bytes();
+    line.push(sequence);
+    line.extend_from_slice(b" :");
+    line.extend_from_slice(&escape(payload));
+    line.extend_from_slice(b"\r\n");
+    line
+}
+
+/// A server `AUTH` line split at the byte level.
+pub(crate) struct AuthLine<'a> {
+    pub package: &'a [u8],
+    pub sequence: u8,
+    /// The trailing blob for `S`, or the remaining parameters for `*`.
+    pub data: &'a [u8],
+}
+
+impl<'a> AuthLine<'a> {
+    pub fn parse(line: &'a [u8]) -> Option<Self> {
+        let line = line
+            .strip_suffix(b"\r\n")
+            .or_else(|| line.strip_suffix(b"\n"))
+            .unwrap_or(line);
+        let mut rest = line;
+        if rest.first() == Some(&b':') {
+            rest = split_word(rest).1;
+        }
+
+        let (command, rest) = split_word(rest);
+        if !command.eq_ignore_ascii_case(b"AUTH") {
+            return None;
+        }
+        let (package, rest) = split_word(rest);
+        let (sequence, rest) = split_word(rest);
+        let &[sequence] = sequence else { return None };
+
+        Some(Self {
+            package,
+            sequence,
+            data: rest.strip_prefix(b":").unwrap_or(rest),
+        })
+    }
+}
+
+fn split_word(bytes: &[u8]) -> (&[u8], &[u8]) {
+    match bytes.iter().position(|&b| b == b' ') {
+        Some(i) => (&bytes[..i], &bytes[i + 1..]),
+        None => (bytes, &[]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SERVER: &str = "207.68.167.253:6667";
+    /// Bytes that all need escaping: NUL, space, comma, backslash, LF, CR, TAB.
+    const CHALLENGE: [u8; 8] = [0x00, 0x20, 0x2c, 0x5c, 0x0a, 0x0d, 0x09, 0x7f];
+
+    fn id() -> GatekeeperId {
+        GatekeeperId::from_bytes(std::array::from_fn(|i| i as u8))
+    }
+
+    fn hex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    /// Runs the anonymous exchange against a recorded challenge for `version`.
+    fn respond(version: u8) -> Vec<u8> {
+        let mut gk = Gatekeeper::new(id(), SERVER);
+        assert_eq!(
+            gk.start(),
+            b"AUTH GateKeeper I :GKSSP\\0\\0\\0\x03\\0\\0\\0\x01\\0\\0\\0\r\n"
+        );
+        let mut challenge = b":TK2CHATCHATA01 AUTH GateKeeper S :GKSSP\\0\\0\\0".to_vec();
+        challenge.push(version);
+        challenge.extend_from_slice(b"\\0\\0\\0\x02\\0\\0\\0\\0\\b\\c\\\\\\n\\r\\t\x7f\r\n");
+        match gk.handle_line(&challenge).unwrap() {
+            GatekeeperStep::Send(line) => line,
+            step => panic!("unexpected {step:?}"),
+        }
+    }
+
+    #[test]
+    fn hmac_without_server_before_version_3() {
+        let expected = hex("0adb1b48fdcf61b64addfa7ec35aea24");
+        assert_eq!(challenge_response(&CHALLENGE, 1, SERVER).to_vec(), expected);
+        assert_eq!(challenge_response(&CHALLENGE, 2, SERVER).to_vec(), expected);
+    }
+
+    #[test]
+    fn hmac_appends_server_from_version_3() {
+        assert_eq!(
+            challenge_response(&CHALLENGE, 3, SERVER).to_vec(),
+            hex("0a7d5a776ec0224b0205862df020405c")
+        );
+    }
+
+    #[test]
+    fn version_1_transcript() {
+        let expected: &[u8] = b"AUTH GateKeeper S :GKSSP\\0\\0\\0\x01\\0\\0\\0\x03\\0\\0\\0\
+            \\n\xdb\x1bH\xfd\xcfa\xb6J\xdd\xfa~\xc3Z\xea$\
+            \\0\x01\x02\x03\x04\x05\x06\x07\x08\\t\\n\x0b\x0c\\r\x0e\x0f\r\n";
+        assert_eq!(respond(1), expected);
+    }
+
+    #[test]
+    fn version_2_transcript() {
+        let expected: &[u8] = b"AUTH GateKeeper S :GKSSP\\0\\0\\0\x02\\0\\0\\0\x03\\0\\0\\0\
+            \\n\xdb\x1bH\xfd\xcfa\xb6J\xdd\xfa~\xc3Z\xea$\
+            \\0\x01\x02\x03\x04\x05\x06\x07\x08\\t\\n\x0b\x0c\\r\x0e\x0f\r\n";
+        assert_eq!(respond(2), expected);
+    }
+
+    #[test]
+    fn version_3_transcript() {
+        let expected: &[u8] = b"AUTH GateKeeper S :GKSSP\\0\\0\\0\x03\\0\\0\\0\x03\\0\\0\\0\
+            \\n}Zwn\xc0\"K\x02\x05\x86-\xf0\\b@\\\\\
+            \\0\x01\x02\x03\x04\x05\x06\x07\x08\\t\\n\x0b\x0c\\r\x0e\x0f\r\n";
+        assert_eq!(respond(3), expected);
+    }
+
+    #[test]
+    fn completes_with_account() {
+        let mut gk = Gatekeeper::new(id(), SERVER);
+        gk.start();
+        let mut challenge = b"AUTH GateKeeper S :".to_vec();
+        challenge.extend_from_slice(&escape(&[&encode_header(3, 2)[..], &CHALLENGE].concat()));
+        gk.handle_line(&challenge).unwrap();
+        assert_eq!(gk.state(), &GatekeeperState::AwaitingResult);
+
+        let step = gk
+            .handle_line(b"AUTH GateKeeper * 000102030405060708090A0B0C0D0E0F@GateKeeper 0")
+            .unwrap();
+        let account = "000102030405060708090A0B0C0D0E0F@GateKeeper".to_owned();
+        assert_eq!(
+            step,
+            GatekeeperStep::Authenticated {
+                account: account.clone()
+            }
+        );
+        assert_eq!(account.parse(), Ok(id()));
+        assert_eq!(
+            gk.handle_line(b"AUTH GateKeeper * x 0"),
+            Err(GatekeeperError::InvalidState)
+        );
+    }
+
+    #[test]
+    fn rejects_bad_challenges() {
+        let challenge = |version: u32, sequence: u32| {
+            let mut line = b"AUTH GateKeeper S :".to_vec();
+            line.extend_from_slice(&escape(
+                &[&encode_header(version, sequence)[..], &CHALLENGE].concat(),
+            ));
+            line
+        };
+        let mut gk = Gatekeeper::new(id(), SERVER);
+        assert_eq!(
+            gk.handle_line(&challenge(3, 2)),
+            Err(GatekeeperError::InvalidState)
+        );
+        gk.start();
+        assert_eq!(
+            gk.handle_line(&challenge(3, 3)),
+            Err(GatekeeperError::UnexpectedSequence {
+                expected: 2,
+                got: 3
+            })
+        );
+        assert_eq!(
+            gk.handle_line(&challenge(4, 2)),
+            Err(GatekeeperError::UnsupportedVersion(4))
+        );
+        assert_eq!(
+            gk.handle_line(b"AUTH GateKeeper S :GKSSP"),
+            Err(GatekeeperError::Truncated)
+        );
+        assert_eq!(
+            gk.handle_line(b"AUTH NTLM S :x"),
+            Err(GatekeeperError::UnexpectedLine)
+        );
+    }
+
+    #[test]
+    fn id_parsing() {
+        let hex = "000102030405060708090A0B0C0D0E0F";
+        assert_eq!(hex.parse(), Ok(id()));
+        assert_eq!(hex.to_lowercase().parse(), Ok(id()));
+        assert_eq!(format!("{hex}@passport").parse(), Ok(id()));
+        assert_eq!(id().to_string(), hex);
+
+        let not_hex = "000102030405060708090A0B0C0D0EZZ";
+        assert_eq!(
+            not_hex.parse::<GatekeeperId>(),
+            Err(GatekeeperError::InvalidId(not_hex.to_owned()))
+        );
+        assert!(matches!(
+            "0001@GateKeeper".parse::<GatekeeperId>(),
+            Err(GatekeeperError::InvalidId(_))
+        ));
+    }
+}
@@ -0,0 +1,652 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    str::FromStr,
+    time::{Duration, SystemTime},
+};
+
+use super::{
+    ChatMessage, Command, ModerationAction, ModerationError, Numeric, Prefix, SessionEvent,
+};
+use crate::types::UserRole;
+
+pub const INVITATION_CODE_LEN: usize = 8;
+
+/// How long an issued code stays redeemable by default.
+pub const DEFAULT_INVITATION_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// CTCP command that carries a code alongside an `INVITE`.
+const INVITATION_CTCP: &str = "INVITATION";
+
+/// No `0`/`O` or `1`/`I`, so codes survive being read aloud or retyped.
+const ALPHABET: &[u8; 32] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InviteError {
+    /// Not a well-formed invitation code.
+    BadCode(String),
+    UnknownCode,
+    Expired,
+    /// The code was issued for another nick or channel.
+    WrongInvitee,
+    /// Only hosts and owners may invite into a restricted channel.
+    NotPermitted(UserRole),
+    /// No pending knock from this nick.
+    NoKnock(String),
+    Moderation(ModerationError),
+}
+
+impl fmt::Display for InviteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InviteError::BadCode(s) => write!(f, "invalid invitation code `{s}`"),
+            InviteError::UnknownCode => f.write_str("unknown invitation code"),
+            InviteError::Expired => f.write_str("invitation code has expired"),
+            InviteError::WrongInvitee => {
+                f.write_str("invitation code was issued for another nick or channel")
+            }
+            InviteError::NotPermitted(role) => write!(f, "role `{role}` may not invite"),
+            InviteError::NoKnock(nick) => write!(f, "no pending knock from {nick}"),
+            InviteError::Moderation(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for InviteError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            InviteError::Moderation(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<ModerationError> for InviteError {
+    fn from(err: ModerationError) -> Self {
+        InviteError::Moderation(err)
+    }
+}
+
+/// A short random token handed out with an invitation; the frame's `InvitationCode`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct InvitationCode(String);
+
+impl InvitationCode {
+    pub fn generate() -> Self {
+        let mut bytes = [0u8; INVITATION_CODE_LEN];
+        getrandom::getrandom(&mut bytes).expect("system random number generator unavailable");
+        Self(
+            bytes
+                .iter()
+                .map(|b| char::from(ALPHABET[usize::from(b % 32)]))
+                .collect(),
+        )
+    }
+
+    /// Accepts any case; the code is stored upper-case.
+    pub fn parse(s: &str) -> Result<Self, InviteError> {
+        let code = s.trim().to_ascii_uppercase();
+        if code.len() != INVITATION_CODE_LEN || !code.bytes().all(|b| ALPHABET.contains(&b)) {
+            return Err(InviteError::BadCode(s.to_owned()));
+        }
+        Ok(Self(code))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for InvitationCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl FromStr for InvitationCode {
+    type Err = InviteError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+/// An invitation of `nick` into `channel`, sent or received.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Invitation {
+    pub channel: String,
+    pub nick: String,
+    /// Who sent it; only known for received invitations.
+    pub from: Option<String>,
+    pub code: Option<InvitationCode>,
+}
+
+impl Invitation {
+    pub fn new(channel: impl Into<String>, nick: impl Into<String>) -> Self {
+        Self {
+            channel: channel.into(),
+            nick: nick.into(),
+            from: None,
+            code: None,
+        }
+    }
+
+    pub fn with_code(mut self, code: InvitationCode) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    /// `INVITE <nick> <channel>`, followed by the code as a CTCP `NOTICE`
+    /// `\x01INVITATION <channel> <code>\x01` when there is one.
+    pub fn commands(&self) -> Vec<Command> {
+        let mut commands = vec![Command::Invite {
+            nick: self.nick.clone(),
+            channel: self.channel.clone(),
+        }];
+        if let Some(code) = &self.code {
+            let ctcp = ChatMessage::Ctcp {
+                command: INVITATION_CTCP.to_owned(),
+                params: Some(format!("{} {code}", self.channel)),
+            };
+            commands.push(ctcp.to_notice(&self.nick));
+        }
+        commands
+    }
+
+    /// A received `INVITE`, or the `INVITATION` notice that carries its code.
+    pub fn from_event(event: &SessionEvent) -> Option<Self> {
+        let SessionEvent::Message { source, command } = event else {
+            return None;
+        };
+        let mut invitation = Self::from_command(command)?;
+        invitation.from = source.as_deref().map(|s| Prefix::parse(s).nick.to_owned());
+        Some(invitation)
+    }
+
+    pub fn from_command(command: &Command) -> Option<Self> {
+        match command {
+            Command::Invite { nick, channel } => Some(Self::new(channel, nick)),
+            Command::Notice { target, .. } => {
+                let ChatMessage::Ctcp {
+                    command,
+                    params: Some(params),
+                } = ChatMessage::from_command(command)?
+                else {
+                    return None;
+                };
+                if !command.eq_ignore_ascii_case(INVITATION_CTCP) {
+                    return None;
+                }
+                let (channel, code) = params.split_once(' ')?;
+                Some(Self::new(channel, target).with_code(code.parse().ok()?))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Issued {
+    channel: String,
+    nick: String,
+    expires_at: SystemTime,
+}
+
+/// Codes a host has handed out, redeemable once each until they expire.
+#[derive(Debug, Clone)]
+pub struct Invitations {
+    ttl: Duration,
+    issued: HashMap<InvitationCode, Issued>,
+}
+
+impl Default for Invitations {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Invitations {
+    pub fn new() -> Self {
+        Self::with_ttl(DEFAULT_INVITATION_TTL)
+    }
+
+    pub fn with_ttl(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            issued: HashMap::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.issued.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.issued.is_empty()
+    }
+
+    /// Generates a code for `nick` and returns the invitation to send.
+    pub fn issue(&mut self, channel: &str, nick: &str) -> Invitation {
+        let code = InvitationCode::generate();
+        self.issued.insert(
+            code.clone(),
+            Issued {
+                channel: channel.to_owned(),
+                nick: nick.to_owned(),
+                expires_at: SystemTime::now() + self.ttl,
+            },
+        );
+        Invitation::new(channel, nick).with_code(code)
+    }
+
+    /// Checks `code` was issued to `nick` for `channel` and has not expired.
+    pub fn validate(&self, code: &str, channel: &str, nick: &str) -> Result<(), InviteError> {
+        let code = InvitationCode::parse(code)?;
+        let issued = self.issued.get(&code).ok_or(InviteError::UnknownCode)?;
+        if issued.expires_at <= SystemTime::now() {
+            return Err(InviteError::Expired);
+        }
+        if !issued.channel.eq_ignore_ascii_case(channel) || !issued.nick.eq_ignore_ascii_case(nick)
+        {
+            return Err(InviteError::WrongInvitee);
+        }
+        Ok(())
+    }
+
+    /// Validates `code` and forgets it, so it cannot be used again.
+    pub fn redeem(&mut self, code: &str, channel: &str, nick: &str) -> Result<(), InviteError> {
+        self.validate(code, channel, nick)?;
+        self.issued.remove(&InvitationCode::parse(code)?);
+        Ok(())
+    }
+
+    pub fn revoke(&mut self, code: &InvitationCode) -> bool {
+        self.issued.remove(code).is_some()
+    }
+
+    /// Drops expired codes; returns how many were removed.
+    pub fn prune_expired(&mut self) -> usize {
+        let now = SystemTime::now();
+        let before = self.issued.len();
+        self.issued.retain(|_, issued| issued.expires_at > now);
+        before - self.issued.len()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KnockState {
+    Pending,
+    /// Invited; cleared once the knocker joins.
+    Accepted,
+    Denied,
+}
+
+/// Someone refused entry to a `+u` channel, as reported to its hosts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Knock {
+    pub channel: String,
+    pub nick: String,
+    /// `*!*@host` when the server sent the knocker's host, else `nick!*@*`.
+    pub mask: String,
+    /// Why the server refused the join, e.g. [`Numeric::InviteOnlyChan`].
+    pub reason: Numeric,
+    pub state: KnockState,
+    pub at: SystemTime,
+}
+
+impl Knock {
+    pub fn from_event(event: &SessionEvent) -> Option<Self> {
+        match event {
+            SessionEvent::Message {
+                source: Some(source),
+                command,
+            } => Self::from_command(source, command),
+            _ => None,
+        }
+    }
+
+    /// Parses `:<prefix> KNOCK <channel> <numeric>`.
+    pub fn from_command(source: &str, command: &Command) -> Option<Self> {
+        let Command::Knock { channel, reason } = command else {
+            return None;
+        };
+        let prefix = Prefix::parse(source);
+        let mask = match prefix.host {
+            Some(host) => format!("*!*@{host}"),
+            None => format!("{}!*@*", prefix.nick),
+        };
+        Some(Self {
+            channel: channel.clone(),
+            nick: prefix.nick.to_owned(),
+            mask,
+            reason: Numeric::from_code(reason.parse().ok()?),
+            state: KnockState::Pending,
+            at: SystemTime::now(),
+        })
+    }
+}
+
+/// Knocks on one channel, as seen by one of its hosts.
+///
+/// A knock is [`KnockState::Pending`] until [`accept`](Self::accept)ed or
+/// [`deny`](Self::deny)ed; knocking again makes it pending again, and the
+/// knocker joining clears it.
+#[derive(Debug, Clone)]
+pub struct KnockQueue {
+    channel: String,
+    knocks: BTreeMap<String, Knock>,
+}
+
+impl KnockQueue {
+    pub fn new(channel: impl Into<String>) -> Self {
+        Self {
+            channel: channel.into(),
+            knocks: BTreeMap::new(),
+        }
+    }
+
+    pub fn channel(&self) -> &str {
+        &self.channel
+    }
+
+    pub fn get(&self, nick: &str) -> Option<&Knock> {
+        self.knocks.get(&nick.to_ascii_lowercase())
+    }
+
+    pub fn state(&self, nick: &str) -> Option<KnockState> {
+        self.get(nick).map(|k| k.state)
+    }
+
+    /// Pending knocks, oldest first.
+    pub fn pending(&self) -> Vec<&Knock> {
+        let mut pending: Vec<_> = self
+            .knocks
+            .values()
+            .filter(|k| k.state == KnockState::Pending)
+            .collect();
+        pending.sort_by_key(|k| k.at);
+        pending
+    }
+
+    /// Applies `event`; returns the knock if it is a new or repeated one.
+    pub fn handle_event(&mut self, event: &SessionEvent) -> Option<&Knock> {
+        let SessionEvent::Message {
+            source: Some(source),
+            command,
+        } = event
+        else {
+            return None;
+        };
+        match command {
+            Command::Knock { channel, .. } if self.is(channel) => {
+                let knock = Knock::from_command(source, command)?;
+                let key = knock.nick.to_ascii_lowercase();
+                self.knocks.insert(key.clone(), knock);
+                self.knocks.get(&key)
+            }
+            Command::Join { channels, keys } => {
+                // IRC8 sends `JOIN <flags> :<channel>`.
+                let joined = std::iter::once(channels.as_str())
+                    .chain(keys.as_deref())
+                    .flat_map(|c| c.split(','))
+                    .any(|c| self.is(c));
+                if joined {
+                    let nick = Prefix::parse(source).nick.to_ascii_lowercase();
+                    self.knocks.remove(&nick);
+                }
+                None
+            }
+            _ => None,
+        }
+    }
+
+    /// Invites the knocker, with `code` if given.
+    ///
+    /// `role` is our own; knocks only come from restricted channels, where
+    /// inviting takes a host.
+    pub fn accept(
+        &mut self,
+        nick: &str,
+        role: UserRole,
+        code: Option<InvitationCode>,
+    ) -> Result<Invitation, InviteError> {
+        if role < UserRole::Host {
+            return Err(InviteError::NotPermitted(role));
+        }
+        let knock = self.pending_mut(nick)?;
+        knock.state = KnockState::Accepted;
+        let invitation = Invitation::new(&knock.channel, &knock.nick);
+        Ok(match code {
+            Some(code) => invitation.with_code(code),
+            None => invitation,
+        })
+    }
+
+    /// Turns the knocker away without telling the server.
+    pub fn deny(&mut self, nick: &str, role: UserRole) -> Result<(), InviteError> {
+        if role < UserRole::Host {
+            return Err(InviteError::NotPermitted(role));
+        }
+        self.pending_mut(nick)?.state = KnockState::Denied;
+        Ok(())
+    }
+
+    /// Denies the knock and returns the `ACCESS ADD DENY` that keeps the
+    /// knocker out; `None` bans permanently.
+    pub fn deny_and_ban(
+        &mut self,
+        nick: &str,
+        role: UserRole,
+        duration: Option<Duration>,
+    ) -> Result<Vec<Command>, InviteError> {
+        let mask = self.pending_mut(nick)?.mask.clone();
+        let action = ModerationAction::ban(mask, duration, None);
+        action.authorize(role, None)?;
+        let commands = action.commands(&self.channel)?;
+        self.deny(nick, role)?;
+        Ok(commands)
+    }
+
+    fn pending_mut(&mut self, nick: &str) -> Result<&mut Knock, InviteError> {
+        self.knocks
+            .get_mut(&nick.to_ascii_lowercase())
+            .filter(|k| k.state == KnockState::Pending)
+            .ok_or_else(|| InviteError::NoKnock(nick.to_owned()))
+    }
+
+    fn is(&self, channel: &str) -> bool {
+        self.channel.eq_ignore_ascii_case(channel)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOBBY: &str = "%#Lobby";
+
+    fn message(source: &str, command: Command) -> SessionEvent {
+        SessionEvent::Message {
+            source: Some(source.to_owned()),
+            command,
+        }
+    }
+
+    fn knock(nick: &str) -> SessionEvent {
+        message(
+            &format!("{nick}!~{nick}@{nick}.example.com"),
+            Command::Knock {
+                channel: LOBBY.to_owned(),
+                reason: "473".to_owned(),
+            },
+        )
+    }
+
+    #[test]
+    fn codes() {
+        let code = InvitationCode::generate();
+        assert_eq!(code.as_str().len(), INVITATION_CODE_LEN);
+        assert_eq!(code.as_str().to_lowercase().parse(), Ok(code));
+        assert_eq!(
+            InvitationCode::parse("ABCDEFG0"),
+            Err(InviteError::BadCode("ABCDEFG0".into()))
+        );
+        assert!(InvitationCode::parse("ABCDEFG").is_err());
+    }
+
+    #[test]
+    fn codes_are_single_use() {
+        let mut invitations = Invitations::new();
+        let code = invitations.issue(LOBBY, "Bob").code.unwrap();
+        invitations
+            .validate(&code.as_str().to_lowercase(), "%#LOBBY", "bob")
+            .unwrap();
+        invitations.redeem(code.as_str(), LOBBY, "Bob").unwrap();
+        assert_eq!(
+            invitations.redeem(code.as_str(), LOBBY, "Bob"),
+            Err(InviteError::UnknownCode)
+        );
+        assert!(invitations.is_empty());
+    }
+
+    #[test]
+    fn wrong_invitee() {
+        let mut invitations = Invitations::new();
+        let code = invitations.issue(LOBBY, "Bob").code.unwrap();
+        assert_eq!(
+            invitations.redeem(code.as_str(), LOBBY, "Mallory"),
+            Err(InviteError::WrongInvitee)
+        );
+        assert_eq!(
+            invitations.redeem(code.as_str(), "%#Other", "Bob"),
+            Err(InviteError::WrongInvitee)
+        );
+        // A failed attempt does not use the code up.
+        invitations.redeem(code.as_str(), LOBBY, "Bob").unwrap();
+    }
+
+    #[test]
+    fn expiry() {
+        let mut invitations = Invitations::with_ttl(Duration::ZERO);
+        let code = invitations.issue(LOBBY, "Bob").code.unwrap();
+        assert_eq!(
+            invitations.validate(code.as_str(), LOBBY, "Bob"),
+            Err(InviteError::Expired)
+        );
+        let mut fresh = Invitations::new();
+        let kept = fresh.issue(LOBBY, "Carol").code.unwrap();
+
+        assert_eq!(invitations.prune_expired(), 1);
+        assert_eq!(fresh.prune_expired(), 0);
+        assert!(fresh.revoke(&kept));
+        assert!(!fresh.revoke(&kept));
+    }
+
+    #[test]
+    fn invitation_commands_round_trip() {
+        let code = InvitationCode::parse("ABCD2345").unwrap();
+        let sent = Invitation::new(LOBBY, "Bob").with_code(code);
+        let received: Vec<_> = sent
+            .commands()
+            .into_iter()
+            .map(|command| {
+                let line = command.to_line().unwrap();
+                let parsed = Command::parse(line.trim_end()).unwrap();
+                Invitation::from_event(&message("Alice!~a@host", parsed)).unwrap()
+            })
+            .collect();
+        assert_eq!(received.len(), 2);
+        assert_eq!(received[0].code, None);
+        assert_eq!(
+            received[1],
+            Invitation {
+                from: Some("Alice".into()),
+                ..sent
+            }
+        );
+    }
+
+    #[test]
+    fn reknock_after_deny() {
+        let mut knocks = KnockQueue::new(LOBBY);
+        assert!(knocks.handle_event(&knock("Bob")).is_some());
+        assert_eq!(knocks.get("bob").unwrap().mask, "*!*@Bob.example.com");
+        assert_eq!(
+            knocks.deny("Bob", UserRole::Participant),
+            Err(InviteError::NotPermitted(UserRole::Participant))
+        );
+        knocks.deny("Bob", UserRole::Host).unwrap();
+        assert_eq!(knocks.state("Bob"), Some(KnockState::Denied));
+        assert_eq!(
+            knocks.accept("Bob", UserRole::Host, None),
+            Err(InviteError::NoKnock("Bob".into()))
+        );
+
+        assert!(knocks.handle_event(&knock("Bob")).is_some());
+        assert_eq!(knocks.state("Bob"), Some(KnockState::Pending));
+        let invitation = knocks.accept("BOB", UserRole::Owner, None).unwrap();
+        assert_eq!(invitation, Invitation::new(LOBBY, "Bob"));
+    }
+
+    #[test]
+    fn pending_oldest_first() {
+        let mut knocks = KnockQueue::new(LOBBY);
+        knocks.handle_event(&knock("Zed"));
+        std::thread::sleep(Duration::from_millis(2));
+        knocks.handle_event(&knock("Amy"));
+        let other = message(
+            "Eve!~e@host",
+            Command::Knock {
+                channel: "%#Other".into(),
+                reason: "473".into(),
+            },
+        );
+        assert!(knocks.handle_event(&other).is_none());
+
+        let nicks: Vec<_> = knocks.pending().iter().map(|k| k.nick.as_str()).collect();
+        assert_eq!(nicks, ["Zed", "Amy"]);
+    }
+
+    #[test]
+    fn join_clears_knock() {
+        let mut knocks = KnockQueue::new(LOBBY);
+        knocks.handle_event(&knock("Bob"));
+        knocks.handle_event(&knock("Carol"));
+        knocks.accept("Bob", UserRole::Host, None).unwrap();
+
+        let join = |channels: &str, keys: Option<&str>| Command::Join {
+            channels: channels.to_owned(),
+            keys: keys.map(str::to_owned),
+        };
+        knocks.handle_event(&message("Bob!~b@host", join("%#Other", None)));
+        assert_eq!(knocks.state("Bob"), Some(KnockState::Accepted));
+        knocks.handle_event(&message("Bob!~b@host", join("%#lobby", None)));
+        assert_eq!(knocks.state("Bob"), None);
+
+        // IRC8 form: `JOIN H,U,GY :%#Lobby`.
+        knocks.handle_event(&message("Carol!~c@host", join("H,U,GY", Some(LOBBY))));
+        assert_eq!(knocks.state("Carol"), None);
+    }
+
+    #[test]
+    fn deny_and_ban() {
+        let mut knocks = KnockQueue::new(LOBBY);
+        knocks.handle_event(&knock("Bob"));
+        assert!(
+            knocks
+                .deny_and_ban("Bob", UserRole::Participant, None)
+                .is_err()
+        );
+        assert_eq!(knocks.state("Bob"), Some(KnockState::Pending));
+
+        let commands = knocks
+            .deny_and_ban("Bob", UserRole::Host, Some(Duration::from_secs(600)))
+            .unwrap();
+        let lines: Vec<_> = commands.iter().map(|c| c.to_line().unwrap()).collect();
+        assert!(
+            lines[0].starts_with("ACCESS %#Lobby ADD DENY *!*@Bob.example.com 10"),
+            "{lines:?}"
+        );
+        assert_eq!(knocks.state("Bob"), Some(KnockState::Denied));
+    }
+}
@@ -0,0 +1,418 @@
+//! `IRCVERS` client identification and the feature set it negotiates.
+//!
+//! The OCX sends `IRCVERS <protocol> <product>!<version>` before `AUTH`; the
+//! server answers with `800` (IRCRPL_IRCX), and the protocol level it accepted
+//! decides which MSN extensions are available for the session.
+
+use std::{fmt, str::FromStr};
+
+use super::{command::Command, message::Message, numeric::Reply};
+
+/// Product name used by the MSN Chat control.
+pub const MSN_OCX: &str = "MSN-OCX";
+
+/// The `IRCx` protocol level named in `IRCVERS`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub enum ProtocolLevel {
+    /// 2.x-era controls; plain IRCX.
+    Irc6,
+    /// Adds `WHISPER` and `DATA`/`REQUEST`/`REPLY`.
+    Irc7,
+    /// Adds UTF-8 nicknames. Sent by every 8.x and 9.x control.
+    #[default]
+    Irc8,
+}
+
+impl ProtocolLevel {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ProtocolLevel::Irc6 => "IRC6",
+            ProtocolLevel::Irc7 => "IRC7",
+            ProtocolLevel::Irc8 => "IRC8",
+        }
+    }
+
+    /// The level named in an `800` version field, as `IRC7` or a bare `7`.
+    ///
+    /// Plain IRCX servers report their IRCX version (`0`) there instead,
+    /// which names no level.
+    pub fn from_server_version(version: &str) -> Option<Self> {
+        match version {
+            "6" => Some(ProtocolLevel::Irc6),
+            "7" => Some(ProtocolLevel::Irc7),
+            "8" => Some(ProtocolLevel::Irc8),
+            _ => version.parse().ok(),
+        }
+    }
+
+    /// The features this level enables once the server accepts IRCX.
+    pub fn capabilities(self) -> Capabilities {
+        use Capability::*;
+
+        let irc6 = Capabilities::from_iter([Ircx]);
+        let irc7 = irc6.union(Capabilities::from_iter([Whisper, Data]));
+        match self {
+            ProtocolLevel::Irc6 => irc6,
+            ProtocolLevel::Irc7 => irc7,
+            ProtocolLevel::Irc8 => irc7.union(Capabilities::from_iter([Utf8Nicks])),
+        }
+    }
+}
+
+impl fmt::Display for ProtocolLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ProtocolLevel {
+    type Err = VersionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_uppercase().as_str() {
+            "IRC6" => Ok(ProtocolLevel::Irc6),
+            "IRC7" => Ok(ProtocolLevel::Irc7),
+            "IRC8" => Ok(ProtocolLevel::Irc8),
+            _ => Err(VersionError::UnknownProtocol(s.to_string())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VersionError {
+    UnknownProtocol(String),
+    /// The client string is not `<product>!<version>`.
+    MalformedClient(String),
+    /// A version component that is not a decimal number.
+    MalformedVersion(String),
+}
+
+impl fmt::Display for VersionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VersionError::UnknownProtocol(s) => write!(f, "unknown IRCVERS protocol `{s}`"),
+            VersionError::MalformedClient(s) => write!(f, "malformed IRCVERS client `{s}`"),
+            VersionError::MalformedVersion(s) => write!(f, "malformed client version `{s}`"),
+        }
+    }
+}
+
+impl std::error::Error for VersionError {}
+
+/// What the client announces in `IRCVERS`, e.g. `IRC8 MSN-OCX!9.02.0310.2401`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ClientVersion {
+    pub protocol: ProtocolLevel,
+    pub product: String,
+    /// Kept verbatim; the OCX zero-pads its components (`9.02.0310.2401`).
+    pub version: String,
+}
+
+impl ClientVersion {
+    pub fn new(
+        protocol: ProtocolLevel,
+        product: impl Into<String>,
+        version: impl Into<String>,
+    ) -> Result<Self, VersionError> {
+        let client = Self {
+            protocol,
+            product: product.into(),
+            version: version.into(),
+        };
+        if client.product.is_empty() || client.product.contains(['!', ' ']) {
+            return Err(VersionError::MalformedClient(client.product));
+        }
+        client.components()?;
+        Ok(client)
+    }
+
+    /// The last MSN Chat control release, 9.02.0310.2401.
+    pub fn msn_ocx() -> Self {
+        Self {
+            protocol: ProtocolLevel::Irc8,
+            product: MSN_OCX.to_owned(),
+            version: "9.02.0310.2401".to_owned(),
+        }
+    }
+
+    /// Parses the two `IRCVERS` arguments.
+    pub fn parse(protocol: &str, client: &str) -> Result<Self, VersionError> {
+        let (product, version) = client
+            .split_once('!')
+            .ok_or_else(|| VersionError::MalformedClient(client.to_string()))?;
+        Self::new(protocol.parse()?, product, version)
+    }
+
+    /// Numeric version components for ordering, e.g. `[9, 2, 310, 2401]`.
+    pub fn components(&self) -> Result<Vec<u32>, VersionError> {
+        self.version
+            .split('.')
+            .map(|c| {
+                c.parse()
+                    .map_err(|_| VersionError::MalformedVersion(self.version.clone()))
+            })
+            .collect()
+    }
+
+    /// The `<product>!<version>` argument.
+    pub fn client_string(&self) -> String {
+        format!("{}!{}", self.product, self.version)
+    }
+
+    pub fn to_command(&self) -> Command {
+        Command::Ircvers {
+            version: self.protocol.to_string(),
+            client: self.client_string(),
+        }
+    }
+}
+
+impl Default for ClientVersion {
+    fn default() -> Self {
+        Self::msn_ocx()
+    }
+}
+
+impl fmt::Display for ClientVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}!{}", self.protocol, self.product, self.version)
+    }
+}
+
+impl FromStr for ClientVersion {
+    type Err = VersionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (protocol, client) = s
+            .trim()
+            .split_once(' ')
+            .ok_or_else(|| VersionError::MalformedClient(s.to_string()))?;
+        Self::parse(protocol, client.trim_start())
+    }
+}
+
+/// A feature gated on the negotiated protocol or the server's packages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Capability {
+    Ircx,
+    Whisper,
+    Data,
+    Utf8Nicks,
+    GateKeeper,
+    GateKeeperPassport,
+    Ntlm,
+}
+
+impl Capability {
+    pub const ALL: [Capability; 7] = [
+        Capability::Ircx,
+        Capability::Whisper,
+        Capability::Data,
+        Capability::Utf8Nicks,
+        Capability::GateKeeper,
+        Capability::GateKeeperPassport,
+        Capability::Ntlm,
+    ];
+
+    /// The capability advertised by a security package name in `800`.
+    pub fn from_package(package: &str) -> Option<Self> {
+        match package.to_ascii_lowercase().as_str() {
+            "gatekeeper" => Some(Capability::GateKeeper),
+            "gatekeeperpassport" => Some(Capability::GateKeeperPassport),
+            "ntlm" => Some(Capability::Ntlm),
+            _ => None,
+        }
+    }
+
+    fn bit(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+/// A set of [`Capability`]s.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Capabilities(u8);
+
+impl Capabilities {
+    pub const NONE: Capabilities = Capabilities(0);
+
+    pub fn contains(self, capability: Capability) -> bool {
+        self.0 & capability.bit() != 0
+    }
+
+    pub fn insert(&mut self, capability: Capability) {
+        self.0 |= capability.bit();
+    }
+
+    pub fn remove(&mut self, capability: Capability) {
+        self.0 &= !capability.bit();
+    }
+
+    pub fn union(self, other: Capabilities) -> Capabilities {
+        Capabilities(self.0 | other.0)
+    }
+
+    pub fn intersection(self, other: Capabilities) -> Capabilities {
+        Capabilities(self.0 & other.0)
+    }
+
+    pub fn iter(self) -> impl Iterator<Item = Capability> {
+        Capability::ALL
+            .into_iter()
+            .filter(move |c| self.contains(*c))
+    }
+}
+
+impl FromIterator<Capability> for Capabilities {
+    fn from_iter<I: IntoIterator<Item = Capability>>(iter: I) -> Self {
+        let mut set = Capabilities::NONE;
+        for capability in iter {
+            set.insert(capability);
+        }
+        set
+    }
+}
+
+/// The outcome of `IRCVERS` once the server's `800` reply arrives.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Negotiation {
+    pub protocol: ProtocolLevel,
+    /// The server's IRCX version field.
+    pub server_version: String,
+    pub packages: Vec<String>,
+    pub max_message_len: Option<usize>,
+    pub capabilities: Capabilities,
+}
+
+impl Negotiation {
+    /// Combines what the client asked for with the server's `800` reply.
+    ///
+    /// The protocol is the lower of the client's level and the one the server
+    /// reports; a server that reports none gets the client's level.
+    ///
+    /// Returns `None` if `msg` is not an `800`.
+    pub fn from_reply(client: &ClientVersion, msg: &Message<'_>) -> Option<Self> {
+        let Some(Reply::Ircx {
+            enabled,
+            version,
+            packages,
+            max_message_len,
+            ..
+        }) = Reply::parse(msg)
+        else {
+            return None;
+        };
+
+        let protocol = ProtocolLevel::from_server_version(version)
+            .map_or(client.protocol, |server| server.min(client.protocol));
+        let mut capabilities = packages
+            .iter()
+            .filter_map(|p| Capability::from_package(p))
+            .collect::<Capabilities>();
+        if enabled {
+            capabilities = capabilities.union(protocol.capabilities());
+        }
+
+        Some(Self {
+            protocol,
+            server_version: version.to_owned(),
+            packages: packages.into_iter().map(str::to_owned).collect(),
+            max_message_len,
+            capabilities,
+        })
+    }
+
+    pub fn supports(&self, capability: Capability) -> bool {
+        self.capabilities.contains(capability)
+    }
+
+    /// The security package to authenticate with: Passport when requested and offered.
+    pub fn auth_package(&self, want_passport: bool) -> Option<&'static str> {
+        use super::gatekeeper::{GATEKEEPER, GATEKEEPER_PASSPORT};
+
+        if want_passport && self.supports(Capability::GateKeeperPassport) {
+            Some(GATEKEEPER_PASSPORT)
+        } else if self.supports(Capability::GateKeeper) {
+            Some(GATEKEEPER)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn negotiate(client: ProtocolLevel, reply: &str) -> Negotiation {
+        let client = ClientVersion {
+            protocol: client,
+            ..ClientVersion::msn_ocx()
+        };
+        let msg = Message::parse(reply).unwrap();
+        Negotiation::from_reply(&client, &msg).unwrap()
+    }
+
+    #[test]
+    fn server_level_caps_protocol() {
+        let n = negotiate(
+            ProtocolLevel::Irc8,
+            ":TK2CHATCHATA01 800 * 1 IRC7 GateKeeper,NTLM 512 *",
+        );
+        assert_eq!(n.protocol, ProtocolLevel::Irc7);
+        assert!(n.supports(Capability::Whisper));
+        assert!(!n.supports(Capability::Utf8Nicks));
+        assert!(n.supports(Capability::Ntlm));
+
+        let n = negotiate(ProtocolLevel::Irc8, ":server 800 * 1 6 GateKeeper 512 *");
+        assert_eq!(n.protocol, ProtocolLevel::Irc6);
+        assert_eq!(
+            n.capabilities,
+            Capabilities::from_iter([Capability::Ircx, Capability::GateKeeper])
+        );
+    }
+
+    #[test]
+    fn client_level_caps_protocol() {
+        let n = negotiate(ProtocolLevel::Irc6, ":server 800 * 1 IRC8 GateKeeper 512 *");
+        assert_eq!(n.protocol, ProtocolLevel::Irc6);
+        assert!(!n.supports(Capability::Whisper));
+    }
+
+    #[test]
+    fn unreported_level_keeps_client_level() {
+        let n = negotiate(
+            ProtocolLevel::Irc8,
+            ":server 800 * 1 0 GateKeeper,GateKeeperPassport 512 *",
+        );
+        assert_eq!(n.protocol, ProtocolLevel::Irc8);
+        assert_eq!(n.server_version, "0");
+        assert_eq!(n.max_message_len, Some(512));
+        assert!(n.supports(Capability::Utf8Nicks));
+        assert_eq!(n.auth_package(true), Some("GateKeeperPassport"));
+        assert_eq!(n.auth_package(false), Some("GateKeeper"));
+    }
+
+    #[test]
+    fn disabled_ircx_only_has_packages() {
+        let n = negotiate(ProtocolLevel::Irc8, ":server 800 * 0 IRC8 NTLM 512 *");
+        assert_eq!(n.capabilities, Capabilities::from_iter([Capability::Ntlm]));
+        assert_eq!(n.auth_package(true), None);
+    }
+
+    #[test]
+    fn client_version_round_trips() {
+        let client: ClientVersion = "IRC8 MSN-OCX!9.02.0310.2401".parse().unwrap();
+        assert_eq!(client, ClientVersion::msn_ocx());
+        assert_eq!(client.components().unwrap(), [9, 2, 310, 2401]);
+        assert_eq!(client.to_string(), "IRC8 MSN-OCX!9.02.0310.2401");
+        assert_eq!(
+            "IRC9 MSN-OCX!1.0".parse::<ClientVersion>(),
+            Err(VersionError::UnknownProtocol("IRC9".into()))
+        );
+        assert_eq!(
+            "IRC8 MSN-OCX!9.x".parse::<ClientVersion>(),
+            Err(VersionError::MalformedVersion("9.x".into()))
+        );
+    }
+}
@@ -0,0 +1,548 @@
+use std::fmt;
+
+/// Maximum length of a line on the wire, including the trailing CRLF.
+pub const MAX_LINE_LEN: usize = 512;
+
+/// Maximum number of parameters (middle + trailing) in a strict message.
+pub const MAX_PARAMS: usize = 15;
+
+/// How forgiving [`Message::parse_with`] is about malformed input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ParseMode {
+    /// RFC 1459 grammar: single spaces, at most 15 parameters, 512-byte lines.
+    #[default]
+    Strict,
+    /// Accepts what real servers send: repeated spaces, bare LF, long lines
+    /// and any number of parameters.
+    Lenient,
+}
+
+/// What went wrong while parsing a line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    Empty,
+    TooLong,
+    EmptyPrefix,
+    MissingCommand,
+    InvalidCommand,
+    TooManyParams,
+    /// Two consecutive spaces, or a space before the line end (strict only).
+    EmptyParam,
+    /// NUL, CR or LF inside the line.
+    IllegalByte(u8),
+}
+
+/// A parse failure and the byte offset at which it was detected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    pub offset: usize,
+}
+
+impl ParseError {
+    fn at(kind: ParseErrorKind, offset: usize) -> Self {
+        Self { kind, offset }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            ParseErrorKind::Empty => write!(f, "empty line"),
+            ParseErrorKind::TooLong => write!(f, "line exceeds {MAX_LINE_LEN} bytes"),
+            ParseErrorKind::EmptyPrefix => write!(f, "empty prefix"),
+            ParseErrorKind::MissingCommand => write!(f, "missing command"),
+            ParseErrorKind::InvalidCommand => write!(f, "invalid command at byte {}", self.offset),
+            ParseErrorKind::TooManyParams => write!(f, "more than {MAX_PARAMS} parameters"),
+            ParseErrorKind::EmptyParam => write!(f, "empty parameter at byte {}", self.offset),
+            ParseErrorKind::IllegalByte(b) => {
+                write!(f, "illegal byte {b:#04x} at byte {}", self.offset)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Why a [`Message`] could not be serialized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EncodeError {
+    TooLong(usize),
+    InvalidCommand,
+    InvalidPrefix,
+    /// A middle parameter is empty, starts with `:` or contains a space.
+    InvalidParam(usize),
+    IllegalByte(u8),
+}
+
+impl fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EncodeError::TooLong(len) => {
+                write!(f, "encoded line is {len} bytes, limit is {MAX_LINE_LEN}")
+            }
+            EncodeError::InvalidCommand => write!(f, "invalid command"),
+            EncodeError::InvalidPrefix => write!(f, "invalid prefix"),
+            EncodeError::InvalidParam(i) => write!(f, "parameter {i} cannot be a middle parameter"),
+            EncodeError::IllegalByte(b) => write!(f, "illegal byte {b:#04x}"),
+        }
+    }
+}
+
+impl std::error::Error for EncodeError {}
+
+/// One IRC/IRCX protocol line, borrowing from the input it was parsed from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message<'a> {
+    pub prefix: Option<&'a str>,
+    pub command: &'a str,
+    /// Middle parameters, without the trailing one.
+    pub params: Vec<&'a str>,
+    /// The `:`-introduced last parameter, which may contain spaces.
+    pub trailing: Option<&'a str>,
+}
+
+impl<'a> Message<'a> {
+    pub fn new(command: &'a str) -> Self {
+        Self {
+            prefix: None,
+            command,
+            params: Vec::new(),
+            trailing: None,
+        }
+    }
+
+    pub fn with_prefix(mut self, prefix: &'a str) -> Self {
+        self.prefix = Some(prefix);
+        self
+    }
+
+    pub fn param(mut self, param: &'a str) -> Self {
+        self.params.push(param);
+        self
+    }
+
+    pub fn trailing(mut self, trailing: &'a str) -> Self {
+        self.trailing = Some(trailing);
+        self
+    }
+
+    /// Parses a line in [`ParseMode::Strict`].
+    pub fn parse(line: &'a str) -> Result<Self, ParseError> {
+        Self::parse_with(line, ParseMode::Strict)
+    }
+
+    /// Parses a line in [`ParseMode::Lenient`].
+    pub fn parse_lenient(line: &'a str) -> Result<Self, ParseError> {
+        Self::parse_with(line, ParseMode::Lenient)
+    }
+
+    /// Parses a single line, with or without its CRLF terminator.
+    pub fn parse_with(line: &'a str, mode: ParseMode) -> Result<Self, ParseError> {
+        let strict = mode == ParseMode::Strict;
+        let body = match mode {
+            ParseMode::Strict => line.strip_suffix("\r\n").unwrap_or(line),
+            ParseMode::Lenient => line.trim_end_matches(['\r', '\n']),
+        };
+
+        if strict && body.len() + 2 > MAX_LINE_LEN {
+            return Err(ParseError::at(ParseErrorKind::TooLong, MAX_LINE_LEN - 2));
+        }
+        if let Some(at) = body
+            .bytes()
+            .position(|b| matches!(b, b'\0' | b'\r' | b'\n'))
+        {
+            return Err(ParseError::at(
+                ParseErrorKind::IllegalByte(body.as_bytes()[at]),
+                at,
+            ));
+        }
+
+        let mut cursor = Cursor {
+            src: body,
+            pos: 0,
+            strict,
+        };
+        if !strict {
+            cursor.skip_spaces();
+        }
+        if cursor.at_end() {
+            return Err(ParseError::at(ParseErrorKind::Empty, 0));
+        }
+
+        let prefix = if cursor.peek() == Some(b':') {
+            cursor.pos += 1;
+            let start = cursor.pos;
+            let prefix = cursor.word();
+            if prefix.is_empty() {
+                return Err(ParseError::at(ParseErrorKind::EmptyPrefix, start));
+            }
+            if cursor.at_end() {
+                return Err(ParseError::at(ParseErrorKind::MissingCommand, cursor.pos));
+            }
+            cursor.separator()?;
+            Some(prefix)
+        } else {
+            None
+        };
+
+        let command_at = cursor.pos;
+        let command = cursor.word();
+        if command.is_empty() {
+            return Err(ParseError::at(ParseErrorKind::MissingCommand, command_at));
+        }
+        if strict && !is_valid_command(command) {
+            return Err(ParseError::at(ParseErrorKind::InvalidCommand, command_at));
+        }
+
+        let mut params = Vec::new();
+        let mut trailing = None;
+        while !cursor.at_end() {
+            cursor.separator()?;
+            if cursor.at_end() {
+                break;
+            }
+            if cursor.peek() == Some(b':') {
+                trailing = Some(&body[cursor.pos + 1..]);
+                break;
+            }
+            params.push(cursor.word());
+        }
+
+        let count = params.len() + trailing.is_some() as usize;
+        if strict && count > MAX_PARAMS {
+            return Err(ParseError::at(ParseErrorKind::TooManyParams, body.len()));
+        }
+
+        Ok(Self {
+            prefix,
+            command,
+            params,
+            trailing,
+        })
+    }
+
+    /// The three-digit reply code, if this is a numeric reply.
+    pub fn numeric(&self) -> Option<u16> {
+        let bytes = self.command.as_bytes();
+        if bytes.len() == 3 && bytes.iter().all(u8::is_ascii_digit) {
+            self.command.parse().ok()
+        } else {
+            None
+        }
+    }
+
+    /// True when `command` matches case-insensitively.
+    pub fn is(&self, command: &str) -> bool {
+        self.command.eq_ignore_ascii_case(command)
+    }
+
+    /// All parameters, with the trailing one last.
+    pub fn args(&self) -> impl Iterator<Item = &'a str> + '_ {
+        self.params.iter().copied().chain(self.trailing)
+    }
+
+    /// Parameter `index`, counting the trailing parameter as the last one.
+    pub fn arg(&self, index: usize) -> Option<&'a str> {
+        self.args().nth(index)
+    }
+
+    pub fn arg_count(&self) -> usize {
+        self.params.len() + self.trailing.is_some() as usize
+    }
+
+    /// The final parameter, whether middle or trailing.
+    pub fn last_arg(&self) -> Option<&'a str> {
+        self.trailing.or_else(|| self.params.last().copied())
+    }
+
+    /// The prefix split into its `nick!user@host` parts.
+    pub fn source(&self) -> Option<Prefix<'a>> {
+        self.prefix.map(Prefix::parse)
+    }
+
+    /// Appends the encoded line, including CRLF, to `out`.
+    pub fn encode(&self, out: &mut Vec<u8>) -> Result<(), EncodeError> {
+        self.validate()?;
+        let start = out.len();
+        out.extend_from_slice(self.to_string().as_bytes());
+        out.extend_from_slice(b"\r\n");
+
+        let len = out.len() - start;
+        if len > MAX_LINE_LEN {
+            out.truncate(start);
+            return Err(EncodeError::TooLong(len));
+        }
+        Ok(())
+    }
+
+    /// Encodes the message as a CRLF-terminated line.
+    pub fn to_line(&self) -> Result<String, EncodeError> {
+        let mut out = Vec::new();
+        self.encode(&mut out)?;
+        Ok(String::from_utf8(out).expect("encoded from str parts"))
+    }
+
+    fn validate(&self) -> Result<(), EncodeError> {
+        let all = self
+            .prefix
+            .into_iter()
+            .chain([self.command])
+            .chain(self.args());
+        for part in all {
+            if let Some(b) = part.bytes().find(|b| matches!(b, b'\0' | b'\r' | b'\n')) {
+                return Err(EncodeError::IllegalByte(b));
+            }
+        }
+
+        if let Some(prefix) = self.prefix
+            && (prefix.is_empty() || prefix.contains(' '))
+        {
+            return Err(EncodeError::InvalidPrefix);
+        }
+        if self.command.is_empty() || self.command.contains([' ', ':']) {
+            return Err(EncodeError::InvalidCommand);
+        }
+        for (i, param) in self.params.iter().enumerate() {
+            if param.is_empty() || param.starts_with(':') || param.contains(' ') {
+                return Err(EncodeError::InvalidParam(i));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Message<'_> {
+    /// Writes the line without CRLF and without validation.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(prefix) = self.prefix {
+            write!(f, ":{prefix} ")?;
+        }
+        f.write_str(self.command)?;
+        for param in &self.params {
+            write!(f, " {param}")?;
+        }
+        if let Some(trailing) = self.trailing {
+            write!(f, " :{trailing}")?;
+        }
+        Ok(())
+    }
+}
+
+/// A message source split into `nick!user@host`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Prefix<'a> {
+    /// Nickname, or the server name when there is no `!`/`@`.
+    pub nick: &'a str,
+    pub user: Option<&'a str>,
+    pub host: Option<&'a str>,
+}
+
+impl<'a> Prefix<'a> {
+    pub fn parse(prefix: &'a str) -> Self {
+        let (rest, host) = match prefix.split_once('@') {
+            Some((rest, host)) => (rest, Some(host)),
+            None => (prefix, None),
+        };
+        let (nick, user) = match rest.split_once('!') {
+            Some((nick, user)) => (nick, Some(user)),
+            None => (rest, None),
+        };
+        Self { nick, user, host }
+    }
+
+    /// True for a bare server name such as `TK2CHATCHATA01`.
+    pub fn is_server(&self) -> bool {
+        self.user.is_none() && self.host.is_none()
+    }
+}
+
+impl fmt::Display for Prefix<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.nick)?;
+        if let Some(user) = self.user {
+            write!(f, "!{user}")?;
+        }
+        if let Some(host) = self.host {
+            write!(f, "@{host}")?;
+        }
+        Ok(())
+    }
+}
+
+fn is_valid_command(command: &str) -> bool {
+    let bytes = command.as_bytes();
+    bytes.iter().all(u8::is_ascii_alphabetic)
+        || (bytes.len() == 3 && bytes.iter().all(u8::is_ascii_digit))
+}
+
+struct Cursor<'a> {
+    src: &'a str,
+    pos: usize,
+    strict: bool,
+}
+
+impl<'a> Cursor<'a> {
+    fn at_end(&self) -> bool {
+        self.pos >= self.src.len()
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.src.as_bytes().get(self.pos).copied()
+    }
+
+    /// Consumes up to the next space.
+    fn word(&mut self) -> &'a str {
+        let rest = &self.src[self.pos..];
+        let len = rest.find(' ').unwrap_or(rest.len());
+        self.pos += len;
+        &rest[..len]
+    }
+
+    fn skip_spaces(&mut self) {
+        while self.peek() == Some(b' ') {
+            self.pos += 1;
+        }
+    }
+
+    /// Consumes the space(s) between two tokens.
+    fn separator(&mut self) -> Result<(), ParseError> {
+        if self.strict {
+            // `word` stops only at a space or the end, so this is a single space.
+            self.pos += 1;
+            if self.at_end() || self.peek() == Some(b' ') {
+                return Err(ParseError::at(ParseErrorKind::EmptyParam, self.pos));
+            }
+        } else {
+            self.skip_spaces();
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prefix_is_optional() {
+        let msg = Message::parse("PING :TK2CHATCHATA01").unwrap();
+        assert_eq!(msg, Message::new("PING").trailing("TK2CHATCHATA01"));
+        assert!(msg.source().is_none());
+
+        let msg = Message::parse(":Alice!alice@host.example JOIN %#Lobby").unwrap();
+        assert_eq!(
+            msg.source(),
+            Some(Prefix {
+                nick: "Alice",
+                user: Some("alice"),
+                host: Some("host.example"),
+            })
+        );
+        assert_eq!(msg.params, ["%#Lobby"]);
+        assert_eq!(msg.trailing, None);
+        assert!(Prefix::parse("TK2CHATCHATA01").is_server());
+    }
+
+    #[test]
+    fn trailing_params() {
+        let msg = Message::parse("PRIVMSG %#Lobby ::) hello  there").unwrap();
+        assert_eq!(msg.params, ["%#Lobby"]);
+        assert_eq!(msg.trailing, Some(":) hello  there"));
+        assert_eq!(msg.last_arg(), Some(":) hello  there"));
+        assert_eq!(
+            msg.to_line().unwrap(),
+            "PRIVMSG %#Lobby ::) hello  there\r\n"
+        );
+
+        let msg = Message::parse("TOPIC %#Lobby :").unwrap();
+        assert_eq!(msg.trailing, Some(""));
+        assert_eq!(msg.arg_count(), 2);
+        assert_eq!(msg.to_line().unwrap(), "TOPIC %#Lobby :\r\n");
+    }
+
+    #[test]
+    fn parameter_limit() {
+        let params = ["p"; MAX_PARAMS].join(" ");
+        let line = format!("CMD {params}");
+        assert_eq!(Message::parse(&line).unwrap().arg_count(), MAX_PARAMS);
+
+        let line = format!("CMD {params} :one too many");
+        let err = Message::parse(&line).unwrap_err();
+        assert_eq!(err.kind, ParseErrorKind::TooManyParams);
+        assert_eq!(
+            Message::parse_lenient(&line).unwrap().arg_count(),
+            MAX_PARAMS + 1
+        );
+    }
+
+    #[test]
+    fn line_endings() {
+        let expected = Message::new("PING").param("x");
+        assert_eq!(Message::parse("PING x\r\n"), Ok(expected.clone()));
+        assert_eq!(Message::parse("PING x"), Ok(expected.clone()));
+        assert_eq!(Message::parse_lenient("PING x\n"), Ok(expected.clone()));
+        assert_eq!(Message::parse_lenient("PING x\r\n\r\n"), Ok(expected));
+
+        let err = Message::parse("PING x\n").unwrap_err();
+        assert_eq!(err, ParseError::at(ParseErrorKind::IllegalByte(b'\n'), 6));
+        let err = Message::parse("PING x\ry").unwrap_err();
+        assert_eq!(err.kind, ParseErrorKind::IllegalByte(b'\r'));
+    }
+
+    #[test]
+    fn strict_grammar() {
+        let kind = |line| Message::parse(line).unwrap_err().kind;
+        assert_eq!(kind(""), ParseErrorKind::Empty);
+        assert_eq!(kind(": PING"), ParseErrorKind::EmptyPrefix);
+        assert_eq!(kind(":server"), ParseErrorKind::MissingCommand);
+        assert_eq!(kind("PR1VMSG x"), ParseErrorKind::InvalidCommand);
+        assert_eq!(kind("PING  x"), ParseErrorKind::EmptyParam);
+        assert_eq!(kind("PING x "), ParseErrorKind::EmptyParam);
+        assert_eq!(kind(&"A".repeat(MAX_LINE_LEN)), ParseErrorKind::TooLong);
+
+        let msg = Message::parse_lenient("  PING   x  ").unwrap();
+        assert_eq!(msg, Message::new("PING").param("x"));
+        assert_eq!(Message::parse("001 Alice").unwrap().numeric(), Some(1));
+    }
+
+    #[test]
+    fn encode_rejects_line_breaks() {
+        let injected = Message::new("PRIVMSG")
+            .param("%#Lobby")
+            .trailing("hi\r\nQUIT");
+        let mut out = b"kept".to_vec();
+        assert_eq!(
+            injected.encode(&mut out),
+            Err(EncodeError::IllegalByte(b'\r'))
+        );
+        assert_eq!(out, b"kept");
+
+        let injected = Message::new("JOIN").param("%#a\nb");
+        assert_eq!(injected.to_line(), Err(EncodeError::IllegalByte(b'\n')));
+        let injected = Message::new("PING").with_prefix("a\0b");
+        assert_eq!(injected.to_line(), Err(EncodeError::IllegalByte(0)));
+    }
+
+    #[test]
+    fn encode_errors() {
+        assert_eq!(
+            Message::new("PRIVMSG").param("two words").to_line(),
+            Err(EncodeError::InvalidParam(0))
+        );
+        assert_eq!(
+            Message::new("MODE").param("x").param(":y").to_line(),
+            Err(EncodeError::InvalidParam(1))
+        );
+        assert_eq!(Message::new("").to_line(), Err(EncodeError::InvalidCommand));
+        assert_eq!(
+            Message::new("PING").with_prefix("").to_line(),
+            Err(EncodeError::InvalidPrefix)
+        );
+        let long = "x".repeat(MAX_LINE_LEN);
+        assert_eq!(
+            Message::new("PRIVMSG").trailing(&long).to_line(),
+            Err(EncodeError::TooLong(MAX_LINE_LEN + 11))
+        );
+    }
+}
@@ -0,0 +1,41 @@
+pub mod access;
+pub mod channel;
+pub mod chat;
+pub mod command;
+pub mod escape;
+pub mod formatting;
+pub mod gatekeeper;
+pub mod invite;
+pub mod ircvers;
+pub mod message;
+pub mod moderation;
+pub mod numeric;
+pub mod passport;
+pub mod props;
+pub mod room;
+pub mod session;
+pub mod whisper;
+
+pub use access::{AccessEntry, AccessError, AccessLevel, AccessList, mask_matches};
+pub use channel::{Channel, Gender, Member, Profile};
+pub use chat::{ChatMessage, KNOWN_SOUNDS, SoundName};
+pub use command::{AuthSequence, Command, CommandError};
+pub use escape::{EscapeError, escape, escape_str, unescape, unescape_str};
+pub use formatting::{Color, Font, FormatError, FormattedText, Span, Style};
+pub use gatekeeper::{Gatekeeper, GatekeeperError, GatekeeperId, GatekeeperState, GatekeeperStep};
+pub use invite::{
+    Invitation, InvitationCode, Invitations, InviteError, Knock, KnockQueue, KnockState,
+};
+pub use ircvers::{
+    Capabilities, Capability, ClientVersion, Negotiation, ProtocolLevel, VersionError,
+};
+pub use message::{
+    EncodeError, MAX_LINE_LEN, MAX_PARAMS, Message, ParseError, ParseErrorKind, ParseMode, Prefix,
+};
+pub use moderation::{Moderation, ModerationAction, ModerationError, ModerationRecord};
+pub use numeric::{Numeric, Reply, ServerError};
+pub use passport::{PassportCredentials, PassportError};
+pub use props::{ChannelProp, ChannelProps, PropError, validate_prop};
+pub use room::{CreateError, MAX_ROOM_NAME_LEN, RoomCreation, RoomSpec, create_room, room_channel};
+pub use session::{Session, SessionConfig, SessionError, SessionEvent, SessionState};
+pub use whisper::{Conversation, Whisper, WhisperEntry, WhisperError, Whispers, check_whisper};
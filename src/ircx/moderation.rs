@@ -0,0 +1,462 @@
+use std::{
+    fmt,
+    time::{Duration, SystemTime},
+};
+
+use super::{AccessEntry, AccessError, AccessLevel, Channel, Command, access};
+use crate::types::{Privilege, UserRole};
+
+/// Why a moderation action was refused before anything was sent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ModerationError {
+    NotPermitted {
+        role: UserRole,
+        privilege: Privilege,
+    },
+    /// The target's role is equal to or above the actor's.
+    Outranked {
+        nick: String,
+        role: UserRole,
+    },
+    NotInChannel(String),
+    Access(AccessError),
+}
+
+impl fmt::Display for ModerationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ModerationError::NotPermitted { role, privilege } => {
+                write!(f, "role `{role}` lacks the {privilege:?} privilege")
+            }
+            ModerationError::Outranked { nick, role } => {
+                write!(f, "{nick} holds role `{role}` and cannot be moderated")
+            }
+            ModerationError::NotInChannel(nick) => write!(f, "{nick} is not in the channel"),
+            ModerationError::Access(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for ModerationError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ModerationError::Access(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<AccessError> for ModerationError {
+    fn from(err: AccessError) -> Self {
+        ModerationError::Access(err)
+    }
+}
+
+/// A host or owner operation on a channel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ModerationAction {
+    Kick {
+        nick: String,
+        reason: Option<String>,
+    },
+    /// `ACCESS ADD DENY`; `duration` is rounded up to minutes, `None` is permanent.
+    Ban {
+        mask: String,
+        duration: Option<Duration>,
+        reason: Option<String>,
+    },
+    Unban {
+        mask: String,
+    },
+    /// IRCX user mode `z`: the member's messages are dropped on every channel.
+    ///
+    /// This is a server-wide user mode, so only sysops may gag; hosts and
+    /// owners moderate with `+m` and voice instead.
+    Gag {
+        nick: String,
+    },
+    Ungag {
+        nick: String,
+    },
+    Voice {
+        nick: String,
+    },
+    Devoice {
+        nick: String,
+    },
+    /// Sets or clears `+m`.
+    Moderated(bool),
+}
+
+impl ModerationAction {
+    pub fn kick(nick: impl Into<String>, reason: Option<&str>) -> Self {
+        ModerationAction::Kick {
+            nick: nick.into(),
+            reason: reason.map(str::to_owned),
+        }
+    }
+
+    pub fn ban(mask: impl Into<String>, duration: Option<Duration>, reason: Option<&str>) -> Self {
+        ModerationAction::Ban {
+            mask: mask.into(),
+            duration,
+            reason: reason.map(str::to_owned),
+        }
+    }
+
+    /// Short lower-case name for logs, e.g. `kick`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ModerationAction::Kick { .. } => "kick",
+            ModerationAction::Ban { .. } => "ban",
+            ModerationAction::Unban { .. } => "unban",
+            ModerationAction::Gag { .. } => "gag",
+            ModerationAction::Ungag { .. } => "ungag",
+            ModerationAction::Voice { .. } => "voice",
+            ModerationAction::Devoice { .. } => "devoice",
+            ModerationAction::Moderated(true) => "moderate",
+            ModerationAction::Moderated(false) => "unmoderate",
+        }
+    }
+
+    pub fn privilege(&self) -> Privilege {
+        match self {
+            ModerationAction::Kick { .. } => Privilege::Kick,
+            ModerationAction::Ban { .. } | ModerationAction::Unban { .. } => Privilege::Ban,
+            ModerationAction::Gag { .. } | ModerationAction::Ungag { .. } => Privilege::Gag,
+            ModerationAction::Voice { .. } | ModerationAction::Devoice { .. } => Privilege::Voice,
+            ModerationAction::Moderated(_) => Privilege::ChangeModes,
+        }
+    }
+
+    /// The member acted on, if the action names one.
+    pub fn target_nick(&self) -> Option<&str> {
+        match self {
+            ModerationAction::Kick { nick, .. }
+            | ModerationAction::Gag { nick }
+            | ModerationAction::Ungag { nick }
+            | ModerationAction::Voice { nick }
+            | ModerationAction::Devoice { nick } => Some(nick),
+            _ => None,
+        }
+    }
+
+    /// The member or mask acted on.
+    pub fn target(&self) -> Option<&str> {
+        match self {
+            ModerationAction::Ban { mask, .. } | ModerationAction::Unban { mask } => Some(mask),
+            _ => self.target_nick(),
+        }
+    }
+
+    pub fn reason(&self) -> Option<&str> {
+        match self {
+            ModerationAction::Kick { reason, .. } | ModerationAction::Ban { reason, .. } => {
+                reason.as_deref()
+            }
+            _ => None,
+        }
+    }
+
+    /// Lowest role that may take the action, on top of holding its privilege.
+    fn minimum_role(&self) -> UserRole {
+        match self {
+            ModerationAction::Gag { .. } | ModerationAction::Ungag { .. } => UserRole::Sysop,
+            _ => UserRole::Spectator,
+        }
+    }
+
+    /// Checks that `actor` may do this to a member holding `target`.
+    ///
+    /// Pass `None` for `target` when the action names a mask or no member.
+    pub fn authorize(
+        &self,
+        actor: UserRole,
+        target: Option<UserRole>,
+    ) -> Result<(), ModerationError> {
+        let privilege = self.privilege();
+        if !actor.may(privilege) || actor < self.minimum_role() {
+            return Err(ModerationError::NotPermitted {
+                role: actor,
+                privilege,
+            });
+        }
+        match (self.target_nick(), target) {
+            (Some(nick), Some(role)) if !actor.may_act_on(role, privilege) => {
+                Err(ModerationError::Outranked {
+                    nick: nick.to_owned(),
+                    role,
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// The commands that carry out the action in `channel`, without checks.
+    pub fn commands(&self, channel: &str) -> Result<Vec<Command>, ModerationError> {
+        let mode = |modes: &str, args: Vec<String>| Command::Mode {
+            target: channel.to_owned(),
+            modes: Some(modes.to_owned()),
+            args,
+        };
+        let user_mode = |nick: &str, modes: &str| Command::Mode {
+            target: nick.to_owned(),
+            modes: Some(modes.to_owned()),
+            args: Vec::new(),
+        };
+
+        let command = match self {
+            ModerationAction::Kick { nick, reason } => Command::Kick {
+                channel: channel.to_owned(),
+                nick: nick.clone(),
+                reason: reason.clone(),
+            },
+            ModerationAction::Ban {
+                mask,
+                duration,
+                reason,
+            } => {
+                let mut entry = AccessEntry::new(AccessLevel::Deny, mask.clone());
+                if let Some(duration) = duration {
+                    entry = entry.with_timeout(*duration);
+                }
+                entry.reason = reason.clone();
+                entry.add_command(channel)?
+            }
+            ModerationAction::Unban { mask } => {
+                access::delete_command(channel, AccessLevel::Deny, mask)?
+            }
+            ModerationAction::Gag { nick } => user_mode(nick, "+z"),
+            ModerationAction::Ungag { nick } => user_mode(nick, "-z"),
+            ModerationAction::Voice { nick } => mode("+v", vec![nick.clone()]),
+            ModerationAction::Devoice { nick } => mode("-v", vec![nick.clone()]),
+            ModerationAction::Moderated(true) => mode("+m", Vec::new()),
+            ModerationAction::Moderated(false) => mode("-m", Vec::new()),
+        };
+        Ok(vec![command])
+    }
+
+    /// Authorizes `actor` using the roles in `channel` and compiles the action.
+    pub fn compile(&self, channel: &Channel, actor: &str) -> Result<Moderation, ModerationError> {
+        let actor_role = channel
+            .member(actor)
+            .ok_or_else(|| ModerationError::NotInChannel(actor.to_owned()))?
+            .role;
+        let target_role = match self.target_nick() {
+            Some(nick) => Some(
+                channel
+                    .member(nick)
+                    .ok_or_else(|| ModerationError::NotInChannel(nick.to_owned()))?
+                    .role,
+            ),
+            None => None,
+        };
+        self.authorize(actor_role, target_role)?;
+
+        Ok(Moderation {
+            commands: self.commands(channel.name())?,
+            record: ModerationRecord {
+                actor: actor.to_owned(),
+                channel: channel.name().to_owned(),
+                action: self.clone(),
+                at: SystemTime::now(),
+            },
+        })
+    }
+}
+
+/// Who did what, where and when; one per compiled action.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModerationRecord {
+    pub actor: String,
+    pub channel: String,
+    pub action: ModerationAction,
+    pub at: SystemTime,
+}
+
+/// An authorized action ready to send.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Moderation {
+    pub commands: Vec<Command>,
+    pub record: ModerationRecord,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `%#Lobby` with `.Olga` (owner), `@Hank` (host), `Pete`, and sysop `Sam`.
+    fn lobby() -> Channel {
+        let numeric = |code: &str, params: &[&str]| Command::Other {
+            command: code.to_owned(),
+            params: std::iter::once("Me")
+                .chain(params.iter().copied())
+                .map(str::to_owned)
+                .collect(),
+        };
+        let mut channel = Channel::new("%#Lobby");
+        channel.handle(
+            None,
+            &numeric("353", &["=", "%#Lobby", ".Olga @Hank Pete H,S,,Sam"]),
+        );
+        channel.handle(None, &numeric("366", &["%#Lobby", "End of /NAMES list."]));
+        channel
+    }
+
+    fn mode(target: &str, modes: &str, args: &[&str]) -> Command {
+        Command::Mode {
+            target: target.to_owned(),
+            modes: Some(modes.to_owned()),
+            args: args.iter().map(|a| a.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn authorize() {
+        let kick = ModerationAction::kick("Pete", None);
+        assert_eq!(
+            kick.authorize(UserRole::Participant, Some(UserRole::Participant)),
+            Err(ModerationError::NotPermitted {
+                role: UserRole::Participant,
+                privilege: Privilege::Kick,
+            })
+        );
+        assert_eq!(
+            kick.authorize(UserRole::Host, Some(UserRole::Owner)),
+            Err(ModerationError::Outranked {
+                nick: "Pete".into(),
+                role: UserRole::Owner,
+            })
+        );
+        assert_eq!(
+            kick.authorize(UserRole::Host, Some(UserRole::Participant)),
+            Ok(())
+        );
+        // Masks have no role to outrank.
+        let ban = ModerationAction::ban("*!*@host", None, None);
+        assert_eq!(ban.authorize(UserRole::Host, None), Ok(()));
+        assert_eq!(
+            ModerationAction::Moderated(true).authorize(UserRole::Host, None),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn gag_is_for_sysops() {
+        let gag = ModerationAction::Gag {
+            nick: "Pete".into(),
+        };
+        for role in [UserRole::Host, UserRole::Owner, UserRole::Guide] {
+            assert_eq!(
+                gag.authorize(role, Some(UserRole::Participant)),
+                Err(ModerationError::NotPermitted {
+                    role,
+                    privilege: Privilege::Gag,
+                })
+            );
+        }
+        assert_eq!(
+            gag.authorize(UserRole::Sysop, Some(UserRole::Participant)),
+            Ok(())
+        );
+        let ungag = ModerationAction::Ungag {
+            nick: "Pete".into(),
+        };
+        assert_eq!(
+            ungag.compile(&lobby(), "Sam").unwrap().commands,
+            [mode("Pete", "-z", &[])]
+        );
+        assert!(ungag.compile(&lobby(), "Olga").is_err());
+    }
+
+    #[test]
+    fn compile_looks_up_roles() {
+        let channel = lobby();
+        let kick = ModerationAction::kick("Pete", Some("flooding"));
+        assert_eq!(
+            kick.compile(&channel, "Ghost"),
+            Err(ModerationError::NotInChannel("Ghost".into()))
+        );
+        assert_eq!(
+            ModerationAction::kick("Ghost", None).compile(&channel, "Hank"),
+            Err(ModerationError::NotInChannel("Ghost".into()))
+        );
+        assert!(matches!(
+            ModerationAction::kick("Olga", None).compile(&channel, "hank"),
+            Err(ModerationError::Outranked { .. })
+        ));
+
+        let moderation = kick.compile(&channel, "Hank").unwrap();
+        assert_eq!(
+            moderation.commands,
+            [Command::Kick {
+                channel: "%#Lobby".into(),
+                nick: "Pete".into(),
+                reason: Some("flooding".into()),
+            }]
+        );
+        assert_eq!(moderation.record.actor, "Hank");
+        assert_eq!(moderation.record.channel, "%#Lobby");
+        assert_eq!(moderation.record.action, kick);
+    }
+
+    #[test]
+    fn timed_bans_round_up_to_minutes() {
+        let ban = |duration| {
+            let action = ModerationAction::ban("*!*@spam.example", duration, Some("spam"));
+            action.commands("%#Lobby").unwrap().remove(0).to_string()
+        };
+        assert_eq!(ban(None), "ACCESS %#Lobby ADD DENY *!*@spam.example 0 spam");
+        assert_eq!(
+            ban(Some(Duration::from_secs(1))),
+            "ACCESS %#Lobby ADD DENY *!*@spam.example 1 spam"
+        );
+        assert_eq!(
+            ban(Some(Duration::from_secs(90))),
+            "ACCESS %#Lobby ADD DENY *!*@spam.example 2 spam"
+        );
+        assert_eq!(
+            ban(Some(Duration::from_secs(3600))),
+            "ACCESS %#Lobby ADD DENY *!*@spam.example 60 spam"
+        );
+    }
+
+    #[test]
+    fn commands() {
+        let nick = || "Pete".to_owned();
+        let cases = [
+            (ModerationAction::kick("Pete", None), "KICK %#Lobby Pete"),
+            (
+                ModerationAction::Unban {
+                    mask: "*!*@host".into(),
+                },
+                "ACCESS %#Lobby DELETE DENY *!*@host",
+            ),
+            (ModerationAction::Gag { nick: nick() }, "MODE Pete +z"),
+            (ModerationAction::Ungag { nick: nick() }, "MODE Pete -z"),
+            (
+                ModerationAction::Voice { nick: nick() },
+                "MODE %#Lobby +v Pete",
+            ),
+            (
+                ModerationAction::Devoice { nick: nick() },
+                "MODE %#Lobby -v Pete",
+            ),
+            (ModerationAction::Moderated(true), "MODE %#Lobby +m"),
+            (ModerationAction::Moderated(false), "MODE %#Lobby -m"),
+        ];
+        for (action, expected) in cases {
+            let commands = action.commands("%#Lobby").unwrap();
+            let lines: Vec<_> = commands.iter().map(Command::to_string).collect();
+            assert_eq!(lines, [expected], "{}", action.name());
+        }
+
+        let bad_mask = ModerationAction::Unban {
+            mask: String::new(),
+        };
+        assert!(matches!(
+            bad_mask.commands("%#Lobby"),
+            Err(ModerationError::Access(_))
+        ));
+    }
+}
@@ -0,0 +1,479 @@
+use std::fmt;
+
+use super::message::Message;
+
+/// Declares the [`Numeric`] enum together with its code and name tables.
+macro_rules! numerics {
+    ($($variant:ident = $code:literal => $name:literal,)*) => {
+        /// A numeric reply code, RFC 1459 plus the MSN 7xx and IRCX 8xx and 9xx ranges.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum Numeric {
+            $($variant,)*
+            Unknown(u16),
+        }
+
+        impl Numeric {
+            pub fn from_code(code: u16) -> Self {
+                match code {
+                    $($code => Numeric::$variant,)*
+                    other => Numeric::Unknown(other),
+                }
+            }
+
+            pub fn code(self) -> u16 {
+                match self {
+                    $(Numeric::$variant => $code,)*
+                    Numeric::Unknown(code) => code,
+                }
+            }
+
+            /// Symbolic name such as `ERR_NICKNAMEINUSE`.
+            pub fn name(self) -> Option<&'static str> {
+                match self {
+                    $(Numeric::$variant => Some($name),)*
+                    Numeric::Unknown(_) => None,
+                }
+            }
+        }
+    };
+}
+
+numerics! {
+    Welcome = 1 => "RPL_WELCOME",
+    YourHost = 2 => "RPL_YOURHOST",
+    Created = 3 => "RPL_CREATED",
+    MyInfo = 4 => "RPL_MYINFO",
+    UModeIs = 221 => "RPL_UMODEIS",
+    LuserClient = 251 => "RPL_LUSERCLIENT",
+    LuserOp = 252 => "RPL_LUSEROP",
+    LuserUnknown = 253 => "RPL_LUSERUNKNOWN",
+    LuserChannels = 254 => "RPL_LUSERCHANNELS",
+    LuserMe = 255 => "RPL_LUSERME",
+    LocalUsers = 265 => "RPL_LOCALUSERS",
+    GlobalUsers = 266 => "RPL_GLOBALUSERS",
+    Away = 301 => "RPL_AWAY",
+    UnAway = 305 => "RPL_UNAWAY",
+    NowAway = 306 => "RPL_NOWAWAY",
+    WhoisUser = 311 => "RPL_WHOISUSER",
+    WhoisServer = 312 => "RPL_WHOISSERVER",
+    WhoisOperator = 313 => "RPL_WHOISOPERATOR",
+    EndOfWho = 315 => "RPL_ENDOFWHO",
+    WhoisIdle = 317 => "RPL_WHOISIDLE",
+    EndOfWhois = 318 => "RPL_ENDOFWHOIS",
+    WhoisChannels = 319 => "RPL_WHOISCHANNELS",
+    ListStart = 321 => "RPL_LISTSTART",
+    List = 322 => "RPL_LIST",
+    ListEnd = 323 => "RPL_LISTEND",
+    ChannelModeIs = 324 => "RPL_CHANNELMODEIS",
+    NoTopic = 331 => "RPL_NOTOPIC",
+    Topic = 332 => "RPL_TOPIC",
+    TopicWhoTime = 333 => "RPL_TOPICWHOTIME",
+    Inviting = 341 => "RPL_INVITING",
+    WhoReply = 352 => "RPL_WHOREPLY",
+    NamReply = 353 => "RPL_NAMREPLY",
+    EndOfNames = 366 => "RPL_ENDOFNAMES",
+    Motd = 372 => "RPL_MOTD",
+    MotdStart = 375 => "RPL_MOTDSTART",
+    EndOfMotd = 376 => "RPL_ENDOFMOTD",
+    YoureOper = 381 => "RPL_YOUREOPER",
+    Time = 391 => "RPL_TIME",
+
+    NoSuchNick = 401 => "ERR_NOSUCHNICK",
+    NoSuchServer = 402 => "ERR_NOSUCHSERVER",
+    NoSuchChannel = 403 => "ERR_NOSUCHCHANNEL",
+    CannotSendToChan = 404 => "ERR_CANNOTSENDTOCHAN",
+    TooManyChannels = 405 => "ERR_TOOMANYCHANNELS",
+    NoOrigin = 409 => "ERR_NOORIGIN",
+    NoRecipient = 411 => "ERR_NORECIPIENT",
+    NoTextToSend = 412 => "ERR_NOTEXTTOSEND",
+    UnknownCommand = 421 => "ERR_UNKNOWNCOMMAND",
+    NoMotd = 422 => "ERR_NOMOTD",
+    NoNicknameGiven = 431 => "ERR_NONICKNAMEGIVEN",
+    ErroneousNickname = 432 => "ERR_ERRONEUSNICKNAME",
+    NicknameInUse = 433 => "ERR_NICKNAMEINUSE",
+    NickCollision = 436 => "ERR_NICKCOLLISION",
+    UserNotInChannel = 441 => "ERR_USERNOTINCHANNEL",
+    NotOnChannel = 442 => "ERR_NOTONCHANNEL",
+    UserOnChannel = 443 => "ERR_USERONCHANNEL",
+    NotRegistered = 451 => "ERR_NOTREGISTERED",
+    NeedMoreParams = 461 => "ERR_NEEDMOREPARAMS",
+    AlreadyRegistered = 462 => "ERR_ALREADYREGISTRED",
+    PasswdMismatch = 464 => "ERR_PASSWDMISMATCH",
+    YoureBannedCreep = 465 => "ERR_YOUREBANNEDCREEP",
+    KeySet = 467 => "ERR_KEYSET",
+    ChannelIsFull = 471 => "ERR_CHANNELISFULL",
+    UnknownMode = 472 => "ERR_UNKNOWNMODE",
+    InviteOnlyChan = 473 => "ERR_INVITEONLYCHAN",
+    BannedFromChan = 474 => "ERR_BANNEDFROMCHAN",
+    BadChannelKey = 475 => "ERR_BADCHANNELKEY",
+    BadChanName = 479 => "ERR_BADCHANNAME",
+    NoPrivileges = 481 => "ERR_NOPRIVILEGES",
+    ChanOpPrivsNeeded = 482 => "ERR_CHANOPRIVSNEEDED",
+    CantKillServer = 483 => "ERR_CANTKILLSERVER",
+    NoOperHost = 491 => "ERR_NOOPERHOST",
+    UModeUnknownFlag = 501 => "ERR_UMODEUNKNOWNFLAG",
+    UsersDontMatch = 502 => "ERR_USERSDONTMATCH",
+
+    BadCategory = 701 => "IRCERR_BADCATEGORY",
+    Profanity = 706 => "IRCERR_PROFANITY",
+
+    Ircx = 800 => "IRCRPL_IRCX",
+    AccessAdd = 801 => "IRCRPL_ACCESSADD",
+    AccessDelete = 802 => "IRCRPL_ACCESSDELETE",
+    AccessStart = 803 => "IRCRPL_ACCESSSTART",
+    AccessList = 804 => "IRCRPL_ACCESSLIST",
+    AccessEnd = 805 => "IRCRPL_ACCESSEND",
+    EventAdd = 806 => "IRCRPL_EVENTADD",
+    EventDel = 807 => "IRCRPL_EVENTDEL",
+    EventStart = 808 => "IRCRPL_EVENTSTART",
+    EventList = 809 => "IRCRPL_EVENTLIST",
+    EventEnd = 810 => "IRCRPL_EVENTEND",
+    ListxStart = 811 => "IRCRPL_LISTXSTART",
+    ListxList = 812 => "IRCRPL_LISTXLIST",
+    ListxPics = 813 => "IRCRPL_LISTXPICS",
+    ListxTrunc = 816 => "IRCRPL_LISTXTRUNC",
+    ListxEnd = 817 => "IRCRPL_LISTXEND",
+    PropList = 818 => "IRCRPL_PROPLIST",
+    PropEnd = 819 => "IRCRPL_PROPEND",
+    AccessClear = 820 => "IRCRPL_ACCESSCLEAR",
+    UserUnaway = 821 => "IRCRPL_USERUNAWAY",
+    UserNowAway = 822 => "IRCRPL_USERNOWAWAY",
+
+    BadCommand = 900 => "IRCERR_BADCOMMAND",
+    TooManyArguments = 901 => "IRCERR_TOOMANYARGUMENTS",
+    BadFunction = 902 => "IRCERR_BADFUNCTION",
+    BadLevel = 903 => "IRCERR_BADLEVEL",
+    BadTag = 904 => "IRCERR_BADTAG",
+    BadProperty = 905 => "IRCERR_BADPROPERTY",
+    BadValue = 906 => "IRCERR_BADVALUE",
+    Resource = 907 => "IRCERR_RESOURCE",
+    Security = 908 => "IRCERR_SECURITY",
+    AlreadyAuthenticated = 909 => "IRCERR_ALREADYAUTHENTICATED",
+    AuthenticationFailed = 910 => "IRCERR_AUTHENTICATIONFAILED",
+    AuthenticationSuspended = 911 => "IRCERR_AUTHENTICATIONSUSPENDED",
+    UnknownPackage = 912 => "IRCERR_UNKNOWNPACKAGE",
+    NoAccess = 913 => "IRCERR_NOACCESS",
+    DupAccess = 914 => "IRCERR_DUPACCESS",
+    MisAccess = 915 => "IRCERR_MISACCESS",
+    TooManyAccesses = 916 => "IRCERR_TOOMANYACCESSES",
+    EventDup = 918 => "IRCERR_EVENTDUP",
+    EventMis = 919 => "IRCERR_EVENTMIS",
+    NoSuchEvent = 920 => "IRCERR_NOSUCHEVENT",
+    TooManyEvents = 921 => "IRCERR_TOOMANYEVENTS",
+    NoWhisper = 923 => "IRCERR_NOWHISPER",
+    NoSuchObject = 924 => "IRCERR_NOSUCHOBJECT",
+    NotSupported = 925 => "IRCERR_NOTSUPPORTED",
+    ChannelExist = 926 => "IRCERR_CHANNELEXIST",
+    AlreadyOnChannel = 927 => "IRCERR_ALREADYONCHANNEL",
+    UnknownError = 999 => "IRCERR_UNKNOWNERROR",
+}
+
+impl Numeric {
+    /// True for the `4xx`/`5xx` RFC errors, the MSN `CREATE` errors and the `9xx` IRCX errors.
+    pub fn is_error(self) -> bool {
+        matches!(self.code(), 400..=599 | 900..=999)
+            || matches!(self, Numeric::BadCategory | Numeric::Profanity)
+    }
+
+    /// The numeric of `msg`, if it is a numeric reply.
+    pub fn of(msg: &Message<'_>) -> Option<Self> {
+        msg.numeric().map(Numeric::from_code)
+    }
+}
+
+impl fmt::Display for Numeric {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name() {
+            Some(name) => write!(f, "{:03} {name}", self.code()),
+            None => write!(f, "{:03}", self.code()),
+        }
+    }
+}
+
+/// An error numeric returned by the server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerError {
+    pub numeric: Numeric,
+    /// Parameters after the recipient nick, excluding the human-readable text.
+    pub params: Vec<String>,
+    /// The server's explanation, usually the trailing parameter.
+    pub text: String,
+}
+
+impl ServerError {
+    /// Builds a `ServerError` from `msg` if it carries an error numeric.
+    pub fn from_message(msg: &Message<'_>) -> Option<Self> {
+        let numeric = Numeric::of(msg).filter(|n| n.is_error())?;
+        let mut args: Vec<&str> = msg.args().skip(1).collect();
+        let text = match msg.trailing {
+            Some(_) => args.pop().unwrap_or_default().to_string(),
+            None => String::new(),
+        };
+        Some(Self {
+            numeric,
+            params: args.into_iter().map(str::to_string).collect(),
+            text,
+        })
+    }
+
+    /// The object the error refers to, e.g. the channel or nickname.
+    pub fn subject(&self) -> Option<&str> {
+        self.params.first().map(String::as_str)
+    }
+}
+
+impl fmt::Display for ServerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.numeric)?;
+        if let Some(subject) = self.subject() {
+            write!(f, " ({subject})")?;
+        }
+        if !self.text.is_empty() {
+            write!(f, ": {}", self.text)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ServerError {}
+
+/// Typed parameters of the replies clients most often act on.
+///
+/// Every variant skips the leading recipient nick, except [`Reply::Welcome`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Reply<'a> {
+    /// `001 <nick> :<text>`; `nick` is the one the server registered, which
+    /// may differ from the one requested.
+    Welcome {
+        nick: &'a str,
+        text: &'a str,
+    },
+    /// `800 <nick> <state> <version> <packages> <maxmsg> <options>`
+    Ircx {
+        enabled: bool,
+        version: &'a str,
+        packages: Vec<&'a str>,
+        max_message_len: Option<usize>,
+        options: &'a str,
+    },
+    Topic {
+        channel: &'a str,
+        topic: &'a str,
+    },
+    NoTopic {
+        channel: &'a str,
+    },
+    /// `353 <nick> <visibility> <channel> :<names>`
+    Names {
+        visibility: &'a str,
+        channel: &'a str,
+        names: Vec<&'a str>,
+    },
+    EndOfNames {
+        channel: &'a str,
+    },
+    ChannelModes {
+        channel: &'a str,
+        modes: Vec<&'a str>,
+    },
+    /// `818 <nick> <target> <property> :<value>`
+    Prop {
+        target: &'a str,
+        property: &'a str,
+        value: &'a str,
+    },
+    PropEnd {
+        target: &'a str,
+    },
+    /// `804 <nick> <target> <level> <mask> <timeout> <creator> :<reason>`
+    Access {
+        target: &'a str,
+        level: &'a str,
+        mask: &'a str,
+        timeout: &'a str,
+        creator: &'a str,
+        reason: &'a str,
+    },
+    AccessEnd {
+        target: &'a str,
+    },
+    Error(ServerError),
+}
+
+impl<'a> Reply<'a> {
+    /// Extracts typed parameters, or `None` for replies without a typed form.
+    pub fn parse(msg: &Message<'a>) -> Option<Self> {
+        let numeric = Numeric::of(msg)?;
+        if let Some(err) = ServerError::from_message(msg) {
+            return Some(Reply::Error(err));
+        }
+
+        let nick = msg.arg(0)?;
+        let args: Vec<&'a str> = msg.args().skip(1).collect();
+        let arg = |i: usize| args.get(i).copied();
+
+        let reply = match numeric {
+            Numeric::Welcome => Reply::Welcome {
+                nick,
+                text: arg(0).unwrap_or_default(),
+            },
+            Numeric::Ircx => Reply::Ircx {
+                enabled: arg(0)? == "1",
+                version: arg(1)?,
+                packages: arg(2)?.split(',').filter(|p| !p.is_empty()).collect(),
+                max_message_len: arg(3).and_then(|n| n.parse().ok()),
+                options: arg(4).unwrap_or("*"),
+            },
+            Numeric::Topic => Reply::Topic {
+                channel: arg(0)?,
+                topic: arg(1).unwrap_or_default(),
+            },
+            Numeric::NoTopic => Reply::NoTopic { channel: arg(0)? },
+            Numeric::NamReply => Reply::Names {
+                visibility: arg(0)?,
+                channel: arg(1)?,
+                names: arg(2).unwrap_or_default().split_whitespace().collect(),
+            },
+            Numeric::EndOfNames => Reply::EndOfNames { channel: arg(0)? },
+            Numeric::ChannelModeIs => Reply::ChannelModes {
+                channel: arg(0)?,
+                modes: args[1..].to_vec(),
+            },
+            Numeric::PropList => Reply::Prop {
+                target: arg(0)?,
+                property: arg(1)?,
+                value: arg(2).unwrap_or_default(),
+            },
+            Numeric::PropEnd => Reply::PropEnd { target: arg(0)? },
+            Numeric::AccessList => Reply::Access {
+                target: arg(0)?,
+                level: arg(1)?,
+                mask: arg(2)?,
+                timeout: arg(3).unwrap_or("0"),
+                creator: arg(4).unwrap_or_default(),
+                reason: arg(5).unwrap_or_default(),
+            },
+            Numeric::AccessEnd => Reply::AccessEnd { target: arg(0)? },
+            _ => return None,
+        };
+
+        Some(reply)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error as _;
+
+    use super::*;
+    use crate::Error;
+
+    #[test]
+    fn codes_round_trip() {
+        let mut known = 0;
+        for code in 0..1000 {
+            let numeric = Numeric::from_code(code);
+            assert_eq!(numeric.code(), code);
+            match numeric {
+                Numeric::Unknown(unknown) => {
+                    assert_eq!(unknown, code);
+                    assert_eq!(numeric.name(), None);
+                }
+                _ => {
+                    known += 1;
+                    let name = numeric.name().unwrap();
+                    assert!(
+                        name.starts_with("RPL_")
+                            || name.contains("ERR_")
+                            || name.starts_with("IRCRPL_")
+                    );
+                    assert_eq!(name.contains("ERR_"), numeric.is_error(), "{numeric}");
+                }
+            }
+        }
+        assert!(known > 100);
+        assert_eq!(Numeric::from_code(433), Numeric::NicknameInUse);
+        assert_eq!(Numeric::from_code(2000), Numeric::Unknown(2000));
+    }
+
+    #[test]
+    fn unknown_codes_are_kept() {
+        let numeric = Numeric::from_code(598);
+        assert_eq!(numeric, Numeric::Unknown(598));
+        assert!(numeric.is_error());
+        assert!(!Numeric::from_code(700).is_error());
+        assert_eq!(numeric.to_string(), "598");
+        assert_eq!(Numeric::Welcome.to_string(), "001 RPL_WELCOME");
+
+        let msg = Message::parse(":server 598 Alice %#Lobby :something new").unwrap();
+        let err = ServerError::from_message(&msg).unwrap();
+        assert_eq!(err.numeric, Numeric::Unknown(598));
+        assert_eq!(err.to_string(), "598 (%#Lobby): something new");
+    }
+
+    #[test]
+    fn server_errors() {
+        let msg = Message::parse(":server 433 * Alice :Nickname is already in use").unwrap();
+        let err = ServerError::from_message(&msg).unwrap();
+        assert_eq!(err.numeric, Numeric::NicknameInUse);
+        assert_eq!(err.params, ["Alice"]);
+        assert_eq!(err.subject(), Some("Alice"));
+        assert_eq!(err.text, "Nickname is already in use");
+        assert_eq!(
+            err.to_string(),
+            "433 ERR_NICKNAMEINUSE (Alice): Nickname is already in use"
+        );
+
+        // Without a trailing parameter every argument is kept and there is no text.
+        let msg = Message::parse(":server 913 Alice %#Lobby").unwrap();
+        let err = ServerError::from_message(&msg).unwrap();
+        assert_eq!(err.params, ["%#Lobby"]);
+        assert_eq!(err.text, "");
+
+        let msg = Message::parse(":server 332 Alice %#Lobby :Welcome").unwrap();
+        assert_eq!(ServerError::from_message(&msg), None);
+        assert!(Error::from_reply(&msg).is_none());
+        let msg = Message::parse("PING :server").unwrap();
+        assert_eq!(ServerError::from_message(&msg), None);
+    }
+
+    #[test]
+    fn error_conversions() {
+        let msg = Message::parse(":server 474 Alice %#Lobby :Cannot join channel (+b)").unwrap();
+        let server = ServerError::from_message(&msg).unwrap();
+
+        let err = Error::from_reply(&msg).unwrap();
+        assert!(matches!(&err, Error::Server(inner) if *inner == server));
+        assert_eq!(err.to_string(), format!("server error: {server}"));
+        assert_eq!(err.source().unwrap().to_string(), server.to_string());
+
+        let err: Error = server.clone().into();
+        assert!(matches!(err, Error::Server(inner) if inner == server));
+        assert!(server.source().is_none());
+    }
+
+    #[test]
+    fn typed_replies() {
+        let msg = Message::parse(":server 001 Alice2 :Welcome").unwrap();
+        assert_eq!(
+            Reply::parse(&msg),
+            Some(Reply::Welcome {
+                nick: "Alice2",
+                text: "Welcome",
+            })
+        );
+        let msg = Message::parse(":server 353 Alice = %#Lobby :.Alice @Bob Carol").unwrap();
+        assert_eq!(
+            Reply::parse(&msg),
+            Some(Reply::Names {
+                visibility: "=",
+                channel: "%#Lobby",
+                names: vec![".Alice", "@Bob", "Carol"],
+            })
+        );
+        let msg = Message::parse(":server 403 Alice %#Gone :No such channel").unwrap();
+        assert!(matches!(Reply::parse(&msg), Some(Reply::Error(_))));
+        let msg = Message::parse(":server 372 Alice :- motd").unwrap();
+        assert_eq!(Reply::parse(&msg), None);
+    }
+}
@@ -0,0 +1,268 @@
+//! Passport credentials for the `GateKeeperPassport` package.
+//!
+//! After the GKSSP challenge succeeds the server sends `AUTH GateKeeperPassport S :OK`
+//! and expects the ticket and profile back, each prefixed by its length as
+//! eight upper-case hex digits:
+//!
+//! ```text
+//! AUTH GateKeeperPassport S :0000000At=AbCdEfGh00000004p=Zz
+//! ```
+
+use std::fmt;
+
+use super::command::Command;
+
+/// Width of the hex length prefix in front of each field.
+const LENGTH_PREFIX: usize = 8;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PassportError {
+    Empty(&'static str),
+    /// A byte outside printable ASCII; tickets are URL-safe tokens.
+    IllegalByte {
+        field: &'static str,
+        offset: usize,
+    },
+    /// A length prefix that is not eight hex digits.
+    BadLength(usize),
+    Truncated(usize),
+    /// Bytes left over after the profile.
+    TrailingData(usize),
+}
+
+impl fmt::Display for PassportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PassportError::Empty(field) => write!(f, "Passport {field} is empty"),
+            PassportError::IllegalByte { field, offset } => {
+                write!(f, "illegal byte in Passport {field} at offset {offset}")
+            }
+            PassportError::BadLength(offset) => write!(f, "bad length prefix at byte {offset}"),
+            PassportError::Truncated(offset) => write!(f, "payload truncated at byte {offset}"),
+            PassportError::TrailingData(offset) => write!(f, "unexpected data at byte {offset}"),
+        }
+    }
+}
+
+impl std::error::Error for PassportError {}
+
+/// The `PassportTicket`, `PassportProfile` and optional `MSNREGCookie` of a frame.
+///
+/// Constructed values are always valid. `Debug` never prints the secrets.
+#[derive(Clone, PartialEq, Eq)]
+pub struct PassportCredentials {
+    ticket: String,
+    profile: String,
+    regcookie: Option<String>,
+}
+
+impl PassportCredentials {
+    pub fn new(
+        ticket: impl Into<String>,
+        profile: impl Into<String>,
+    ) -> Result<Self, PassportError> {
+        let ticket = ticket.into();
+        let profile = profile.into();
+        validate("ticket", &ticket)?;
+        validate("profile", &profile)?;
+        Ok(Self {
+            ticket,
+            profile,
+            regcookie: None,
+        })
+    }
+
+    pub fn with_regcookie(mut self, cookie: impl Into<String>) -> Result<Self, PassportError> {
+        let cookie = cookie.into();
+        validate("MSNREGCookie", &cookie)?;
+        self.regcookie = Some(cookie);
+        Ok(self)
+    }
+
+    pub fn ticket(&self) -> &str {
+        &self.ticket
+    }
+
+    pub fn profile(&self) -> &str {
+        &self.profile
+    }
+
+    pub fn regcookie(&self) -> Option<&str> {
+        self.regcookie.as_deref()
+    }
+
+    /// The ticket/profile blob sent in reply to the server's `OK`.
+    pub fn encode_payload(&self) -> Vec<u8> {
+        let mut out =
+            Vec::with_capacity(2 * LENGTH_PREFIX + self.ticket.len() + self.profile.len());
+        encode_field(&mut out, &self.ticket);
+        encode_field(&mut out, &self.profile);
+        out
+    }
+
+    /// Parses a blob produced by [`encode_payload`](Self::encode_payload).
+    pub fn decode_payload(data: &[u8]) -> Result<Self, PassportError> {
+        let (ticket, offset) = decode_field(data, 0)?;
+        let (profile, offset) = decode_field(data, offset)?;
+        if offset != data.len() {
+            return Err(PassportError::TrailingData(offset));
+        }
+        Self::new(ticket, profile)
+    }
+
+    /// `PROP $ MSNREGCOOKIE`, sent once registered so the server can attach the profile.
+    pub fn regcookie_command(&self) -> Option<Command> {
+        self.regcookie.as_ref().map(|cookie| Command::Prop {
+            target: "$".to_owned(),
+            property: "MSNREGCOOKIE".to_owned(),
+            value: Some(cookie.clone()),
+        })
+    }
+}
+
+impl fmt::Debug for PassportCredentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PassportCredentials")
+            .field("ticket", &Redacted(self.ticket.len()))
+            .field("profile", &Redacted(self.profile.len()))
+            .field(
+                "regcookie",
+                &self.regcookie.as_ref().map(|c| Redacted(c.len())),
+            )
+            .finish()
+    }
+}
+
+struct Redacted(usize);
+
+impl fmt::Debug for Redacted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<redacted, {} bytes>", self.0)
+    }
+}
+
+fn validate(field: &'static str, value: &str) -> Result<(), PassportError> {
+    if value.is_empty() {
+        return Err(PassportError::Empty(field));
+    }
+    match value.bytes().position(|b| !b.is_ascii_graphic()) {
+        Some(offset) => Err(PassportError::IllegalByte { field, offset }),
+        None => Ok(()),
+    }
+}
+
+fn encode_field(out: &mut Vec<u8>, value: &str) {
+    out.extend_from_slice(format!("{:08X}", value.len()).as_bytes());
+    out.extend_from_slice(value.as_bytes());
+}
+
+fn decode_field(data: &[u8], offset: usize) -> Result<(String, usize), PassportError> {
+    let prefix = data
+        .get(offset..offset + LENGTH_PREFIX)
+        .ok_or(PassportError::Truncated(offset))?;
+    let len = std::str::from_utf8(prefix)
+        .ok()
+        .filter(|s| s.bytes().all(|b| b.is_ascii_hexdigit()))
+        .and_then(|s| usize::from_str_radix(s, 16).ok())
+        .ok_or(PassportError::BadLength(offset))?;
+
+    let start = offset + LENGTH_PREFIX;
+    let end = start
+        .checked_add(len)
+        .ok_or(PassportError::BadLength(offset))?;
+    let value = data
+        .get(start..end)
+        .ok_or(PassportError::Truncated(start))?;
+    Ok((String::from_utf8_lossy(value).into_owned(), end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn creds() -> PassportCredentials {
+        PassportCredentials::new("t=AbCdEfGh", "p=Zz").unwrap()
+    }
+
+    #[test]
+    fn payload_round_trips() {
+        let payload = creds().encode_payload();
+        assert_eq!(payload, b"0000000At=AbCdEfGh00000004p=Zz");
+        assert_eq!(PassportCredentials::decode_payload(&payload), Ok(creds()));
+    }
+
+    #[test]
+    fn decode_errors() {
+        let decode = PassportCredentials::decode_payload;
+        assert_eq!(decode(b""), Err(PassportError::Truncated(0)));
+        assert_eq!(decode(b"0000000At=AbCd"), Err(PassportError::Truncated(8)));
+        assert_eq!(
+            decode(b"0000000At=AbCdEfGh0004"),
+            Err(PassportError::Truncated(18))
+        );
+        assert_eq!(
+            decode(b"0000000Gt=AbCdEfGh00000004p=Zz"),
+            Err(PassportError::BadLength(0))
+        );
+        assert_eq!(
+            decode(b"+000000At=AbCdEfGh00000004p=Zz"),
+            Err(PassportError::BadLength(0))
+        );
+        assert_eq!(
+            decode(b"0000000At=AbCdEfGh00000004p=Zz!"),
+            Err(PassportError::TrailingData(30))
+        );
+        assert_eq!(
+            decode(b"0000000000000004p=Zz"),
+            Err(PassportError::Empty("ticket"))
+        );
+        assert_eq!(
+            decode(b"00000003a b00000004p=Zz"),
+            Err(PassportError::IllegalByte {
+                field: "ticket",
+                offset: 1,
+            })
+        );
+    }
+
+    #[test]
+    fn validation() {
+        assert_eq!(
+            PassportCredentials::new("t=1", ""),
+            Err(PassportError::Empty("profile"))
+        );
+        assert_eq!(
+            PassportCredentials::new("t=1\r\n", "p=1"),
+            Err(PassportError::IllegalByte {
+                field: "ticket",
+                offset: 3,
+            })
+        );
+        assert_eq!(
+            creds().with_regcookie("é"),
+            Err(PassportError::IllegalByte {
+                field: "MSNREGCookie",
+                offset: 0,
+            })
+        );
+
+        assert_eq!(creds().regcookie_command(), None);
+        let with_cookie = creds().with_regcookie("cookie").unwrap();
+        assert_eq!(with_cookie.regcookie(), Some("cookie"));
+        assert_eq!(
+            with_cookie.regcookie_command().unwrap().to_string(),
+            "PROP $ MSNREGCOOKIE :cookie"
+        );
+    }
+
+    #[test]
+    fn debug_redacts_secrets() {
+        let creds = creds().with_regcookie("cookie").unwrap();
+        let debug = format!("{creds:?}");
+        assert_eq!(
+            debug,
+            "PassportCredentials { ticket: <redacted, 10 bytes>, \
+             profile: <redacted, 4 bytes>, regcookie: Some(<redacted, 6 bytes>) }"
+        );
+    }
+}
@@ -0,0 +1,487 @@
+use std::{fmt, str::FromStr};
+
+use super::{Command, Numeric, SessionEvent};
+use crate::types::ChannelLanguage;
+
+/// Highest `LAG` the server accepts, in seconds.
+pub const MAX_LAG: u8 = 2;
+
+/// An IRCX channel property.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ChannelProp {
+    Topic,
+    Subject,
+    /// Notice sent to each member on join; the frame's `WelcomeMsg`.
+    OnJoin,
+    OnPart,
+    Language,
+    Client,
+    /// When the channel was created, in seconds since the epoch.
+    Creation,
+    /// Seconds the server delays every message.
+    Lag,
+    /// PICS content rating label.
+    Pics,
+    OwnerKey,
+    HostKey,
+    MemberKey,
+}
+
+impl ChannelProp {
+    pub const ALL: [ChannelProp; 12] = [
+        ChannelProp::Topic,
+        ChannelProp::Subject,
+        ChannelProp::OnJoin,
+        ChannelProp::OnPart,
+        ChannelProp::Language,
+        ChannelProp::Client,
+        ChannelProp::Creation,
+        ChannelProp::Lag,
+        ChannelProp::Pics,
+        ChannelProp::OwnerKey,
+        ChannelProp::HostKey,
+        ChannelProp::MemberKey,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ChannelProp::Topic => "TOPIC",
+            ChannelProp::Subject => "SUBJECT",
+            ChannelProp::OnJoin => "ONJOIN",
+            ChannelProp::OnPart => "ONPART",
+            ChannelProp::Language => "LANGUAGE",
+            ChannelProp::Client => "CLIENT",
+            ChannelProp::Creation => "CREATION",
+            ChannelProp::Lag => "LAG",
+            ChannelProp::Pics => "PICS",
+            ChannelProp::OwnerKey => "OWNERKEY",
+            ChannelProp::HostKey => "HOSTKEY",
+            ChannelProp::MemberKey => "MEMBERKEY",
+        }
+    }
+
+    /// Longest value the server accepts.
+    pub fn max_len(self) -> usize {
+        match self {
+            ChannelProp::Topic => 160,
+            ChannelProp::Subject => 32,
+            ChannelProp::OwnerKey | ChannelProp::HostKey | ChannelProp::MemberKey => 31,
+            ChannelProp::Language => 3,
+            ChannelProp::Creation => 20,
+            ChannelProp::Lag => 1,
+            ChannelProp::OnJoin | ChannelProp::OnPart | ChannelProp::Client | ChannelProp::Pics => {
+                255
+            }
+        }
+    }
+
+    /// Set by the server; `PROP` cannot change it.
+    pub fn is_read_only(self) -> bool {
+        self == ChannelProp::Creation
+    }
+
+    /// Only owners may read or set it.
+    pub fn is_owner_only(self) -> bool {
+        matches!(self, ChannelProp::OwnerKey | ChannelProp::HostKey)
+    }
+
+    /// Keys are single words.
+    fn allows_spaces(self) -> bool {
+        !matches!(
+            self,
+            ChannelProp::OwnerKey | ChannelProp::HostKey | ChannelProp::MemberKey
+        )
+    }
+}
+
+impl fmt::Display for ChannelProp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ChannelProp {
+    type Err = PropError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|p| p.as_str().eq_ignore_ascii_case(s))
+            .ok_or_else(|| PropError::Unknown(s.to_string()))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PropError {
+    Unknown(String),
+    ReadOnly(ChannelProp),
+    TooLong { prop: ChannelProp, max: usize },
+    IllegalChar { prop: ChannelProp, ch: char },
+    InvalidValue { prop: ChannelProp, value: String },
+}
+
+impl fmt::Display for PropError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PropError::Unknown(s) => write!(f, "unknown channel property `{s}`"),
+            PropError::ReadOnly(p) => write!(f, "channel property {p} is read-only"),
+            PropError::TooLong { prop, max } => {
+                write!(f, "{prop} is longer than {max} characters")
+            }
+            PropError::IllegalChar { prop, ch } => {
+                write!(f, "{prop} may not contain {:?}", ch)
+            }
+            PropError::InvalidValue { prop, value } => {
+                write!(f, "invalid {prop} value `{value}`")
+            }
+        }
+    }
+}
+
+impl std::error::Error for PropError {}
+
+/// Checks `value` against the length and character rules for `prop`.
+pub fn validate_prop(prop: ChannelProp, value: &str) -> Result<(), PropError> {
+    if value.chars().count() > prop.max_len() {
+        return Err(PropError::TooLong {
+            prop,
+            max: prop.max_len(),
+        });
+    }
+    let illegal = |c: char| {
+        matches!(c, '\0' | '\r' | '\n') || (!prop.allows_spaces() && matches!(c, ' ' | ','))
+    };
+    if let Some(ch) = value.chars().find(|c| illegal(*c)) {
+        return Err(PropError::IllegalChar { prop, ch });
+    }
+    Ok(())
+}
+
+/// The known properties of a channel; `None` when unset or not yet seen.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ChannelProps {
+    pub topic: Option<String>,
+    pub subject: Option<String>,
+    pub on_join: Option<String>,
+    pub on_part: Option<String>,
+    pub language: Option<ChannelLanguage>,
+    pub client: Option<String>,
+    pub creation: Option<u64>,
+    pub lag: Option<u8>,
+    pub pics: Option<String>,
+    pub owner_key: Option<String>,
+    pub host_key: Option<String>,
+    pub member_key: Option<String>,
+}
+
+impl ChannelProps {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The value of `prop` as sent on the wire.
+    pub fn get(&self, prop: ChannelProp) -> Option<String> {
+        match prop {
+            ChannelProp::Topic => self.topic.clone(),
+            ChannelProp::Subject => self.subject.clone(),
+            ChannelProp::OnJoin => self.on_join.clone(),
+            ChannelProp::OnPart => self.on_part.clone(),
+            ChannelProp::Language => self.language.map(|l| l.to_string()),
+            ChannelProp::Client => self.client.clone(),
+            ChannelProp::Creation => self.creation.map(|c| c.to_string()),
+            ChannelProp::Lag => self.lag.map(|l| l.to_string()),
+            ChannelProp::Pics => self.pics.clone(),
+            ChannelProp::OwnerKey => self.owner_key.clone(),
+            ChannelProp::HostKey => self.host_key.clone(),
+            ChannelProp::MemberKey => self.member_key.clone(),
+        }
+    }
+
+    /// Validates and stores a wire value; an empty value clears the property.
+    pub fn set(&mut self, prop: ChannelProp, value: &str) -> Result<(), PropError> {
+        validate_prop(prop, value)?;
+        let text = (!value.is_empty()).then(|| value.to_owned());
+        let invalid = || PropError::InvalidValue {
+            prop,
+            value: value.to_owned(),
+        };
+        match prop {
+            ChannelProp::Topic => self.topic = text,
+            ChannelProp::Subject => self.subject = text,
+            ChannelProp::OnJoin => self.on_join = text,
+            ChannelProp::OnPart => self.on_part = text,
+            ChannelProp::Language => {
+                self.language = text.map(|t| t.parse().map_err(|_| invalid())).transpose()?
+            }
+            ChannelProp::Client => self.client = text,
+            ChannelProp::Creation => {
+                self.creation = text.map(|t| t.parse().map_err(|_| invalid())).transpose()?
+            }
+            ChannelProp::Lag => {
+                self.lag = text
+                    .map(|t| t.parse().ok().filter(|l| *l <= MAX_LAG).ok_or_else(invalid))
+                    .transpose()?
+            }
+            ChannelProp::Pics => self.pics = text,
+            ChannelProp::OwnerKey => self.owner_key = text,
+            ChannelProp::HostKey => self.host_key = text,
+            ChannelProp::MemberKey => self.member_key = text,
+        }
+        Ok(())
+    }
+
+    /// Properties with a value, in [`ChannelProp::ALL`] order.
+    pub fn iter(&self) -> impl Iterator<Item = (ChannelProp, String)> + '_ {
+        ChannelProp::ALL
+            .into_iter()
+            .filter_map(|p| self.get(p).map(|v| (p, v)))
+    }
+
+    /// `PROP <channel> <prop>`, or `PROP <channel> *` for every property.
+    pub fn query(channel: impl Into<String>, prop: Option<ChannelProp>) -> Command {
+        Command::Prop {
+            target: channel.into(),
+            property: prop.map_or("*", ChannelProp::as_str).to_owned(),
+            value: None,
+        }
+    }
+
+    /// `PROP <channel> <prop> :<value>`; an empty value clears the property.
+    pub fn set_command(
+        channel: impl Into<String>,
+        prop: ChannelProp,
+        value: &str,
+    ) -> Result<Command, PropError> {
+        if prop.is_read_only() {
+            return Err(PropError::ReadOnly(prop));
+        }
+        ChannelProps::new().set(prop, value)?;
+        Ok(Command::Prop {
+            target: channel.into(),
+            property: prop.as_str().to_owned(),
+            value: Some(value.to_owned()),
+        })
+    }
+
+    /// One `PROP` command per writable property that has a value.
+    pub fn to_commands(&self, channel: &str) -> Result<Vec<Command>, PropError> {
+        self.iter()
+            .filter(|(p, _)| !p.is_read_only())
+            .map(|(p, v)| Self::set_command(channel, p, &v))
+            .collect()
+    }
+
+    /// Applies `event`; returns true if a property of `channel` changed.
+    pub fn handle_event(&mut self, channel: &str, event: &SessionEvent) -> bool {
+        match event {
+            SessionEvent::Message { command, .. } => self.handle(channel, command),
+            _ => false,
+        }
+    }
+
+    /// Applies a `PROP` change or an 818 reply for `channel`.
+    ///
+    /// Unknown properties and invalid values are ignored.
+    pub fn handle(&mut self, channel: &str, command: &Command) -> bool {
+        let (target, property, value) = match command {
+            Command::Prop {
+                target,
+                property,
+                value,
+            } => (target.as_str(), property.as_str(), value.as_deref()),
+            Command::Other { command, params }
+                if command.parse().map(Numeric::from_code) == Ok(Numeric::PropList) =>
+            {
+                // `818 <nick> <target> <property> :<value>`
+                match params.as_slice() {
+                    [_, target, property, rest @ ..] => (
+                        target.as_str(),
+                        property.as_str(),
+                        rest.first().map(String::as_str),
+                    ),
+                    _ => return false,
+                }
+            }
+            _ => return false,
+        };
+        // A `PROP` without a value is someone else's query.
+        let (Some(value), Ok(prop)) = (value, property.parse()) else {
+            return false;
+        };
+        if !target.eq_ignore_ascii_case(channel) {
+            return false;
+        }
+        let before = self.get(prop);
+        self.set(prop, value).is_ok() && self.get(prop) != before
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prop(target: &str, property: &str, value: Option<&str>) -> Command {
+        Command::Prop {
+            target: target.to_owned(),
+            property: property.to_owned(),
+            value: value.map(str::to_owned),
+        }
+    }
+
+    fn prop_list(params: &[&str]) -> Command {
+        Command::Other {
+            command: "818".to_owned(),
+            params: std::iter::once("Me")
+                .chain(params.iter().copied())
+                .map(str::to_owned)
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn names_round_trip() {
+        for prop in ChannelProp::ALL {
+            assert_eq!(prop.as_str().to_lowercase().parse(), Ok(prop));
+        }
+        assert_eq!(
+            "NAME".parse::<ChannelProp>(),
+            Err(PropError::Unknown("NAME".into()))
+        );
+    }
+
+    #[test]
+    fn value_rules() {
+        assert_eq!(validate_prop(ChannelProp::Topic, &"t".repeat(160)), Ok(()));
+        assert_eq!(
+            validate_prop(ChannelProp::Topic, &"t".repeat(161)),
+            Err(PropError::TooLong {
+                prop: ChannelProp::Topic,
+                max: 160,
+            })
+        );
+        // Lengths count characters, not bytes.
+        assert_eq!(validate_prop(ChannelProp::Subject, &"é".repeat(32)), Ok(()));
+        assert_eq!(
+            validate_prop(ChannelProp::Topic, "two words, one comma"),
+            Ok(())
+        );
+        assert_eq!(
+            validate_prop(ChannelProp::OnJoin, "hi\r\nQUIT"),
+            Err(PropError::IllegalChar {
+                prop: ChannelProp::OnJoin,
+                ch: '\r',
+            })
+        );
+        for (key, ch) in [("two words", ' '), ("a,b", ',')] {
+            assert_eq!(
+                validate_prop(ChannelProp::MemberKey, key),
+                Err(PropError::IllegalChar {
+                    prop: ChannelProp::MemberKey,
+                    ch,
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn set_parses_typed_values() {
+        let mut props = ChannelProps::new();
+        props.set(ChannelProp::Lag, "2").unwrap();
+        assert_eq!(props.lag, Some(MAX_LAG));
+        assert_eq!(
+            props.set(ChannelProp::Lag, "3"),
+            Err(PropError::InvalidValue {
+                prop: ChannelProp::Lag,
+                value: "3".into(),
+            })
+        );
+        assert_eq!(props.lag, Some(2));
+
+        props.set(ChannelProp::Language, "3").unwrap();
+        assert_eq!(props.get(ChannelProp::Language).as_deref(), Some("3"));
+        assert_eq!(
+            props.set(ChannelProp::Language, "99"),
+            Err(PropError::InvalidValue {
+                prop: ChannelProp::Language,
+                value: "99".into(),
+            })
+        );
+        props.set(ChannelProp::Creation, "1016911234").unwrap();
+        assert_eq!(props.creation, Some(1016911234));
+
+        props.set(ChannelProp::Topic, "Welcome").unwrap();
+        props.set(ChannelProp::Topic, "").unwrap();
+        props.set(ChannelProp::Lag, "").unwrap();
+        assert_eq!(props.topic, None);
+        assert_eq!(props.lag, None);
+        assert_eq!(
+            props.iter().map(|(p, _)| p).collect::<Vec<_>>(),
+            [ChannelProp::Language, ChannelProp::Creation]
+        );
+    }
+
+    #[test]
+    fn commands() {
+        assert_eq!(
+            ChannelProps::set_command("%#Lobby", ChannelProp::Creation, "1"),
+            Err(PropError::ReadOnly(ChannelProp::Creation))
+        );
+        assert_eq!(
+            ChannelProps::set_command("%#Lobby", ChannelProp::OnJoin, "Hi all").unwrap(),
+            prop("%#Lobby", "ONJOIN", Some("Hi all"))
+        );
+        assert_eq!(
+            ChannelProps::set_command("%#Lobby", ChannelProp::Topic, "").unwrap(),
+            prop("%#Lobby", "TOPIC", Some(""))
+        );
+        assert_eq!(
+            ChannelProps::query("%#Lobby", None),
+            prop("%#Lobby", "*", None)
+        );
+
+        let props = ChannelProps {
+            topic: Some("Chat".into()),
+            creation: Some(1),
+            ..ChannelProps::default()
+        };
+        assert_eq!(
+            props.to_commands("%#Lobby").unwrap(),
+            [prop("%#Lobby", "TOPIC", Some("Chat"))]
+        );
+    }
+
+    #[test]
+    fn handle_prop_and_818() {
+        let mut props = ChannelProps::new();
+        assert!(props.handle("%#Lobby", &prop("%#lobby", "topic", Some("Hello"))));
+        assert_eq!(props.topic.as_deref(), Some("Hello"));
+        // The same value again is not a change.
+        assert!(!props.handle("%#Lobby", &prop("%#Lobby", "TOPIC", Some("Hello"))));
+
+        assert!(props.handle("%#Lobby", &prop_list(&["%#Lobby", "LAG", "1"])));
+        assert_eq!(props.lag, Some(1));
+        assert!(props.handle("%#Lobby", &prop_list(&["%#Lobby", "TOPIC", ""])));
+        assert_eq!(props.topic, None);
+
+        let before = props.clone();
+        for ignored in [
+            prop("%#Other", "TOPIC", Some("Elsewhere")),
+            prop("%#Lobby", "TOPIC", None),
+            prop("%#Lobby", "NAME", Some("x")),
+            prop("%#Lobby", "LAG", Some("9")),
+            prop_list(&["%#Other", "TOPIC", "Elsewhere"]),
+            prop_list(&["%#Lobby", "TOPIC"]),
+            prop_list(&["%#Lobby"]),
+        ] {
+            assert!(!props.handle("%#Lobby", &ignored), "{ignored:?}");
+        }
+        assert_eq!(props, before);
+
+        let event = SessionEvent::Message {
+            source: None,
+            command: prop("%#Lobby", "ONJOIN", Some("Welcome")),
+        };
+        assert!(props.handle_event("%#Lobby", &event));
+        assert_eq!(props.on_join.as_deref(), Some("Welcome"));
+    }
+}
@@ -0,0 +1,391 @@
+use std::fmt;
+
+use super::{
+    ChannelProps, Command, Numeric, PropError, ServerError, SessionConfig, SessionError,
+    SessionEvent, escape_str,
+};
+use crate::types::{Category, ChannelLanguage, ChannelModes, CreationModes, ModeError};
+
+/// Longest room name the server accepts, before escaping.
+pub const MAX_ROOM_NAME_LEN: usize = 63;
+
+const CHANNEL_PREFIXES: [char; 3] = ['%', '#', '&'];
+
+/// Whether `name` is a channel rather than a nick or `$`.
+pub(crate) fn is_channel(name: &str) -> bool {
+    name.starts_with(CHANNEL_PREFIXES)
+}
+
+/// The IRC channel for a room name: `%#` plus the escaped name, unless already prefixed.
+pub fn room_channel(room: &str) -> String {
+    if is_channel(room) {
+        room.to_owned()
+    } else {
+        format!("%#{}", escape_str(room))
+    }
+}
+
+/// Why a room could not be created.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CreateError {
+    InvalidName(String),
+    InvalidCategory(String),
+    Mode(ModeError),
+    Prop(PropError),
+    /// A room with this name already exists.
+    NameInUse(String),
+    /// The server's filter rejected the name or topic.
+    Profanity(String),
+    /// Any other refusal.
+    Server(ServerError),
+}
+
+impl CreateError {
+    /// Classifies the server's reply to a `CREATE`.
+    pub fn from_server(err: ServerError) -> Self {
+        let subject = err.subject().unwrap_or_default().to_owned();
+        match err.numeric {
+            Numeric::ChannelExist => CreateError::NameInUse(subject),
+            Numeric::BadChanName => CreateError::InvalidName(subject),
+            Numeric::BadCategory => CreateError::InvalidCategory(subject),
+            Numeric::Profanity => CreateError::Profanity(subject),
+            _ => CreateError::Server(err),
+        }
+    }
+}
+
+impl fmt::Display for CreateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CreateError::InvalidName(name) => write!(f, "invalid room name `{name}`"),
+            CreateError::InvalidCategory(code) => write!(f, "invalid room category `{code}`"),
+            CreateError::Mode(err) => err.fmt(f),
+            CreateError::Prop(err) => err.fmt(f),
+            CreateError::NameInUse(name) => write!(f, "room {name} already exists"),
+            CreateError::Profanity(subject) => {
+                write!(f, "{subject} was rejected by the profanity filter")
+            }
+            CreateError::Server(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for CreateError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CreateError::Mode(err) => Some(err),
+            CreateError::Prop(err) => Some(err),
+            CreateError::Server(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<ModeError> for CreateError {
+    fn from(err: ModeError) -> Self {
+        CreateError::Mode(err)
+    }
+}
+
+impl From<PropError> for CreateError {
+    fn from(err: PropError) -> Self {
+        CreateError::Prop(err)
+    }
+}
+
+/// What the control's "create a room" page asks for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RoomSpec {
+    /// Display name, e.g. `Rust Lounge`; see [`room_channel`].
+    pub name: String,
+    pub category: Category,
+    pub topic: Option<String>,
+    pub language: Option<ChannelLanguage>,
+    pub modes: ChannelModes,
+    /// Joining with this key makes the creator an owner again later.
+    pub owner_key: Option<String>,
+}
+
+impl RoomSpec {
+    pub fn new(name: impl Into<String>, category: Category) -> Self {
+        Self {
+            name: name.into(),
+            category,
+            topic: None,
+            language: None,
+            modes: ChannelModes::default(),
+            owner_key: None,
+        }
+    }
+
+    pub fn with_topic(mut self, topic: impl Into<String>) -> Self {
+        self.topic = Some(topic.into());
+        self
+    }
+
+    pub fn with_language(mut self, language: ChannelLanguage) -> Self {
+        self.language = Some(language);
+        self
+    }
+
+    pub fn with_modes(mut self, modes: ChannelModes) -> Self {
+        self.modes = modes;
+        self
+    }
+
+    pub fn with_owner_key(mut self, key: impl Into<String>) -> Self {
+        self.owner_key = Some(key.into());
+        self
+    }
+
+    pub fn channel(&self) -> String {
+        room_channel(&self.name)
+    }
+
+    /// The frame's `CreationModes` for this room.
+    pub fn creation_modes(&self) -> CreationModes {
+        CreationModes {
+            modes: self.modes.clone(),
+            language: self.language,
+        }
+    }
+
+    /// Properties set right after `CREATE`: the topic, the category as
+    /// `SUBJECT`, and the owner key.
+    pub fn props(&self) -> ChannelProps {
+        ChannelProps {
+            topic: self.topic.clone(),
+            subject: Some(self.category.code().to_owned()),
+            owner_key: self.owner_key.clone(),
+            ..ChannelProps::default()
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), CreateError> {
+        let name = self.name.trim_start_matches(CHANNEL_PREFIXES);
+        if name.is_empty()
+            || name.chars().count() > MAX_ROOM_NAME_LEN
+            || name.contains(|c: char| c.is_control())
+        {
+            return Err(CreateError::InvalidName(self.name.clone()));
+        }
+        if !self.category.is_known() {
+            return Err(CreateError::InvalidCategory(
+                self.category.code().to_owned(),
+            ));
+        }
+        self.creation_modes().validate()?;
+        self.props().to_commands(&self.channel())?;
+        Ok(())
+    }
+
+    /// A session that reopens the room, as owner when there is an owner key.
+    pub fn session_config(
+        &self,
+        server: impl Into<String>,
+        nick_name: impl Into<String>,
+    ) -> SessionConfig {
+        let config = SessionConfig::new(server, nick_name).with_room(self.channel());
+        match &self.owner_key {
+            Some(key) => config.with_room_key(key),
+            None => config,
+        }
+    }
+}
+
+/// A validated room creation: the commands to send and how to read the reply.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RoomCreation {
+    pub spec: RoomSpec,
+    pub channel: String,
+    /// `CREATE <channel> <modes> [args...] [language]`.
+    pub create: Command,
+    /// The `PROP`s from [`RoomSpec::props`]; send them once
+    /// [`handle_event`](Self::handle_event) reports success, as only an owner may.
+    pub setup: Vec<Command>,
+}
+
+impl RoomCreation {
+    /// `None` until `event` settles the outcome: our own join of the new
+    /// channel on success, or a server error about it.
+    pub fn handle_event(&self, event: &SessionEvent) -> Option<Result<(), CreateError>> {
+        match event {
+            SessionEvent::Joined { channel } if channel.eq_ignore_ascii_case(&self.channel) => {
+                Some(Ok(()))
+            }
+            SessionEvent::Error(SessionError::Server(err)) if self.concerns(err) => {
+                Some(Err(CreateError::from_server(err.clone())))
+            }
+            _ => None,
+        }
+    }
+
+    /// An error naming another channel is not ours; only category and
+    /// profanity errors may come without a subject.
+    fn concerns(&self, err: &ServerError) -> bool {
+        match err.subject() {
+            Some(subject) => subject.eq_ignore_ascii_case(&self.channel),
+            None => matches!(err.numeric, Numeric::BadCategory | Numeric::Profanity),
+        }
+    }
+}
+
+/// Validates `spec` and builds the commands that create the room.
+pub fn create_room(spec: RoomSpec) -> Result<RoomCreation, CreateError> {
+    spec.validate()?;
+    let channel = spec.channel();
+    Ok(RoomCreation {
+        create: Command::Create {
+            channel: channel.clone(),
+            args: spec.creation_modes().create_params(),
+        },
+        setup: spec.props().to_commands(&channel)?,
+        spec,
+        channel,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn server_error(numeric: Numeric, params: &[&str]) -> ServerError {
+        ServerError {
+            numeric,
+            params: params.iter().map(|p| p.to_string()).collect(),
+            text: "refused".to_owned(),
+        }
+    }
+
+    fn error_event(err: ServerError) -> SessionEvent {
+        SessionEvent::Error(SessionError::Server(err))
+    }
+
+    fn creation() -> RoomCreation {
+        create_room(RoomSpec::new("Rust Lounge", Category::Computing)).unwrap()
+    }
+
+    fn creation_line(spec: RoomSpec) -> String {
+        create_room(spec).unwrap().create.to_line().unwrap()
+    }
+
+    #[test]
+    fn create_argument_order() {
+        let (modes, _) = ChannelModes::parse_with_args("+mnlk", ["50", "secret"]).unwrap();
+        let spec = RoomSpec::new("Rust Lounge", Category::Computing)
+            .with_modes(modes)
+            .with_language(ChannelLanguage::German)
+            .with_topic("All things Rust")
+            .with_owner_key("ownerkey");
+        let creation = create_room(spec).unwrap();
+        assert_eq!(creation.channel, "%#Rust\\bLounge");
+        assert_eq!(
+            creation.create.to_line().unwrap(),
+            "CREATE %#Rust\\bLounge +mnlk 50 secret 3\r\n"
+        );
+        let setup: Vec<_> = creation
+            .setup
+            .iter()
+            .map(|c| c.to_line().unwrap())
+            .collect();
+        assert_eq!(
+            setup,
+            [
+                "PROP %#Rust\\bLounge TOPIC :All things Rust\r\n",
+                "PROP %#Rust\\bLounge SUBJECT :CP\r\n",
+                "PROP %#Rust\\bLounge OWNERKEY :ownerkey\r\n",
+            ]
+        );
+
+        let plain = creation_line(RoomSpec::new("Plain", Category::General));
+        assert_eq!(plain, "CREATE %#Plain +\r\n");
+    }
+
+    #[test]
+    fn rejects_invalid_specs() {
+        assert_eq!(
+            create_room(RoomSpec::new("%#", Category::General)),
+            Err(CreateError::InvalidName("%#".into()))
+        );
+        let long = "x".repeat(MAX_ROOM_NAME_LEN + 1);
+        assert_eq!(
+            create_room(RoomSpec::new(&long, Category::General)),
+            Err(CreateError::InvalidName(long))
+        );
+        assert_eq!(
+            create_room(RoomSpec::new("Room", Category::Unknown("ZZ".into()))),
+            Err(CreateError::InvalidCategory("ZZ".into()))
+        );
+    }
+
+    #[test]
+    fn from_server_mapping() {
+        let channel = "%#Rust\\bLounge";
+        let cases = [
+            (
+                Numeric::ChannelExist,
+                CreateError::NameInUse(channel.into()),
+            ),
+            (
+                Numeric::BadChanName,
+                CreateError::InvalidName(channel.into()),
+            ),
+            (
+                Numeric::BadCategory,
+                CreateError::InvalidCategory(channel.into()),
+            ),
+            (Numeric::Profanity, CreateError::Profanity(channel.into())),
+        ];
+        for (numeric, expected) in cases {
+            assert_eq!(
+                CreateError::from_server(server_error(numeric, &[channel])),
+                expected
+            );
+        }
+        let other = server_error(Numeric::BannedFromChan, &[channel]);
+        assert_eq!(
+            CreateError::from_server(other.clone()),
+            CreateError::Server(other)
+        );
+    }
+
+    #[test]
+    fn settles_on_own_join() {
+        let creation = creation();
+        let joined = |channel: &str| SessionEvent::Joined {
+            channel: channel.into(),
+        };
+        assert_eq!(creation.handle_event(&joined("%#Other")), None);
+        assert_eq!(
+            creation.handle_event(&joined("%#rust\\blounge")),
+            Some(Ok(()))
+        );
+    }
+
+    #[test]
+    fn settles_only_on_errors_about_this_room() {
+        let creation = creation();
+        let ours = server_error(Numeric::ChannelExist, &["%#Rust\\bLounge"]);
+        assert_eq!(
+            creation.handle_event(&error_event(ours)),
+            Some(Err(CreateError::NameInUse("%#Rust\\bLounge".into())))
+        );
+
+        for numeric in [
+            Numeric::BadCategory,
+            Numeric::Profanity,
+            Numeric::ChannelExist,
+        ] {
+            let elsewhere = server_error(numeric, &["%#Other"]);
+            assert_eq!(creation.handle_event(&error_event(elsewhere)), None);
+        }
+
+        let unnamed = server_error(Numeric::Profanity, &[]);
+        assert_eq!(
+            creation.handle_event(&error_event(unnamed)),
+            Some(Err(CreateError::Profanity(String::new())))
+        );
+        let unnamed = server_error(Numeric::BannedFromChan, &[]);
+        assert_eq!(creation.handle_event(&error_event(unnamed)), None);
+    }
+}
@@ -0,0 +1,754 @@
+//! Sans-IO client session: the connect → `IRCVERS` → `AUTH` → `NICK`/`USER` →
+//! `JOIN` sequence a configured [`ChatFrame`](crate::ChatFrame) performs, as a
+//! state machine fed with received bytes.
+//!
+//! Callers own the socket: write whatever [`Session::poll_transmit`] yields,
+//! pass everything read to [`Session::feed`], and drain [`Session::poll_event`].
+
+use std::{collections::VecDeque, fmt};
+
+use super::{
+    command::{Command, CommandError},
+    gatekeeper::{
+        AuthLine, GATEKEEPER_PASSPORT, Gatekeeper, GatekeeperError, GatekeeperId, GatekeeperStep,
+    },
+    ircvers::{ClientVersion, Negotiation},
+    message::{EncodeError, Message},
+    numeric::{Numeric, ServerError},
+    passport::PassportCredentials,
+    room::room_channel,
+};
+
+/// Received data without a line break beyond this is treated as a protocol error.
+///
+/// Well above `MAX_LINE_LEN`, since Passport `AUTH` lines are much longer.
+const MAX_BUFFERED: usize = 16 * 1024;
+
+/// The frame properties a session needs, named after their `ChatFrame` counterparts.
+#[derive(Debug, Clone)]
+pub struct SessionConfig {
+    /// `host:port`; also hashed into the GateKeeper response.
+    pub server: String,
+    pub nick_name: String,
+    pub room_name: Option<String>,
+    pub room_key: Option<String>,
+    pub passport: Option<PassportCredentials>,
+    pub gatekeeper_id: GatekeeperId,
+    pub client_version: ClientVersion,
+    /// `USER` ident; the server replaces it with the GateKeeper account.
+    pub user_name: String,
+}
+
+impl SessionConfig {
+    /// A GateKeeper (guest) configuration with a fresh identity.
+    pub fn new(server: impl Into<String>, nick_name: impl Into<String>) -> Self {
+        Self {
+            server: server.into(),
+            nick_name: nick_name.into(),
+            room_name: None,
+            room_key: None,
+            passport: None,
+            gatekeeper_id: GatekeeperId::generate(),
+            client_version: ClientVersion::default(),
+            user_name: "msnchat".to_owned(),
+        }
+    }
+
+    pub fn with_room(mut self, room_name: impl Into<String>) -> Self {
+        self.room_name = Some(room_name.into());
+        self
+    }
+
+    pub fn with_room_key(mut self, key: impl Into<String>) -> Self {
+        self.room_key = Some(key.into());
+        self
+    }
+
+    pub fn with_passport(mut self, credentials: PassportCredentials) -> Self {
+        self.passport = Some(credentials);
+        self
+    }
+
+    pub fn with_gatekeeper_id(mut self, id: GatekeeperId) -> Self {
+        self.gatekeeper_id = id;
+        self
+    }
+
+    pub fn with_client_version(mut self, version: ClientVersion) -> Self {
+        self.client_version = version;
+        self
+    }
+
+    /// The IRC channel for `room_name`: `%#` plus the escaped name, unless already prefixed.
+    pub fn channel(&self) -> Option<String> {
+        self.room_name.as_deref().map(room_channel)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionState {
+    Disconnected,
+    /// `IRCVERS` sent, waiting for `800`.
+    Negotiating,
+    Authenticating,
+    /// `NICK`/`USER` sent, waiting for `001`.
+    Registering,
+    /// Registered and not in a room.
+    Registered,
+    Joining,
+    Joined,
+    Closed,
+}
+
+/// Why a session failed or an operation was refused.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SessionError {
+    Server(ServerError),
+    Auth(GatekeeperError),
+    /// The server offered none of the packages we can use.
+    NoAuthPackage(Vec<String>),
+    /// A received line could not be understood; the session carries on.
+    Protocol(CommandError),
+    LineTooLong,
+    Encode(EncodeError),
+    /// The server sent `ERROR` or the session was already closed.
+    Closed(Option<String>),
+}
+
+impl fmt::Display for SessionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SessionError::Server(err) => write!(f, "server error: {err}"),
+            SessionError::Auth(err) => write!(f, "authentication failed: {err}"),
+            SessionError::NoAuthPackage(offered) => {
+                write!(
+                    f,
+                    "no usable auth package (server offers {})",
+                    offered.join(",")
+                )
+            }
+            SessionError::Protocol(err) => write!(f, "protocol error: {err}"),
+            SessionError::LineTooLong => write!(f, "received line exceeds {MAX_BUFFERED} bytes"),
+            SessionError::Encode(err) => write!(f, "cannot encode message: {err}"),
+            SessionError::Closed(Some(reason)) => write!(f, "connection closed: {reason}"),
+            SessionError::Closed(None) => write!(f, "connection closed"),
+        }
+    }
+}
+
+impl std::error::Error for SessionError {}
+
+/// Something that happened on the connection.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SessionEvent {
+    Negotiated(Negotiation),
+    Authenticated {
+        account: String,
+    },
+    Registered {
+        nick: String,
+    },
+    Joined {
+        channel: String,
+    },
+    Parted {
+        channel: String,
+    },
+    /// Any other message, including numerics.
+    Message {
+        source: Option<String>,
+        command: Command,
+    },
+    /// Fatal errors are followed by [`SessionEvent::Closed`].
+    Error(SessionError),
+    Closed {
+        reason: Option<String>,
+    },
+}
+
+/// A client connection driven without I/O.
+#[derive(Debug)]
+pub struct Session {
+    config: SessionConfig,
+    state: SessionState,
+    nick: String,
+    channel: Option<String>,
+    negotiation: Option<Negotiation>,
+    gatekeeper: Option<Gatekeeper>,
+    buffer: Vec<u8>,
+    transmit: VecDeque<Vec<u8>>,
+    events: VecDeque<SessionEvent>,
+}
+
+impl Session {
+    pub fn new(config: SessionConfig) -> Self {
+        Self {
+            nick: config.nick_name.clone(),
+            config,
+            state: SessionState::Disconnected,
+            channel: None,
+            negotiation: None,
+            gatekeeper: None,
+            buffer: Vec::new(),
+            transmit: VecDeque::new(),
+            events: VecDeque::new(),
+        }
+    }
+
+    pub fn config(&self) -> &SessionConfig {
+        &self.config
+    }
+
+    pub fn state(&self) -> SessionState {
+        self.state
+    }
+
+    /// Our current nickname, as confirmed by the server.
+    pub fn nick(&self) -> &str {
+        &self.nick
+    }
+
+    /// The room we are in, once joined.
+    pub fn channel(&self) -> Option<&str> {
+        self.channel.as_deref()
+    }
+
+    pub fn negotiation(&self) -> Option<&Negotiation> {
+        self.negotiation.as_ref()
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.state == SessionState::Closed
+    }
+
+    /// Starts the handshake; call once the transport is connected.
+    pub fn connect(&mut self) {
+        if self.state != SessionState::Disconnected {
+            return;
+        }
+        self.state = SessionState::Negotiating;
+        self.queue(&self.config.client_version.to_command());
+    }
+
+    /// Queues a command for sending.
+    pub fn send(&mut self, command: &Command) -> Result<(), SessionError> {
+        if self.is_closed() {
+            return Err(SessionError::Closed(None));
+        }
+        let line = command.to_line().map_err(SessionError::Encode)?;
+        self.transmit.push_back(line.into_bytes());
+        Ok(())
+    }
+
+    /// Queues `QUIT`; the session closes when the server drops the connection.
+    pub fn quit(&mut self, reason: Option<&str>) {
+        if !self.is_closed() {
+            self.queue(&Command::Quit(reason.map(str::to_owned)));
+        }
+    }
+
+    /// Marks the session closed after the transport went away.
+    pub fn disconnected(&mut self, reason: Option<String>) {
+        self.close(reason);
+    }
+
+    /// The next line to write to the socket, CRLF included.
+    pub fn poll_transmit(&mut self) -> Option<Vec<u8>> {
+        self.transmit.pop_front()
+    }
+
+    pub fn poll_event(&mut self) -> Option<SessionEvent> {
+        self.events.pop_front()
+    }
+
+    /// Feeds bytes read from the socket.
+    pub fn feed(&mut self, data: &[u8]) {
+        if self.is_closed() {
+            return;
+        }
+        self.buffer.extend_from_slice(data);
+
+        while let Some(end) = self.buffer.iter().position(|&b| b == b'\n') {
+            let mut line: Vec<u8> = self.buffer.drain(..=end).collect();
+            line.pop();
+            if line.last() == Some(&b'\r') {
+                line.pop();
+            }
+            if !line.is_empty() {
+                self.handle_line(&line);
+            }
+            if self.is_closed() {
+                return;
+            }
+        }
+
+        if self.buffer.len() > MAX_BUFFERED {
+            self.fail(SessionError::LineTooLong);
+        }
+    }
+
+    fn handle_line(&mut self, line: &[u8]) {
+        // AUTH blobs are binary, so they bypass the text parser.
+        if self.state == SessionState::Authenticating && AuthLine::parse(line).is_some() {
+            return self.handle_auth(line);
+        }
+
+        let text = String::from_utf8_lossy(line);
+        let msg = match Message::parse_lenient(&text) {
+            Ok(msg) => msg,
+            Err(err) => {
+                let err = SessionError::Protocol(CommandError::Parse(err));
+                return self.events.push_back(SessionEvent::Error(err));
+            }
+        };
+
+        if let Some(err) = ServerError::from_message(&msg) {
+            return self.handle_server_error(err);
+        }
+
+        let own = msg
+            .source()
+            .is_some_and(|p| p.nick.eq_ignore_ascii_case(&self.nick));
+        let command = msg.command.to_ascii_uppercase();
+        match (msg.numeric().map(Numeric::from_code), command.as_str()) {
+            (_, "PING") => {
+                let token = msg.last_arg().unwrap_or_default().to_owned();
+                self.queue(&Command::Pong(token));
+            }
+            (Some(Numeric::Ircx), _) if self.state == SessionState::Negotiating => {
+                self.handle_negotiation(&msg)
+            }
+            (Some(Numeric::Welcome), _) if self.state == SessionState::Registering => {
+                if let Some(nick) = msg.arg(0) {
+                    self.nick = nick.to_owned();
+                }
+                self.events.push_back(SessionEvent::Registered {
+                    nick: self.nick.clone(),
+                });
+                self.send_regcookie();
+                self.join_configured_room();
+            }
+            (_, "JOIN") if own => {
+                let channel = msg.last_arg().unwrap_or_default().to_owned();
+                self.state = SessionState::Joined;
+                self.channel = Some(channel.clone());
+                self.events.push_back(SessionEvent::Joined { channel });
+            }
+            (_, "PART") if own => {
+                let channel = msg.arg(0).unwrap_or_default().to_owned();
+                self.left(channel);
+            }
+            (_, "KICK")
+                if msg
+                    .arg(1)
+                    .is_some_and(|n| n.eq_ignore_ascii_case(&self.nick)) =>
+            {
+                self.left(msg.arg(0).unwrap_or_default().to_owned());
+                self.emit_message(&msg);
+            }
+            (_, "NICK") if own => {
+                self.nick = msg.last_arg().unwrap_or_default().to_owned();
+                self.emit_message(&msg);
+            }
+            (_, "ERROR") => self.close(msg.last_arg().map(str::to_owned)),
+            _ => self.emit_message(&msg),
+        }
+    }
+
+    fn handle_negotiation(&mut self, msg: &Message<'_>) {
+        let Some(negotiation) = Negotiation::from_reply(&self.config.client_version, msg) else {
+            return;
+        };
+        let package = negotiation.auth_package(self.config.passport.is_some());
+        let offered = negotiation.packages.clone();
+        self.negotiation = Some(negotiation.clone());
+        self.events.push_back(SessionEvent::Negotiated(negotiation));
+
+        let id = self.config.gatekeeper_id;
+        let server = self.config.server.clone();
+        let mut gatekeeper = match (package, &self.config.passport) {
+            (Some(GATEKEEPER_PASSPORT), Some(creds)) => {
+                Gatekeeper::passport(id, server, creds.clone())
+            }
+            (Some(_), _) => Gatekeeper::new(id, server),
+            (None, _) => return self.fail(SessionError::NoAuthPackage(offered)),
+        };
+        self.transmit.push_back(gatekeeper.start());
+        self.gatekeeper = Some(gatekeeper);
+        self.state = SessionState::Authenticating;
+    }
+
+    fn handle_auth(&mut self, line: &[u8]) {
+        let Some(gatekeeper) = self.gatekeeper.as_mut() else {
+            return;
+        };
+        match gatekeeper.handle_line(line) {
+            Ok(GatekeeperStep::Send(reply)) => self.transmit.push_back(reply),
+            Ok(GatekeeperStep::Authenticated { account }) => {
+                self.gatekeeper = None;
+                self.events
+                    .push_back(SessionEvent::Authenticated { account });
+                self.register();
+            }
+            Err(err) => self.fail(SessionError::Auth(err)),
+        }
+    }
+
+    fn handle_server_error(&mut self, err: ServerError) {
+        match self.state {
+            SessionState::Negotiating
+                if err.numeric == Numeric::UnknownCommand
+                    && err.subject() == Some("IRCVERS")
+                    && self.config.passport.is_none() =>
+            {
+                // Plain IRC server: skip IRCX negotiation and register as a guest.
+                self.register();
+            }
+            SessionState::Negotiating
+            | SessionState::Authenticating
+            | SessionState::Registering => self.fail(SessionError::Server(err)),
+            SessionState::Joining if self.refuses_pending_join(&err) => {
+                self.state = SessionState::Registered;
+                self.events
+                    .push_back(SessionEvent::Error(SessionError::Server(err)));
+            }
+            _ => self
+                .events
+                .push_back(SessionEvent::Error(SessionError::Server(err))),
+        }
+    }
+
+    /// True when `err` is about the room we are joining rather than some other command.
+    fn refuses_pending_join(&self, err: &ServerError) -> bool {
+        let pending = self.config.channel();
+        err.subject()
+            .zip(pending.as_deref())
+            .is_some_and(|(subject, channel)| subject.eq_ignore_ascii_case(channel))
+    }
+
+    fn register(&mut self) {
+        self.state = SessionState::Registering;
+        self.queue(&Command::Nick(self.config.nick_name.clone()));
+        self.queue(&Command::User {
+            username: self.config.user_name.clone(),
+            mode: "0".to_owned(),
+            unused: "*".to_owned(),
+            realname: self.config.nick_name.clone(),
+        });
+    }
+
+    /// Passport sessions hand the server their profile cookie before joining.
+    fn send_regcookie(&mut self) {
+        let used_passport = self
+            .negotiation
+            .as_ref()
+            .is_some_and(|n| n.auth_package(true) == Some(GATEKEEPER_PASSPORT));
+        let command = self
+            .config
+            .passport
+            .as_ref()
+            .filter(|_| used_passport)
+            .and_then(PassportCredentials::regcookie_command);
+        if let Some(command) = command {
+            self.queue(&command);
+        }
+    }
+
+    fn join_configured_room(&mut self) {
+        match self.config.channel() {
+            Some(channel) => {
+                self.state = SessionState::Joining;
+                self.queue(&Command::Join {
+                    channels: channel,
+                    keys: self.config.room_key.clone(),
+                });
+            }
+            None => self.state = SessionState::Registered,
+        }
+    }
+
+    fn left(&mut self, channel: String) {
+        if self.channel.as_deref() == Some(channel.as_str()) {
+            self.channel = None;
+            self.state = SessionState::Registered;
+        }
+        self.events.push_back(SessionEvent::Parted { channel });
+    }
+
+    fn emit_message(&mut self, msg: &Message<'_>) {
+        let event = match Command::from_message(msg) {
+            Ok(command) => SessionEvent::Message {
+                source: msg.prefix.map(str::to_owned),
+                command,
+            },
+            Err(err) => SessionEvent::Error(SessionError::Protocol(err)),
+        };
+        self.events.push_back(event);
+    }
+
+    fn queue(&mut self, command: &Command) {
+        // Commands we build ourselves only fail to encode on bad configuration.
+        if let Err(err) = self.send(command) {
+            self.fail(err);
+        }
+    }
+
+    fn fail(&mut self, err: SessionError) {
+        self.events.push_back(SessionEvent::Error(err));
+        self.close(None);
+    }
+
+    fn close(&mut self, reason: Option<String>) {
+        if self.is_closed() {
+            return;
+        }
+        self.state = SessionState::Closed;
+        self.gatekeeper = None;
+        self.buffer.clear();
+        self.events.push_back(SessionEvent::Closed { reason });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ircx::gatekeeper::auth_line;
+
+    const SERVER: &str = "207.68.167.253:6667";
+    const ID: &str = "000102030405060708090A0B0C0D0E0F";
+
+    fn config() -> SessionConfig {
+        SessionConfig::new(SERVER, "Alice")
+            .with_room("Lobby")
+            .with_gatekeeper_id(ID.parse().unwrap())
+    }
+
+    fn transmitted(session: &mut Session) -> Vec<String> {
+        std::iter::from_fn(|| session.poll_transmit())
+            .map(|line| String::from_utf8_lossy(&line).into_owned())
+            .collect()
+    }
+
+    fn events(session: &mut Session) -> Vec<SessionEvent> {
+        std::iter::from_fn(|| session.poll_event()).collect()
+    }
+
+    fn challenge(package: &str) -> Vec<u8> {
+        let mut line = format!(":server AUTH {package} S :").into_bytes();
+        line.extend_from_slice(b"GKSSP\\0\\0\\0\x03\\0\\0\\0\x02\\0\\0\\0abcdefgh\r\n");
+        line
+    }
+
+    /// Checks the session answers `challenge` exactly as a standalone exchange would.
+    fn answer_challenge(session: &mut Session, mut expected: Gatekeeper, package: &str) {
+        let challenge = challenge(package);
+        let GatekeeperStep::Send(response) = expected.handle_line(&challenge).unwrap() else {
+            panic!("no challenge response");
+        };
+        session.feed(&challenge);
+        assert_eq!(session.poll_transmit(), Some(response));
+        assert_eq!(session.poll_transmit(), None);
+    }
+
+    /// Feeds `001` and our `JOIN`, checking what is sent and reported.
+    fn register_and_join(session: &mut Session, before_join: &[&str]) {
+        session.feed(b":server 001 Alice :Welcome to MSN Chat\r\n");
+        let mut expected: Vec<String> = before_join.iter().map(|l| format!("{l}\r\n")).collect();
+        expected.push("JOIN %#Lobby\r\n".to_owned());
+        assert_eq!(transmitted(session), expected);
+        assert_eq!(session.state(), SessionState::Joining);
+
+        session.feed(b":Alice!~a@host JOIN :%#Lobby\r\n");
+        assert_eq!(
+            events(session),
+            [
+                SessionEvent::Registered {
+                    nick: "Alice".into()
+                },
+                SessionEvent::Joined {
+                    channel: "%#Lobby".into()
+                },
+            ]
+        );
+        assert_eq!(session.state(), SessionState::Joined);
+        assert_eq!(session.channel(), Some("%#Lobby"));
+    }
+
+    #[test]
+    fn gatekeeper_handshake() {
+        let mut session = Session::new(config());
+        session.connect();
+        assert_eq!(
+            transmitted(&mut session),
+            ["IRCVERS IRC8 MSN-OCX!9.02.0310.2401\r\n"]
+        );
+
+        session.feed(b":server 800 * 1 0 GateKeeper,GateKeeperPassport 512 *\r\n");
+        let mut expected = Gatekeeper::new(ID.parse().unwrap(), SERVER);
+        assert_eq!(session.poll_transmit(), Some(expected.start()));
+        assert_eq!(session.state(), SessionState::Authenticating);
+        assert!(matches!(
+            events(&mut session)[..],
+            [SessionEvent::Negotiated(_)]
+        ));
+        answer_challenge(&mut session, expected, "GateKeeper");
+
+        session.feed(format!(":server AUTH GateKeeper * {ID}@GateKeeper 0\r\n").as_bytes());
+        assert_eq!(
+            events(&mut session),
+            [SessionEvent::Authenticated {
+                account: format!("{ID}@GateKeeper")
+            }]
+        );
+        assert_eq!(
+            transmitted(&mut session),
+            ["NICK Alice\r\n", "USER msnchat 0 * :Alice\r\n"]
+        );
+        assert_eq!(session.state(), SessionState::Registering);
+
+        register_and_join(&mut session, &[]);
+    }
+
+    #[test]
+    fn plain_irc_fallback() {
+        let mut session = Session::new(config());
+        session.connect();
+        transmitted(&mut session);
+
+        session.feed(b":server 421 Alice IRCVERS :Unknown command\r\n");
+        assert_eq!(
+            transmitted(&mut session),
+            ["NICK Alice\r\n", "USER msnchat 0 * :Alice\r\n"]
+        );
+        assert_eq!(session.state(), SessionState::Registering);
+        assert!(session.negotiation().is_none());
+        register_and_join(&mut session, &[]);
+    }
+
+    #[test]
+    fn passport_handshake_sends_regcookie() {
+        let creds = PassportCredentials::new("ticket", "profile")
+            .unwrap()
+            .with_regcookie("cookie")
+            .unwrap();
+        let mut session = Session::new(config().with_passport(creds.clone()));
+        session.connect();
+        transmitted(&mut session);
+
+        session.feed(b":server 800 * 1 0 GateKeeper,GateKeeperPassport 512 *\r\n");
+        let mut expected = Gatekeeper::passport(ID.parse().unwrap(), SERVER, creds.clone());
+        assert_eq!(session.poll_transmit(), Some(expected.start()));
+        answer_challenge(&mut session, expected, "GateKeeperPassport");
+
+        session.feed(b":server AUTH GateKeeperPassport S :OK\r\n");
+        assert_eq!(
+            session.poll_transmit(),
+            Some(auth_line(
+                GATEKEEPER_PASSPORT,
+                b'S',
+                &creds.encode_payload()
+            ))
+        );
+
+        session.feed(format!(":server AUTH GateKeeperPassport * {ID}@passport 0\r\n").as_bytes());
+        assert_eq!(
+            events(&mut session)[1..],
+            [SessionEvent::Authenticated {
+                account: format!("{ID}@passport")
+            }]
+        );
+        assert_eq!(transmitted(&mut session).len(), 2);
+        register_and_join(&mut session, &["PROP $ MSNREGCOOKIE :cookie"]);
+    }
+
+    #[test]
+    fn passport_not_offered_skips_regcookie() {
+        let creds = PassportCredentials::new("ticket", "profile")
+            .unwrap()
+            .with_regcookie("cookie")
+            .unwrap();
+        let mut session = Session::new(config().with_passport(creds));
+        session.connect();
+        transmitted(&mut session);
+        session.feed(b":server 800 * 1 0 GateKeeper 512 *\r\n");
+        let mut expected = Gatekeeper::new(ID.parse().unwrap(), SERVER);
+        assert_eq!(session.poll_transmit(), Some(expected.start()));
+        answer_challenge(&mut session, expected, "GateKeeper");
+        session.feed(format!(":server AUTH GateKeeper * {ID}@GateKeeper 0\r\n").as_bytes());
+        transmitted(&mut session);
+        events(&mut session);
+        register_and_join(&mut session, &[]);
+    }
+
+    #[test]
+    fn no_usable_package_closes() {
+        let mut session = Session::new(config());
+        session.connect();
+        session.feed(b":server 800 * 1 0 NTLM 512 *\r\n");
+        let events = events(&mut session);
+        assert_eq!(
+            events[1..],
+            [
+                SessionEvent::Error(SessionError::NoAuthPackage(vec!["NTLM".into()])),
+                SessionEvent::Closed { reason: None },
+            ]
+        );
+        assert!(session.is_closed());
+    }
+
+    /// A plain IRC session that has sent `JOIN %#Lobby`.
+    fn joining() -> Session {
+        let mut session = Session::new(config());
+        session.connect();
+        session.feed(b":server 421 Alice IRCVERS :Unknown command\r\n");
+        session.feed(b":server 001 Alice :Welcome\r\n");
+        transmitted(&mut session);
+        events(&mut session);
+        assert_eq!(session.state(), SessionState::Joining);
+        session
+    }
+
+    #[test]
+    fn commands_match_case_insensitively() {
+        let mut session = joining();
+        session.feed(b"ping :token\r\n");
+        assert_eq!(transmitted(&mut session), ["PONG token\r\n"]);
+
+        session.feed(b":alice!~a@host join :%#Lobby\r\n");
+        assert_eq!(session.state(), SessionState::Joined);
+        session.feed(b":Alice!~a@host Nick :Alice2\r\n");
+        session.feed(b":Alice2!~a@host part %#Lobby\r\n");
+        assert_eq!(session.state(), SessionState::Registered);
+        assert_eq!(session.channel(), None);
+        assert!(matches!(
+            events(&mut session)[..],
+            [
+                SessionEvent::Joined { .. },
+                SessionEvent::Message { .. },
+                SessionEvent::Parted { .. },
+            ]
+        ));
+
+        session.feed(b"error :Closing link\r\n");
+        assert!(session.is_closed());
+    }
+
+    #[test]
+    fn join_errors_about_other_targets_keep_joining() {
+        let mut session = joining();
+        session.feed(b":server 401 Alice Bob :No such nick\r\n");
+        assert_eq!(session.state(), SessionState::Joining);
+        assert!(matches!(
+            events(&mut session)[..],
+            [SessionEvent::Error(SessionError::Server(_))]
+        ));
+
+        session.feed(b":server 473 Alice %#lobby :Cannot join channel (+i)\r\n");
+        assert_eq!(session.state(), SessionState::Registered);
+        let events = events(&mut session);
+        let [SessionEvent::Error(SessionError::Server(err))] = &events[..] else {
+            panic!("unexpected events {events:?}");
+        };
+        assert_eq!(err.numeric, Numeric::InviteOnlyChan);
+    }
+}
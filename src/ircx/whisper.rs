@@ -0,0 +1,377 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fmt,
+    time::SystemTime,
+};
+
+use super::{ChatMessage, Command, Prefix, SessionEvent};
+use crate::types::{ChannelMode, ChannelModes, UserRole};
+
+/// Entries kept per conversation unless [`Whispers::with_capacity`] says otherwise.
+pub const DEFAULT_HISTORY: usize = 100;
+
+/// Why a whisper may not be sent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WhisperError {
+    /// The channel has `+w` set.
+    Disabled,
+    /// The sender's role does not carry the whisper privilege.
+    NotPermitted(UserRole),
+    NoTargets,
+}
+
+impl fmt::Display for WhisperError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WhisperError::Disabled => f.write_str("whispers are disabled in this channel"),
+            WhisperError::NotPermitted(role) => write!(f, "a {role} may not whisper"),
+            WhisperError::NoTargets => f.write_str("whisper has no recipients"),
+        }
+    }
+}
+
+impl std::error::Error for WhisperError {}
+
+/// Checks whether a member with `role` may whisper in a channel with `modes`.
+///
+/// Hosts and above are exempt from `+w`, as on MSN servers.
+pub fn check_whisper(modes: &ChannelModes, role: UserRole) -> Result<(), WhisperError> {
+    if !role.may_whisper() {
+        return Err(WhisperError::NotPermitted(role));
+    }
+    if modes.contains(ChannelMode::NoWhisper) && role < UserRole::Host {
+        return Err(WhisperError::Disabled);
+    }
+    Ok(())
+}
+
+/// A private message to some members of a channel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Whisper {
+    pub channel: String,
+    /// Sender's nick; `None` for whispers we send.
+    pub from: Option<String>,
+    pub to: Vec<String>,
+    pub message: ChatMessage,
+}
+
+impl Whisper {
+    pub fn new<T: Into<String>>(
+        channel: impl Into<String>,
+        to: impl IntoIterator<Item = T>,
+        message: impl Into<ChatMessage>,
+    ) -> Self {
+        Self {
+            channel: channel.into(),
+            from: None,
+            to: to.into_iter().map(Into::into).collect(),
+            message: message.into(),
+        }
+    }
+
+    /// Reads a received `WHISPER`; `source` is the message prefix.
+    pub fn from_command(source: Option<&str>, command: &Command) -> Option<Self> {
+        let Command::Whisper {
+            channel,
+            targets,
+            text,
+        } = command
+        else {
+            return None;
+        };
+        Some(Self {
+            channel: channel.clone(),
+            from: source.map(|s| Prefix::parse(s).nick.to_owned()),
+            to: targets.split(',').map(str::to_owned).collect(),
+            message: ChatMessage::parse(text),
+        })
+    }
+
+    pub fn from_event(event: &SessionEvent) -> Option<Self> {
+        match event {
+            SessionEvent::Message { source, command } => {
+                Self::from_command(source.as_deref(), command)
+            }
+            _ => None,
+        }
+    }
+
+    /// Builds the `WHISPER` line after checking the sender may whisper.
+    pub fn to_command(
+        &self,
+        modes: &ChannelModes,
+        role: UserRole,
+    ) -> Result<Command, WhisperError> {
+        if self.to.is_empty() {
+            return Err(WhisperError::NoTargets);
+        }
+        check_whisper(modes, role)?;
+        Ok(self
+            .message
+            .to_whisper(self.channel.clone(), self.to.join(",")))
+    }
+}
+
+/// One line of a [`Conversation`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WhisperEntry {
+    /// Nick of whoever wrote it.
+    pub from: String,
+    pub outgoing: bool,
+    pub message: ChatMessage,
+    pub at: SystemTime,
+}
+
+/// Whispers exchanged with one peer in one channel, oldest first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conversation {
+    pub channel: String,
+    pub peer: String,
+    entries: VecDeque<WhisperEntry>,
+}
+
+impl Conversation {
+    fn new(channel: &str, peer: &str) -> Self {
+        Self {
+            channel: channel.to_owned(),
+            peer: peer.to_owned(),
+            entries: VecDeque::new(),
+        }
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &WhisperEntry> {
+        self.entries.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn trim(&mut self, capacity: usize) {
+        while self.entries.len() > capacity {
+            self.entries.pop_front();
+        }
+    }
+
+    /// Text for a frame's `WhisperContent`: one `<nick> text` line per entry.
+    pub fn whisper_content(&self) -> String {
+        self.entries
+            .iter()
+            .map(|e| match &e.message {
+                ChatMessage::Action(Some(text)) => format!("* {} {text}", e.from),
+                ChatMessage::Action(None) => format!("* {}", e.from),
+                message => format!("<{}> {}", e.from, message.plain()),
+            })
+            .collect::<Vec<_>>()
+            .join("\r\n")
+    }
+}
+
+/// Per-peer whisper history for one connection.
+#[derive(Debug, Clone)]
+pub struct Whispers {
+    nick: String,
+    capacity: usize,
+    conversations: BTreeMap<(String, String), Conversation>,
+}
+
+impl Whispers {
+    /// `nick` is our own nick, recorded as the author of outgoing whispers.
+    pub fn new(nick: impl Into<String>) -> Self {
+        Self::with_capacity(nick, DEFAULT_HISTORY)
+    }
+
+    pub fn with_capacity(nick: impl Into<String>, capacity: usize) -> Self {
+        Self {
+            nick: nick.into(),
+            capacity,
+            conversations: BTreeMap::new(),
+        }
+    }
+
+    /// Records a whisper we sent to every recipient's conversation.
+    pub fn sent(&mut self, whisper: &Whisper) {
+        let at = SystemTime::now();
+        for peer in &whisper.to {
+            let entry = WhisperEntry {
+                from: self.nick.clone(),
+                outgoing: true,
+                message: whisper.message.clone(),
+                at,
+            };
+            self.push(&whisper.channel, peer, entry);
+        }
+    }
+
+    /// Records a received whisper; whispers without a sender are ignored.
+    pub fn received(&mut self, whisper: &Whisper) {
+        let Some(from) = &whisper.from else { return };
+        let entry = WhisperEntry {
+            from: from.clone(),
+            outgoing: false,
+            message: whisper.message.clone(),
+            at: SystemTime::now(),
+        };
+        self.push(&whisper.channel, from, entry);
+    }
+
+    /// Records the whisper in `event`, if any, and returns it.
+    pub fn handle_event(&mut self, event: &SessionEvent) -> Option<Whisper> {
+        let whisper = Whisper::from_event(event)?;
+        self.received(&whisper);
+        Some(whisper)
+    }
+
+    pub fn conversation(&self, channel: &str, peer: &str) -> Option<&Conversation> {
+        self.conversations.get(&key(channel, peer))
+    }
+
+    pub fn conversations(&self) -> impl Iterator<Item = &Conversation> {
+        self.conversations.values()
+    }
+
+    /// Follows a `NICK` change, ours or a peer's.
+    ///
+    /// A conversation already held under `new` is merged in, oldest entries first.
+    pub fn rename(&mut self, old: &str, new: &str) {
+        if self.nick.eq_ignore_ascii_case(old) {
+            self.nick = new.to_owned();
+        }
+        let renamed: Vec<_> = self
+            .conversations
+            .keys()
+            .filter(|(_, peer)| peer.eq_ignore_ascii_case(old))
+            .cloned()
+            .collect();
+        for old_key in renamed {
+            let mut conversation = self
+                .conversations
+                .remove(&old_key)
+                .expect("key just listed");
+            conversation.peer = new.to_owned();
+            let new_key = key(&conversation.channel, new);
+            if let Some(existing) = self.conversations.remove(&new_key) {
+                conversation.entries.extend(existing.entries);
+                conversation.entries.make_contiguous().sort_by_key(|e| e.at);
+                conversation.trim(self.capacity);
+            }
+            self.conversations.insert(new_key, conversation);
+        }
+    }
+
+    /// Drops every conversation in `channel`, e.g. after leaving it.
+    pub fn clear_channel(&mut self, channel: &str) {
+        let channel = channel.to_ascii_lowercase();
+        self.conversations.retain(|(c, _), _| *c != channel);
+    }
+
+    fn push(&mut self, channel: &str, peer: &str, entry: WhisperEntry) {
+        let conversation = self
+            .conversations
+            .entry(key(channel, peer))
+            .or_insert_with(|| Conversation::new(channel, peer));
+        conversation.entries.push_back(entry);
+        conversation.trim(self.capacity);
+    }
+}
+
+/// Nicks and channel names compare case-insensitively.
+fn key(channel: &str, peer: &str) -> (String, String) {
+    (channel.to_ascii_lowercase(), peer.to_ascii_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn from(nick: &str, text: &str) -> Whisper {
+        Whisper {
+            from: Some(nick.to_owned()),
+            ..Whisper::new("%#Lobby", ["Alice"], ChatMessage::text(text))
+        }
+    }
+
+    fn texts(whispers: &Whispers, peer: &str) -> Vec<String> {
+        whispers
+            .conversation("%#lobby", peer)
+            .unwrap()
+            .entries()
+            .map(|e| e.message.plain())
+            .collect()
+    }
+
+    #[test]
+    fn trims_to_capacity() {
+        let mut whispers = Whispers::with_capacity("Alice", 3);
+        for i in 0..5 {
+            whispers.received(&from("Bob", &i.to_string()));
+        }
+        whispers.sent(&Whisper::new("%#Lobby", ["Bob"], ChatMessage::text("5")));
+        assert_eq!(texts(&whispers, "bob"), ["3", "4", "5"]);
+        let last = whispers
+            .conversation("%#LOBBY", "BOB")
+            .unwrap()
+            .entries()
+            .last()
+            .unwrap();
+        assert!(last.outgoing);
+        assert_eq!(last.from, "Alice");
+    }
+
+    #[test]
+    fn rename_moves_conversation() {
+        let mut whispers = Whispers::new("Alice");
+        whispers.received(&from("Bob", "hi"));
+        whispers.rename("BOB", "Robert");
+        assert!(whispers.conversation("%#Lobby", "Bob").is_none());
+        assert_eq!(
+            whispers.conversation("%#Lobby", "robert").unwrap().peer,
+            "Robert"
+        );
+        assert_eq!(texts(&whispers, "Robert"), ["hi"]);
+
+        whispers.rename("Alice", "Alicia");
+        whispers.sent(&Whisper::new(
+            "%#Lobby",
+            ["Robert"],
+            ChatMessage::text("yo"),
+        ));
+        let last = whispers
+            .conversation("%#Lobby", "Robert")
+            .unwrap()
+            .entries()
+            .last()
+            .unwrap();
+        assert_eq!(last.from, "Alicia");
+    }
+
+    #[test]
+    fn rename_merges_into_existing_conversation() {
+        let mut whispers = Whispers::with_capacity("Alice", 3);
+        whispers.received(&from("Bob", "1"));
+        whispers.received(&from("Bob", "2"));
+        whispers.received(&from("Robert", "3"));
+        whispers.received(&from("Robert", "4"));
+        whispers.rename("Bob", "Robert");
+
+        assert_eq!(whispers.conversations().count(), 1);
+        assert_eq!(texts(&whispers, "Robert"), ["2", "3", "4"]);
+    }
+
+    #[test]
+    fn rename_keeps_other_channels_apart() {
+        let mut whispers = Whispers::new("Alice");
+        whispers.received(&from("Bob", "lobby"));
+        whispers.received(&Whisper {
+            channel: "%#Other".into(),
+            ..from("Robert", "other")
+        });
+        whispers.rename("Bob", "Robert");
+        assert_eq!(texts(&whispers, "Robert"), ["lobby"]);
+        assert_eq!(whispers.conversations().count(), 2);
+    }
+}
@@ -1,7 +1,20 @@
+#[cfg(windows)]
 #[macro_use]
 pub mod com_macros;
 
+pub mod audit;
+#[cfg(windows)]
 pub mod bindings;
+pub mod client;
+pub mod error;
+pub mod ircx;
+#[cfg(feature = "mock")]
+pub mod mock;
+pub mod resdll;
+pub mod types;
+#[cfg(windows)]
 pub mod wrappers;
 
+pub use error::Error;
+#[cfg(windows)]
 pub use wrappers::{ChatFrame, ChatSettings};
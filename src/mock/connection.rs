@@ -0,0 +1,273 @@
+use std::{
+    io::{BufRead, BufReader},
+    net::TcpStream,
+    sync::Arc,
+    thread,
+};
+
+use super::{
+    Shared,
+    scenario::Action,
+    state::{ClientId, Flow, State},
+};
+use crate::ircx::{
+    Command, Numeric,
+    escape::unescape,
+    gatekeeper::{
+        AuthLine, GATEKEEPER, GATEKEEPER_PASSPORT, GatekeeperId, auth_line, challenge_response,
+        decode_header, encode_header,
+    },
+    passport::PassportCredentials,
+};
+
+/// Server side of the GKSSP exchange for one connection.
+enum Auth {
+    Idle,
+    Challenged {
+        package: String,
+        challenge: [u8; 8],
+    },
+    /// Passport only: challenge passed, waiting for ticket and profile.
+    AwaitingPassport {
+        package: String,
+        id: GatekeeperId,
+    },
+    Done,
+}
+
+pub(super) fn serve(shared: Arc<Shared>, id: ClientId, stream: TcpStream) {
+    let Ok(writer) = stream.try_clone() else {
+        return;
+    };
+    // The address the client dialled, which is what it hashes into its GateKeeper
+    // response; with a wildcard bind it differs from the listener's address.
+    let server_address = stream
+        .local_addr()
+        .map_or_else(|_| shared.server_address.clone(), |addr| addr.to_string());
+    shared.state().add_client(id, writer);
+
+    let mut reader = BufReader::new(stream);
+    let mut auth = Auth::Idle;
+    let mut line = Vec::new();
+
+    loop {
+        line.clear();
+        match reader.read_until(b'\n', &mut line) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {}
+        }
+        if shared.is_shut_down() {
+            break;
+        }
+        while matches!(line.last(), Some(b'\n' | b'\r')) {
+            line.pop();
+        }
+        if line.is_empty() {
+            continue;
+        }
+        shared.record(&line);
+
+        if !shared.latency.is_zero() {
+            thread::sleep(shared.latency);
+        }
+        if handle_line(&shared, id, &server_address, &line, &mut auth) == Flow::Close {
+            return;
+        }
+    }
+
+    shared.state().remove_client(id, "Connection reset by peer");
+}
+
+fn handle_line(
+    shared: &Shared,
+    id: ClientId,
+    server_address: &str,
+    line: &[u8],
+    auth: &mut Auth,
+) -> Flow {
+    let text = String::from_utf8_lossy(line);
+    let mut words = text.split_whitespace();
+    let mut name = words.next().unwrap_or_default();
+    if name.starts_with(':') {
+        name = words.next().unwrap_or_default();
+    }
+    let name = name.to_ascii_uppercase();
+
+    // Bound first so the scenario lock is released before sleeping or locking the state.
+    let action = shared.scenario().take(&name);
+    match action {
+        Some(Action::Reply(lines)) => {
+            let mut state = shared.state();
+            let nick = state
+                .client(id)
+                .and_then(|c| c.nick.clone())
+                .unwrap_or_else(|| "*".to_owned());
+            let server = state.server_name.clone();
+            for reply in lines {
+                let reply = reply.replace("{nick}", &nick).replace("{server}", &server);
+                state.send(id, &reply);
+            }
+            return Flow::Continue;
+        }
+        Some(Action::Error(numeric, text)) => {
+            let subject = words
+                .next()
+                .map_or(name.as_str(), |w| w.trim_start_matches(':'));
+            shared.state().numeric(id, numeric, &[subject, &text]);
+            return Flow::Continue;
+        }
+        Some(Action::Disconnect) => {
+            let mut state = shared.state();
+            state.disconnect(id);
+            state.remove_client(id, "Connection reset by peer");
+            return Flow::Close;
+        }
+        Some(Action::Delay(delay)) => thread::sleep(delay),
+        Some(Action::Ignore) => return Flow::Continue,
+        None => {}
+    }
+
+    if let Some(auth_line) = AuthLine::parse(line) {
+        handle_auth(shared, id, server_address, &auth_line, auth);
+        return Flow::Continue;
+    }
+
+    match Command::parse(&text) {
+        Ok(command) => shared.state().handle(id, command),
+        Err(_) => {
+            shared.state().numeric(
+                id,
+                Numeric::NeedMoreParams,
+                &[&name, "Not enough parameters"],
+            );
+            Flow::Continue
+        }
+    }
+}
+
+fn handle_auth(
+    shared: &Shared,
+    id: ClientId,
+    server_address: &str,
+    line: &AuthLine<'_>,
+    auth: &mut Auth,
+) {
+    let package = String::from_utf8_lossy(line.package).into_owned();
+    let mut state = shared.state();
+    let fail = |state: &mut State| {
+        state.numeric(
+            id,
+            Numeric::AuthenticationFailed,
+            &[GATEKEEPER, "Authentication failed"],
+        );
+    };
+
+    if ![GATEKEEPER, GATEKEEPER_PASSPORT]
+        .iter()
+        .any(|p| p.eq_ignore_ascii_case(&package))
+    {
+        return state.numeric(
+            id,
+            Numeric::UnknownPackage,
+            &[&package, "Unsupported package"],
+        );
+    }
+
+    match (std::mem::replace(auth, Auth::Idle), line.sequence) {
+        (Auth::Done, _) => {
+            *auth = Auth::Done;
+            state.numeric(
+                id,
+                Numeric::AlreadyAuthenticated,
+                &["Already authenticated"],
+            );
+        }
+        (Auth::Idle, b'I') => {
+            let header = unescape(line.data).ok().and_then(|d| {
+                decode_header(&d)
+                    .ok()
+                    .map(|(version, seq, _)| (version, seq))
+            });
+            let Some((version, 1)) = header else {
+                return fail(&mut state);
+            };
+            let mut challenge = [0u8; 8];
+            getrandom::getrandom(&mut challenge)
+                .expect("system random number generator unavailable");
+            let mut payload = encode_header(version, 2).to_vec();
+            payload.extend_from_slice(&challenge);
+            state.send_raw(id, &auth_line(&package, b'S', &payload));
+            *auth = Auth::Challenged { package, challenge };
+        }
+        (
+            Auth::Challenged {
+                package: expected,
+                challenge,
+            },
+            b'S',
+        ) if expected == package => {
+            let Ok(data) = unescape(line.data) else {
+                return fail(&mut state);
+            };
+            let Ok((version, 3, body)) = decode_header(&data) else {
+                return fail(&mut state);
+            };
+            let expected_mac = challenge_response(&challenge, version, server_address);
+            let Some((mac, guid)) = body.split_at_checked(16) else {
+                return fail(&mut state);
+            };
+            let Ok(guid) = <[u8; 16]>::try_from(guid) else {
+                return fail(&mut state);
+            };
+            if mac != expected_mac {
+                return fail(&mut state);
+            }
+            let gatekeeper_id = GatekeeperId::from_bytes(guid);
+
+            if package.eq_ignore_ascii_case(GATEKEEPER_PASSPORT) {
+                state.send(id, &format!("AUTH {package} S :OK"));
+                *auth = Auth::AwaitingPassport {
+                    package,
+                    id: gatekeeper_id,
+                };
+            } else {
+                authenticated(
+                    &mut state,
+                    id,
+                    &package,
+                    format!("{gatekeeper_id}@GateKeeper"),
+                );
+                *auth = Auth::Done;
+            }
+        }
+        (
+            Auth::AwaitingPassport {
+                package: expected,
+                id: gatekeeper_id,
+            },
+            b'S',
+        ) if expected == package => {
+            let credentials = unescape(line.data)
+                .ok()
+                .and_then(|d| PassportCredentials::decode_payload(&d).ok());
+            if credentials.is_none() {
+                return fail(&mut state);
+            }
+            authenticated(
+                &mut state,
+                id,
+                &package,
+                format!("{gatekeeper_id}@passport"),
+            );
+            *auth = Auth::Done;
+        }
+        _ => fail(&mut state),
+    }
+}
+
+fn authenticated(state: &mut State, id: ClientId, package: &str, account: String) {
+    state.send(id, &format!("AUTH {package} * {account} 0"));
+    if let Some(client) = state.client_mut(id) {
+        client.account = Some(account);
+    }
+}
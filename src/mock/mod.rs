@@ -0,0 +1,236 @@
+//! A local IRCX/MSN Chat server for tests, standing in for the defunct `irc.irc7.com`.
+//!
+//! It verifies GateKeeper and GateKeeperPassport auth, answers `IRCVERS`, and
+//! implements enough of JOIN/PART/PRIVMSG/WHISPER/PROP/ACCESS/KNOCK/CREATE for
+//! end-to-end tests. A [`Scenario`] scripts deviations and faults.
+//!
+//! ```no_run
+//! use msnchat_bindings::mock::{Action, MockServer, Scenario};
+//!
+//! let server = MockServer::builder()
+//!     .scenario(Scenario::new().once("JOIN", Action::Disconnect))
+//!     .spawn()
+//!     .unwrap();
+//! println!("listening on {}", server.address());
+//! ```
+
+mod connection;
+mod scenario;
+mod state;
+
+use std::{
+    io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    sync::{
+        Arc, Mutex, MutexGuard,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+pub use scenario::{Action, Rule, Scenario};
+use state::State;
+
+/// Configures a [`MockServer`].
+#[derive(Debug, Clone)]
+pub struct MockServerBuilder {
+    bind: SocketAddr,
+    server_name: String,
+    scenario: Scenario,
+    latency: Duration,
+    require_create: bool,
+}
+
+impl MockServerBuilder {
+    /// Listens on `addr` instead of a random localhost port.
+    pub fn bind(mut self, addr: impl ToSocketAddrs) -> io::Result<Self> {
+        self.bind = addr
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::AddrNotAvailable, "no address to bind"))?;
+        Ok(self)
+    }
+
+    /// Name used as the prefix of server messages.
+    pub fn server_name(mut self, name: impl Into<String>) -> Self {
+        self.server_name = name.into();
+        self
+    }
+
+    pub fn scenario(mut self, scenario: Scenario) -> Self {
+        self.scenario = scenario;
+        self
+    }
+
+    /// Delays the handling of every received line.
+    pub fn latency(mut self, latency: Duration) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    /// Makes `JOIN` fail with 403 for missing rooms, as MSN did; rooms then need `CREATE`.
+    pub fn require_create(mut self, require: bool) -> Self {
+        self.require_create = require;
+        self
+    }
+
+    /// Binds and starts accepting connections on a background thread.
+    pub fn spawn(self) -> io::Result<MockServer> {
+        let listener = TcpListener::bind(self.bind)?;
+        let addr = listener.local_addr()?;
+        let shared = Arc::new(Shared {
+            server_address: connectable(addr).to_string(),
+            latency: self.latency,
+            state: Mutex::new(State::new(self.server_name, self.require_create)),
+            scenario: Mutex::new(self.scenario),
+            received: Mutex::new(Vec::new()),
+            shut_down: AtomicBool::new(false),
+        });
+
+        let accept_shared = Arc::clone(&shared);
+        let acceptor = thread::spawn(move || accept(listener, accept_shared));
+
+        Ok(MockServer {
+            addr,
+            shared,
+            acceptor: Some(acceptor),
+        })
+    }
+}
+
+impl Default for MockServerBuilder {
+    fn default() -> Self {
+        Self {
+            bind: SocketAddr::from(([127, 0, 0, 1], 0)),
+            server_name: "mock.irc7.local".to_owned(),
+            scenario: Scenario::default(),
+            latency: Duration::ZERO,
+            require_create: false,
+        }
+    }
+}
+
+/// A running mock server; shut down on drop.
+#[derive(Debug)]
+pub struct MockServer {
+    addr: SocketAddr,
+    shared: Arc<Shared>,
+    acceptor: Option<JoinHandle<()>>,
+}
+
+impl MockServer {
+    pub fn builder() -> MockServerBuilder {
+        MockServerBuilder::default()
+    }
+
+    /// Starts a default server on a random localhost port.
+    pub fn spawn() -> io::Result<Self> {
+        Self::builder().spawn()
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// `ip:port`, in the form a frame's `Server` property and the GateKeeper hash use.
+    ///
+    /// A wildcard bind such as `0.0.0.0` is reported as the loopback address.
+    pub fn address(&self) -> String {
+        self.shared.server_address.clone()
+    }
+
+    /// Every line received so far, across all connections, without CRLF.
+    pub fn received(&self) -> Vec<String> {
+        lock(&self.shared.received).clone()
+    }
+
+    /// Replaces the scenario for lines received from now on.
+    pub fn set_scenario(&self, scenario: Scenario) {
+        *lock(&self.shared.scenario) = scenario;
+    }
+
+    /// Stops accepting and drops every connection.
+    pub fn shutdown(&mut self) {
+        if self.shared.shut_down.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        self.shared.state().disconnect_all();
+        // Wake the acceptor so it sees the flag.
+        let _ = TcpStream::connect(connectable(self.addr));
+        if let Some(acceptor) = self.acceptor.take() {
+            let _ = acceptor.join();
+        }
+    }
+
+    /// Blocks until the server is shut down from another thread or fails.
+    pub fn wait(mut self) {
+        if let Some(acceptor) = self.acceptor.take() {
+            let _ = acceptor.join();
+        }
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+/// `addr` with a wildcard IP replaced by the loopback address of its family.
+fn connectable(mut addr: SocketAddr) -> SocketAddr {
+    if addr.ip().is_unspecified() {
+        let loopback = match addr {
+            SocketAddr::V4(_) => IpAddr::V4(Ipv4Addr::LOCALHOST),
+            SocketAddr::V6(_) => IpAddr::V6(Ipv6Addr::LOCALHOST),
+        };
+        addr.set_ip(loopback);
+    }
+    addr
+}
+
+#[derive(Debug)]
+struct Shared {
+    server_address: String,
+    latency: Duration,
+    state: Mutex<State>,
+    scenario: Mutex<Scenario>,
+    received: Mutex<Vec<String>>,
+    shut_down: AtomicBool,
+}
+
+impl Shared {
+    fn state(&self) -> MutexGuard<'_, State> {
+        lock(&self.state)
+    }
+
+    fn scenario(&self) -> MutexGuard<'_, Scenario> {
+        lock(&self.scenario)
+    }
+
+    fn record(&self, line: &[u8]) {
+        lock(&self.received).push(String::from_utf8_lossy(line).into_owned());
+    }
+
+    fn is_shut_down(&self) -> bool {
+        self.shut_down.load(Ordering::SeqCst)
+    }
+}
+
+fn accept(listener: TcpListener, shared: Arc<Shared>) {
+    for (id, stream) in (1..).zip(listener.incoming()) {
+        if shared.is_shut_down() {
+            return;
+        }
+        let Ok(stream) = stream else { continue };
+        let shared = Arc::clone(&shared);
+        thread::spawn(move || connection::serve(shared, id, stream));
+    }
+}
+
+/// A panicking connection thread must not take the whole server down with it.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
@@ -0,0 +1,92 @@
+use std::time::Duration;
+
+use crate::ircx::Numeric;
+
+/// What the server does instead of (or before) its normal handling of a command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    /// Sends these raw lines and skips normal handling.
+    ///
+    /// `{nick}` and `{server}` are substituted; CRLF is appended.
+    Reply(Vec<String>),
+    /// Answers with an error numeric and skips normal handling.
+    ///
+    /// The error's subject is the command's first parameter, e.g. the channel of
+    /// a `JOIN`, or the command name when it has none.
+    Error(Numeric, String),
+    /// Drops the connection without a reply.
+    Disconnect,
+    /// Waits, then handles the command normally.
+    Delay(Duration),
+    /// Swallows the command.
+    Ignore,
+}
+
+/// An [`Action`] bound to a command name (`*` matches any command).
+#[derive(Debug, Clone)]
+pub struct Rule {
+    pub command: String,
+    pub action: Action,
+    /// Remaining uses; `None` for unlimited.
+    pub remaining: Option<usize>,
+}
+
+impl Rule {
+    fn matches(&self, command: &str) -> bool {
+        self.remaining != Some(0)
+            && (self.command == "*" || self.command.eq_ignore_ascii_case(command))
+    }
+}
+
+/// Scripted deviations from the default server behaviour.
+///
+/// Rules are tried in order and the first match wins; rule state is shared by
+/// every connection to the server.
+#[derive(Debug, Clone, Default)]
+pub struct Scenario {
+    rules: Vec<Rule>,
+}
+
+impl Scenario {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Applies `action` every time `command` is received.
+    pub fn on(mut self, command: &str, action: Action) -> Self {
+        self.rules.push(Rule {
+            command: command.to_owned(),
+            action,
+            remaining: None,
+        });
+        self
+    }
+
+    /// Applies `action` the first time `command` is received.
+    pub fn once(self, command: &str, action: Action) -> Self {
+        self.times(command, 1, action)
+    }
+
+    /// Applies `action` to the next `count` occurrences of `command`.
+    pub fn times(mut self, command: &str, count: usize, action: Action) -> Self {
+        self.rules.push(Rule {
+            command: command.to_owned(),
+            action,
+            remaining: Some(count),
+        });
+        self
+    }
+
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    /// Consumes one use of the first rule matching `command`.
+    pub(super) fn take(&mut self, command: &str) -> Option<Action> {
+        let rule = self.rules.iter_mut().find(|r| r.matches(command))?;
+        if let Some(remaining) = rule.remaining.as_mut() {
+            *remaining -= 1;
+        }
+        Some(rule.action.clone())
+    }
+}
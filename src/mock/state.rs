@@ -0,0 +1,974 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    io::Write,
+    net::TcpStream,
+};
+
+use crate::{
+    ircx::{
+        Command, Numeric,
+        access::{AccessLevel, mask_matches},
+        room::is_channel,
+    },
+    types::{ChannelMode, ChannelModes, CreationModes, Privilege, UserRole},
+};
+
+pub(super) type ClientId = u64;
+
+/// Whether the connection should stay open after a command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Flow {
+    Continue,
+    Close,
+}
+
+#[derive(Debug)]
+pub(super) struct Client {
+    pub nick: Option<String>,
+    pub user: Option<String>,
+    /// `<GUID>@GateKeeper` once authenticated.
+    pub account: Option<String>,
+    pub registered: bool,
+    stream: TcpStream,
+}
+
+impl Client {
+    fn nick(&self) -> &str {
+        self.nick.as_deref().unwrap_or("*")
+    }
+
+    fn prefix(&self) -> String {
+        let user = self.user.as_deref().unwrap_or("~user");
+        format!("{}!{user}@localhost", self.nick())
+    }
+}
+
+#[derive(Debug, Clone)]
+struct AccessEntry {
+    level: String,
+    mask: String,
+    timeout: String,
+    creator: String,
+    reason: String,
+}
+
+#[derive(Debug, Default)]
+struct Channel {
+    name: String,
+    topic: Option<String>,
+    props: BTreeMap<String, String>,
+    modes: ChannelModes,
+    members: BTreeMap<ClientId, UserRole>,
+    access: Vec<AccessEntry>,
+    /// Lower-cased nicks invited past `+i`.
+    invited: BTreeSet<String>,
+}
+
+impl Channel {
+    fn new(name: &str, modes: ChannelModes) -> Self {
+        Self {
+            name: name.to_owned(),
+            modes,
+            ..Self::default()
+        }
+    }
+
+    fn role(&self, id: ClientId) -> Option<UserRole> {
+        self.members.get(&id).copied()
+    }
+
+    /// The highest access level granted to `prefix`; as in
+    /// [`AccessList::level_for`](crate::ircx::access::AccessList::level_for), a
+    /// matching `GRANT` or better overrides `DENY`.
+    fn access_level(&self, prefix: &str) -> Option<AccessLevel> {
+        self.access
+            .iter()
+            .filter(|e| mask_matches(&e.mask, prefix))
+            .filter_map(|e| e.level.parse().ok())
+            .max()
+    }
+}
+
+#[derive(Debug)]
+pub(super) struct State {
+    pub server_name: String,
+    /// JOIN on a missing channel fails with 403 instead of creating it.
+    pub require_create: bool,
+    clients: HashMap<ClientId, Client>,
+    /// Keyed by lower-cased name.
+    channels: BTreeMap<String, Channel>,
+}
+
+impl State {
+    pub fn new(server_name: String, require_create: bool) -> Self {
+        Self {
+            server_name,
+            require_create,
+            clients: HashMap::new(),
+            channels: BTreeMap::new(),
+        }
+    }
+
+    pub fn add_client(&mut self, id: ClientId, stream: TcpStream) {
+        self.clients.insert(
+            id,
+            Client {
+                nick: None,
+                user: None,
+                account: None,
+                registered: false,
+                stream,
+            },
+        );
+    }
+
+    pub fn client(&self, id: ClientId) -> Option<&Client> {
+        self.clients.get(&id)
+    }
+
+    pub fn client_mut(&mut self, id: ClientId) -> Option<&mut Client> {
+        self.clients.get_mut(&id)
+    }
+
+    /// Closes a client's socket without telling anyone.
+    pub fn disconnect(&mut self, id: ClientId) {
+        if let Some(client) = self.clients.get(&id) {
+            let _ = client.stream.shutdown(std::net::Shutdown::Both);
+        }
+    }
+
+    /// Closes every client socket.
+    pub fn disconnect_all(&mut self) {
+        for client in self.clients.values() {
+            let _ = client.stream.shutdown(std::net::Shutdown::Both);
+        }
+    }
+
+    /// Removes a client, telling its channels it quit.
+    pub fn remove_client(&mut self, id: ClientId, reason: &str) {
+        let Some(client) = self.clients.get(&id) else {
+            return;
+        };
+        let line = format!(":{} QUIT :{reason}", client.prefix());
+        let peers: BTreeSet<ClientId> = self
+            .channels
+            .values()
+            .filter(|c| c.members.contains_key(&id))
+            .flat_map(|c| c.members.keys().copied())
+            .filter(|&peer| peer != id)
+            .collect();
+        for peer in peers {
+            self.send(peer, &line);
+        }
+        for channel in self.channels.values_mut() {
+            channel.members.remove(&id);
+        }
+        self.channels.retain(|_, c| !c.members.is_empty());
+        self.clients.remove(&id);
+    }
+
+    /// Writes one line (CRLF appended) to a client, ignoring dead sockets.
+    pub fn send(&mut self, id: ClientId, line: &str) {
+        if let Some(client) = self.clients.get_mut(&id) {
+            let _ = client.stream.write_all(format!("{line}\r\n").as_bytes());
+        }
+    }
+
+    pub fn send_raw(&mut self, id: ClientId, bytes: &[u8]) {
+        if let Some(client) = self.clients.get_mut(&id) {
+            let _ = client.stream.write_all(bytes);
+        }
+    }
+
+    /// Sends `:<server> <numeric> <nick> <params>`; the last param goes out as trailing.
+    pub fn numeric(&mut self, id: ClientId, numeric: Numeric, params: &[&str]) {
+        let nick = self.clients.get(&id).map_or("*", Client::nick).to_owned();
+        let mut line = format!(":{} {:03} {nick}", self.server_name, numeric.code());
+        if let Some((last, rest)) = params.split_last() {
+            for param in rest {
+                line.push(' ');
+                line.push_str(param);
+            }
+            line.push_str(" :");
+            line.push_str(last);
+        }
+        self.send(id, &line);
+    }
+
+    fn broadcast(&mut self, channel: &str, line: &str, except: Option<ClientId>) {
+        let members: Vec<ClientId> = self
+            .channels
+            .get(&channel.to_lowercase())
+            .map(|c| c.members.keys().copied().collect())
+            .unwrap_or_default();
+        for member in members.into_iter().filter(|&m| Some(m) != except) {
+            self.send(member, line);
+        }
+    }
+
+    fn prefix(&self, id: ClientId) -> String {
+        self.clients
+            .get(&id)
+            .map(Client::prefix)
+            .unwrap_or_default()
+    }
+
+    fn nick(&self, id: ClientId) -> String {
+        self.clients.get(&id).map_or("*", Client::nick).to_owned()
+    }
+
+    fn find_nick(&self, nick: &str) -> Option<ClientId> {
+        self.clients
+            .iter()
+            .find(|(_, c)| {
+                c.nick
+                    .as_deref()
+                    .is_some_and(|n| n.eq_ignore_ascii_case(nick))
+            })
+            .map(|(&id, _)| id)
+    }
+
+    /// Handles one parsed command from `id`.
+    pub fn handle(&mut self, id: ClientId, command: Command) -> Flow {
+        let registered = self.clients.get(&id).is_some_and(|c| c.registered);
+        match command {
+            Command::Ircvers { .. } | Command::IsIrcx | Command::ModeIsIrcx => {
+                self.numeric(
+                    id,
+                    Numeric::Ircx,
+                    &["1", "0", "GateKeeper,GateKeeperPassport", "512", "*"],
+                );
+            }
+            Command::Ping(token) => {
+                let line = format!(":{0} PONG {0} :{token}", self.server_name);
+                self.send(id, &line);
+            }
+            Command::Pong(_) | Command::Pass(_) => {}
+            Command::Nick(nick) => self.nick_change(id, nick),
+            Command::User { username, .. } => {
+                if let Some(client) = self.clients.get_mut(&id) {
+                    client.user.get_or_insert(username);
+                }
+                self.try_welcome(id);
+            }
+            Command::Quit(reason) => {
+                self.send(id, "ERROR :Closing link");
+                self.remove_client(id, reason.as_deref().unwrap_or("Quit"));
+                return Flow::Close;
+            }
+            _ if !registered => {
+                self.numeric(id, Numeric::NotRegistered, &["You have not registered"])
+            }
+
+            Command::Join { channels, keys } => {
+                let keys: Vec<&str> = keys.as_deref().map_or(vec![], |k| k.split(',').collect());
+                for (i, channel) in channels.split(',').enumerate() {
+                    self.join(id, channel, keys.get(i).copied());
+                }
+            }
+            Command::Create { channel, args } => self.create(id, &channel, &args),
+            Command::Part { channels, reason } => {
+                for channel in channels.split(',') {
+                    self.part(id, channel, reason.as_deref());
+                }
+            }
+            Command::Privmsg { target, text } => self.message(id, "PRIVMSG", &target, &text),
+            Command::Notice { target, text } => self.message(id, "NOTICE", &target, &text),
+            Command::Whisper {
+                channel,
+                targets,
+                text,
+            } => self.whisper(id, &channel, &targets, &text),
+            Command::Topic { channel, topic } => self.topic(id, &channel, topic),
+            Command::Names(Some(channel)) => self.names(id, &channel),
+            Command::Mode {
+                target,
+                modes,
+                args,
+            } => self.mode(id, &target, modes.as_deref(), &args),
+            Command::Kick {
+                channel,
+                nick,
+                reason,
+            } => self.kick(id, &channel, &nick, reason.as_deref()),
+            Command::Invite { nick, channel } => self.invite(id, &nick, &channel),
+            Command::Prop {
+                target,
+                property,
+                value,
+            } => self.prop(id, &target, &property, value),
+            Command::Access {
+                target,
+                operation,
+                args,
+            } => self.access(id, &target, operation.as_deref(), &args),
+            Command::Knock { channel, reason } => self.knock(id, &channel, &reason),
+            other => {
+                let name = other.name().to_owned();
+                self.numeric(id, Numeric::UnknownCommand, &[&name, "Unknown command"]);
+            }
+        }
+        Flow::Continue
+    }
+
+    fn nick_change(&mut self, id: ClientId, nick: String) {
+        if self.find_nick(&nick).is_some_and(|other| other != id) {
+            return self.numeric(
+                id,
+                Numeric::NicknameInUse,
+                &[&nick, "Nickname is already in use"],
+            );
+        }
+        let old_prefix = self.prefix(id);
+        let Some(client) = self.clients.get_mut(&id) else {
+            return;
+        };
+        let registered = client.registered;
+        client.nick = Some(nick.clone());
+
+        if registered {
+            let line = format!(":{old_prefix} NICK :{nick}");
+            let channels: Vec<String> = self
+                .channels
+                .values()
+                .filter(|c| c.members.contains_key(&id))
+                .map(|c| c.name.clone())
+                .collect();
+            for channel in channels {
+                self.broadcast(&channel, &line, Some(id));
+            }
+            self.send(id, &line);
+        } else {
+            self.try_welcome(id);
+        }
+    }
+
+    fn try_welcome(&mut self, id: ClientId) {
+        let Some(client) = self.clients.get_mut(&id) else {
+            return;
+        };
+        if client.registered || client.nick.is_none() || client.user.is_none() {
+            return;
+        }
+        client.registered = true;
+        let text = format!("Welcome to the MSN Chat mock server {}", client.nick());
+        self.numeric(id, Numeric::Welcome, &[&text]);
+    }
+
+    fn join(&mut self, id: ClientId, name: &str, key: Option<&str>) {
+        let lower = name.to_lowercase();
+        let prefix = self.prefix(id);
+        let nick = self.nick(id).to_lowercase();
+
+        let Some(channel) = self.channels.get_mut(&lower) else {
+            if self.require_create {
+                return self.numeric(id, Numeric::NoSuchChannel, &[name, "No such channel"]);
+            }
+            return self.create_channel(id, name, ChannelModes::default());
+        };
+        if channel.members.contains_key(&id) {
+            return self.numeric(
+                id,
+                Numeric::AlreadyOnChannel,
+                &[name, "Already in the channel"],
+            );
+        }
+
+        let level = channel.access_level(&prefix);
+        let refused = match level {
+            Some(AccessLevel::Deny) => Some((Numeric::BannedFromChan, "Cannot join channel (+b)")),
+            Some(AccessLevel::Owner | AccessLevel::Host) => None,
+            _ if channel.modes.member_key.is_some()
+                && channel.modes.member_key.as_deref() != key =>
+            {
+                Some((Numeric::BadChannelKey, "Cannot join channel (+k)"))
+            }
+            _ if channel.modes.contains(ChannelMode::InviteOnly)
+                && !channel.invited.contains(&nick) =>
+            {
+                Some((Numeric::InviteOnlyChan, "Cannot join channel (+i)"))
+            }
+            _ if channel
+                .modes
+                .user_limit
+                .is_some_and(|limit| channel.members.len() >= usize::from(limit)) =>
+            {
+                Some((Numeric::ChannelIsFull, "Cannot join channel (+l)"))
+            }
+            _ => None,
+        };
+
+        if let Some((numeric, text)) = refused {
+            let knock = channel.modes.contains(ChannelMode::Knock);
+            let channel_name = channel.name.clone();
+            self.numeric(id, numeric, &[&channel_name, text]);
+            if knock && numeric != Numeric::BannedFromChan {
+                self.notify_hosts(
+                    &channel_name,
+                    &format!(":{prefix} KNOCK {channel_name} {:03}", numeric.code()),
+                );
+            }
+            return;
+        }
+
+        let role = match level {
+            Some(AccessLevel::Owner) => UserRole::Owner,
+            Some(AccessLevel::Host) => UserRole::Host,
+            _ if channel.modes.contains(ChannelMode::Moderated) => UserRole::Spectator,
+            _ => UserRole::Participant,
+        };
+        channel.invited.remove(&nick);
+        channel.members.insert(id, role);
+        let channel_name = channel.name.clone();
+        self.joined(id, &channel_name);
+    }
+
+    fn create(&mut self, id: ClientId, name: &str, args: &[String]) {
+        if self.channels.contains_key(&name.to_lowercase()) {
+            return self.numeric(id, Numeric::ChannelExist, &[name, "Channel already exists"]);
+        }
+        match args.join(" ").parse::<CreationModes>() {
+            Ok(creation) => {
+                self.create_channel(id, name, creation.modes);
+                if let Some(language) = creation.language
+                    && let Some(channel) = self.channels.get_mut(&name.to_lowercase())
+                {
+                    channel
+                        .props
+                        .insert("LANGUAGE".to_owned(), language.to_string());
+                }
+            }
+            Err(err) => self.numeric(id, Numeric::BadValue, &[name, &err.to_string()]),
+        }
+    }
+
+    fn create_channel(&mut self, id: ClientId, name: &str, modes: ChannelModes) {
+        let mut channel = Channel::new(name, modes);
+        channel.members.insert(id, UserRole::Owner);
+        self.channels.insert(name.to_lowercase(), channel);
+        self.joined(id, name);
+    }
+
+    fn joined(&mut self, id: ClientId, channel: &str) {
+        let line = format!(":{} JOIN :{channel}", self.prefix(id));
+        self.broadcast(channel, &line, None);
+        if let Some(topic) = self.channel(channel).and_then(|c| c.topic.clone()) {
+            self.numeric(id, Numeric::Topic, &[channel, &topic]);
+        }
+        self.names(id, channel);
+    }
+
+    fn names(&mut self, id: ClientId, name: &str) {
+        let Some(channel) = self.channel(name) else {
+            return self.numeric(id, Numeric::NoSuchChannel, &[name, "No such channel"]);
+        };
+        let channel_name = channel.name.clone();
+        let names: Vec<String> = channel
+            .members
+            .iter()
+            .filter_map(|(member, role)| {
+                let nick = self.clients.get(member)?.nick();
+                Some(match role.prefix() {
+                    Some(prefix) => format!("{prefix}{nick}"),
+                    None => nick.to_owned(),
+                })
+            })
+            .collect();
+        self.numeric(
+            id,
+            Numeric::NamReply,
+            &["=", &channel_name, &names.join(" ")],
+        );
+        self.numeric(
+            id,
+            Numeric::EndOfNames,
+            &[&channel_name, "End of /NAMES list."],
+        );
+    }
+
+    fn part(&mut self, id: ClientId, name: &str, reason: Option<&str>) {
+        if self.member_role(id, name).is_none() {
+            return self.numeric(
+                id,
+                Numeric::NotOnChannel,
+                &[name, "You're not on that channel"],
+            );
+        }
+        let mut line = format!(":{} PART {name}", self.prefix(id));
+        if let Some(reason) = reason {
+            line.push_str(" :");
+            line.push_str(reason);
+        }
+        self.broadcast(name, &line, None);
+        self.leave(id, name);
+    }
+
+    fn leave(&mut self, id: ClientId, name: &str) {
+        let lower = name.to_lowercase();
+        if let Some(channel) = self.channels.get_mut(&lower) {
+            channel.members.remove(&id);
+            if channel.members.is_empty() {
+                self.channels.remove(&lower);
+            }
+        }
+    }
+
+    fn message(&mut self, id: ClientId, command: &str, target: &str, text: &str) {
+        let line = format!(":{} {command} {target} :{text}", self.prefix(id));
+        if !is_channel(target) {
+            return match self.find_nick(target) {
+                Some(peer) => self.send(peer, &line),
+                None => self.numeric(id, Numeric::NoSuchNick, &[target, "No such nick/channel"]),
+            };
+        }
+        match self.member_role(id, target) {
+            None if self.channel(target).is_none() => {
+                self.numeric(id, Numeric::NoSuchChannel, &[target, "No such channel"])
+            }
+            Some(role) if role.may(Privilege::Speak) => self.broadcast(target, &line, Some(id)),
+            _ => self.numeric(
+                id,
+                Numeric::CannotSendToChan,
+                &[target, "Cannot send to channel"],
+            ),
+        }
+    }
+
+    fn whisper(&mut self, id: ClientId, channel: &str, targets: &str, text: &str) {
+        if self.member_role(id, channel).is_none() {
+            return self.numeric(
+                id,
+                Numeric::NotOnChannel,
+                &[channel, "You're not on that channel"],
+            );
+        }
+        if self
+            .channel(channel)
+            .is_some_and(|c| c.modes.contains(ChannelMode::NoWhisper))
+        {
+            return self.numeric(
+                id,
+                Numeric::NoWhisper,
+                &[channel, "Does not permit whispers"],
+            );
+        }
+        let prefix = self.prefix(id);
+        for target in targets.split(',') {
+            match self
+                .find_nick(target)
+                .filter(|&peer| self.member_role(peer, channel).is_some())
+            {
+                Some(peer) => {
+                    let line = format!(":{prefix} WHISPER {channel} {target} :{text}");
+                    self.send(peer, &line);
+                }
+                None => self.numeric(id, Numeric::NoSuchNick, &[target, "No such nick/channel"]),
+            }
+        }
+    }
+
+    fn topic(&mut self, id: ClientId, name: &str, topic: Option<String>) {
+        let Some(role) = self.member_role(id, name) else {
+            return self.numeric(
+                id,
+                Numeric::NotOnChannel,
+                &[name, "You're not on that channel"],
+            );
+        };
+        let Some(topic) = topic else {
+            return match self.channel(name).and_then(|c| c.topic.clone()) {
+                Some(topic) => self.numeric(id, Numeric::Topic, &[name, &topic]),
+                None => self.numeric(id, Numeric::NoTopic, &[name, "No topic is set"]),
+            };
+        };
+        let restricted = self
+            .channel(name)
+            .is_some_and(|c| c.modes.contains(ChannelMode::TopicOp));
+        if restricted && !role.may_set_topic() {
+            return self.op_needed(id, name);
+        }
+        if let Some(channel) = self.channel_mut(name) {
+            channel.topic = Some(topic.clone());
+        }
+        let line = format!(":{} TOPIC {name} :{topic}", self.prefix(id));
+        self.broadcast(name, &line, None);
+    }
+
+    fn mode(&mut self, id: ClientId, target: &str, modes: Option<&str>, args: &[String]) {
+        if !is_channel(target) {
+            return self.numeric(id, Numeric::UModeIs, &["+"]);
+        }
+        let Some(channel) = self.channel(target) else {
+            return self.numeric(id, Numeric::NoSuchChannel, &[target, "No such channel"]);
+        };
+        let Some(modes) = modes else {
+            let mut params = vec![channel.name.clone(), channel.modes.letters()];
+            params.extend(channel.modes.arguments());
+            let params: Vec<&str> = params.iter().map(String::as_str).collect();
+            return self.numeric(id, Numeric::ChannelModeIs, &params);
+        };
+        let Some(role) = self.member_role(id, target) else {
+            return self.numeric(
+                id,
+                Numeric::NotOnChannel,
+                &[target, "You're not on that channel"],
+            );
+        };
+        if !role.may_change_modes() {
+            return self.op_needed(id, target);
+        }
+        let known = |c: char| "+-qovkl".contains(c) || ChannelMode::from_letter(c).is_some();
+        if let Some(unknown) = modes.chars().find(|c| !known(*c)) {
+            return self.numeric(
+                id,
+                Numeric::UnknownMode,
+                &[&unknown.to_string(), "is unknown mode char to me"],
+            );
+        }
+
+        let mut pending = args.iter();
+        let mut adding = true;
+        for c in modes.chars() {
+            match c {
+                '+' => adding = true,
+                '-' => adding = false,
+                'q' | 'o' | 'v' => {
+                    let Some(nick) = pending.next() else { continue };
+                    let Some(peer) = self.find_nick(nick) else {
+                        continue;
+                    };
+                    let new_role = match (c, adding) {
+                        ('q', true) => UserRole::Owner,
+                        ('o', true) => UserRole::Host,
+                        _ => UserRole::Participant,
+                    };
+                    if let Some(channel) = self.channel_mut(target)
+                        && let Some(member) = channel.members.get_mut(&peer)
+                    {
+                        *member = new_role;
+                    }
+                }
+                'k' => {
+                    // `-k` names the key being removed, so both directions take an argument.
+                    let key = pending.next().cloned().filter(|_| adding);
+                    if let Some(channel) = self.channel_mut(target) {
+                        channel.modes.member_key = key;
+                    }
+                }
+                'l' => {
+                    let limit = if adding {
+                        pending.next().and_then(|l| l.parse().ok())
+                    } else {
+                        None
+                    };
+                    if let Some(channel) = self.channel_mut(target) {
+                        channel.modes.user_limit = limit;
+                    }
+                }
+                c => {
+                    let Some(mode) = ChannelMode::from_letter(c) else {
+                        continue;
+                    };
+                    if let Some(channel) = self.channel_mut(target) {
+                        if adding {
+                            channel.modes.flags.insert(mode);
+                        } else {
+                            channel.modes.flags.remove(&mode);
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut line = format!(":{} MODE {target} {modes}", self.prefix(id));
+        for arg in args {
+            line.push(' ');
+            line.push_str(arg);
+        }
+        self.broadcast(target, &line, None);
+    }
+
+    fn kick(&mut self, id: ClientId, name: &str, nick: &str, reason: Option<&str>) {
+        let Some(role) = self.member_role(id, name) else {
+            return self.numeric(
+                id,
+                Numeric::NotOnChannel,
+                &[name, "You're not on that channel"],
+            );
+        };
+        let Some((peer, target_role)) = self
+            .find_nick(nick)
+            .and_then(|peer| Some((peer, self.member_role(peer, name)?)))
+        else {
+            return self.numeric(
+                id,
+                Numeric::UserNotInChannel,
+                &[nick, name, "They aren't on that channel"],
+            );
+        };
+        if !role.may_act_on(target_role, Privilege::Kick) {
+            return self.op_needed(id, name);
+        }
+        let line = format!(
+            ":{} KICK {name} {nick} :{}",
+            self.prefix(id),
+            reason.unwrap_or_default()
+        );
+        self.broadcast(name, &line, None);
+        self.leave(peer, name);
+    }
+
+    fn invite(&mut self, id: ClientId, nick: &str, name: &str) {
+        let Some(role) = self.member_role(id, name) else {
+            return self.numeric(
+                id,
+                Numeric::NotOnChannel,
+                &[name, "You're not on that channel"],
+            );
+        };
+        let Some(peer) = self.find_nick(nick) else {
+            return self.numeric(id, Numeric::NoSuchNick, &[nick, "No such nick/channel"]);
+        };
+        if self.member_role(peer, name).is_some() {
+            return self.numeric(
+                id,
+                Numeric::UserOnChannel,
+                &[nick, name, "is already on channel"],
+            );
+        }
+        let invite_only = self
+            .channel(name)
+            .is_some_and(|c| c.modes.contains(ChannelMode::InviteOnly));
+        if invite_only && role < UserRole::Host {
+            return self.op_needed(id, name);
+        }
+        if let Some(channel) = self.channel_mut(name) {
+            channel.invited.insert(nick.to_lowercase());
+        }
+        self.numeric(id, Numeric::Inviting, &[nick, name]);
+        let line = format!(":{} INVITE {nick} :{name}", self.prefix(id));
+        self.send(peer, &line);
+    }
+
+    fn prop(&mut self, id: ClientId, name: &str, property: &str, value: Option<String>) {
+        // Passport clients hand over their profile cookie; there is no profile to update.
+        if name == "$" && property.eq_ignore_ascii_case("MSNREGCOOKIE") {
+            return;
+        }
+        let Some(channel) = self.channel(name) else {
+            return self.numeric(id, Numeric::NoSuchObject, &[name, "No such object"]);
+        };
+        let channel_name = channel.name.clone();
+        let property = property.to_ascii_uppercase();
+
+        let Some(value) = value else {
+            let mut props: Vec<(String, String)> = channel
+                .props
+                .iter()
+                .filter(|(k, _)| property == "*" || **k == property)
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect();
+            if let Some(topic) = channel
+                .topic
+                .clone()
+                .filter(|_| property == "*" || property == "TOPIC")
+            {
+                props.push(("TOPIC".to_owned(), topic));
+            }
+            for (key, value) in props {
+                self.numeric(id, Numeric::PropList, &[&channel_name, &key, &value]);
+            }
+            return self.numeric(id, Numeric::PropEnd, &[&channel_name, "End of properties"]);
+        };
+
+        let role = self.member_role(id, name).unwrap_or(UserRole::Spectator);
+        let needed = match property.as_str() {
+            "OWNERKEY" => UserRole::Owner,
+            _ => UserRole::Host,
+        };
+        if role < needed {
+            return self.numeric(
+                id,
+                Numeric::Security,
+                &[&channel_name, "No permissions to perform command"],
+            );
+        }
+        if let Some(channel) = self.channel_mut(name) {
+            match property.as_str() {
+                "TOPIC" => channel.topic = Some(value.clone()).filter(|v| !v.is_empty()),
+                "MEMBERKEY" => {
+                    channel.modes.member_key = Some(value.clone()).filter(|v| !v.is_empty())
+                }
+                _ if value.is_empty() => {
+                    channel.props.remove(&property);
+                }
+                _ => {
+                    channel.props.insert(property.clone(), value.clone());
+                }
+            }
+        }
+        let line = format!(
+            ":{} PROP {channel_name} {property} :{value}",
+            self.prefix(id)
+        );
+        self.broadcast(name, &line, None);
+    }
+
+    fn access(&mut self, id: ClientId, name: &str, operation: Option<&str>, args: &[String]) {
+        let Some(channel) = self.channel(name) else {
+            return self.numeric(id, Numeric::NoSuchObject, &[name, "No such object"]);
+        };
+        let channel_name = channel.name.clone();
+        let role = self.member_role(id, name).unwrap_or(UserRole::Spectator);
+        let operation = operation.map(str::to_ascii_uppercase);
+
+        if matches!(operation.as_deref(), None | Some("LIST")) {
+            let entries = channel.access.clone();
+            self.numeric(
+                id,
+                Numeric::AccessStart,
+                &[&channel_name, "Start of access entries"],
+            );
+            for e in entries {
+                self.numeric(
+                    id,
+                    Numeric::AccessList,
+                    &[
+                        &channel_name,
+                        &e.level,
+                        &e.mask,
+                        &e.timeout,
+                        &e.creator,
+                        &e.reason,
+                    ],
+                );
+            }
+            return self.numeric(
+                id,
+                Numeric::AccessEnd,
+                &[&channel_name, "End of access entries"],
+            );
+        }
+
+        if !role.may(Privilege::ManageAccess) {
+            return self.numeric(id, Numeric::NoAccess, &[&channel_name, "No access"]);
+        }
+        let level = args.first().map(|l| l.to_ascii_uppercase());
+        let mask = args.get(1).cloned();
+
+        match (operation.as_deref(), level, mask) {
+            (Some("ADD"), Some(level), Some(mask)) => {
+                if matches!(level.as_str(), "OWNER" | "HOST") && role < UserRole::Owner {
+                    return self.numeric(id, Numeric::NoAccess, &[&channel_name, "No access"]);
+                }
+                let entry = AccessEntry {
+                    level,
+                    mask,
+                    timeout: args.get(2).cloned().unwrap_or_else(|| "0".to_owned()),
+                    creator: self.nick(id),
+                    reason: args.get(3).cloned().unwrap_or_default(),
+                };
+                let Some(channel) = self.channel_mut(name) else {
+                    return;
+                };
+                if channel
+                    .access
+                    .iter()
+                    .any(|e| e.level == entry.level && e.mask.eq_ignore_ascii_case(&entry.mask))
+                {
+                    return self.numeric(
+                        id,
+                        Numeric::DupAccess,
+                        &[&channel_name, "Duplicate access entry"],
+                    );
+                }
+                channel.access.push(entry.clone());
+                self.numeric(
+                    id,
+                    Numeric::AccessAdd,
+                    &[
+                        &channel_name,
+                        &entry.level,
+                        &entry.mask,
+                        &entry.timeout,
+                        &entry.creator,
+                        &entry.reason,
+                    ],
+                );
+            }
+            (Some("DELETE"), Some(level), Some(mask)) => {
+                let Some(channel) = self.channel_mut(name) else {
+                    return;
+                };
+                let before = channel.access.len();
+                channel
+                    .access
+                    .retain(|e| !(e.level == level && e.mask.eq_ignore_ascii_case(&mask)));
+                if channel.access.len() == before {
+                    return self.numeric(
+                        id,
+                        Numeric::MisAccess,
+                        &[&channel_name, "Unknown access entry"],
+                    );
+                }
+                self.numeric(id, Numeric::AccessDelete, &[&channel_name, &level, &mask]);
+            }
+            (Some("CLEAR"), level, _) => {
+                let Some(channel) = self.channel_mut(name) else {
+                    return;
+                };
+                channel
+                    .access
+                    .retain(|e| level.as_ref().is_some_and(|l| *l != e.level));
+                let level = level.unwrap_or_else(|| "*".to_owned());
+                self.numeric(id, Numeric::AccessClear, &[&channel_name, &level, "Clear"]);
+            }
+            (Some(op), ..) => self.numeric(id, Numeric::BadFunction, &[op, "Bad function"]),
+            (None, ..) => unreachable!("LIST handled above"),
+        }
+    }
+
+    fn knock(&mut self, id: ClientId, name: &str, reason: &str) {
+        let Some(channel_name) = self.channel(name).map(|c| c.name.clone()) else {
+            return self.numeric(id, Numeric::NoSuchChannel, &[name, "No such channel"]);
+        };
+        let line = format!(":{} KNOCK {channel_name} {reason}", self.prefix(id));
+        self.notify_hosts(&channel_name, &line);
+    }
+
+    fn notify_hosts(&mut self, name: &str, line: &str) {
+        let hosts: Vec<ClientId> = self
+            .channel(name)
+            .map(|c| {
+                c.members
+                    .iter()
+                    .filter(|(_, role)| **role >= UserRole::Host)
+                    .map(|(&member, _)| member)
+                    .collect()
+            })
+            .unwrap_or_default();
+        for host in hosts {
+            self.send(host, line);
+        }
+    }
+
+    fn op_needed(&mut self, id: ClientId, name: &str) {
+        self.numeric(
+            id,
+            Numeric::ChanOpPrivsNeeded,
+            &[name, "You're not channel operator"],
+        );
+    }
+
+    fn channel(&self, name: &str) -> Option<&Channel> {
+        self.channels.get(&name.to_lowercase())
+    }
+
+    fn channel_mut(&mut self, name: &str) -> Option<&mut Channel> {
+        self.channels.get_mut(&name.to_lowercase())
+    }
+
+    fn member_role(&self, id: ClientId, name: &str) -> Option<UserRole> {
+        self.channel(name)?.role(id)
+    }
+}
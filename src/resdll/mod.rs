@@ -0,0 +1,144 @@
+pub mod pe;
+pub mod string_table;
+
+use std::{collections::BTreeMap, fmt, path::Path};
+
+pub use pe::{PeImage, Resource, ResourceId};
+pub use string_table::StringTable;
+
+/// `RT_STRING` resource type ordinal.
+const RT_STRING: u16 = 6;
+
+/// Errors raised while loading a resource DLL.
+#[derive(Debug)]
+pub enum ResDllError {
+    Io(std::io::Error),
+    NotPe(&'static str),
+    Malformed(&'static str),
+    Truncated { offset: usize },
+}
+
+impl fmt::Display for ResDllError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResDllError::Io(err) => write!(f, "failed to read resource DLL: {err}"),
+            ResDllError::NotPe(why) => write!(f, "not a PE image: {why}"),
+            ResDllError::Malformed(why) => write!(f, "malformed resource section: {why}"),
+            ResDllError::Truncated { offset } => write!(f, "image truncated at offset {offset:#x}"),
+        }
+    }
+}
+
+impl std::error::Error for ResDllError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ResDllError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for ResDllError {
+    fn from(err: std::io::Error) -> Self {
+        ResDllError::Io(err)
+    }
+}
+
+/// A localized satellite DLL as referenced by the `ResDLL` property.
+#[derive(Debug, Clone, Default)]
+pub struct ResDll {
+    tables: BTreeMap<u16, StringTable>,
+}
+
+impl ResDll {
+    /// Loads and parses a resource DLL from disk.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ResDllError> {
+        let bytes = std::fs::read(path)?;
+        Self::parse(&bytes)
+    }
+
+    /// Parses a resource DLL already held in memory.
+    pub fn parse(bytes: &[u8]) -> Result<Self, ResDllError> {
+        let image = PeImage::parse(bytes)?;
+        let mut tables: BTreeMap<u16, StringTable> = BTreeMap::new();
+
+        for res in image.resources()? {
+            if res.kind != ResourceId::Ordinal(RT_STRING) {
+                continue;
+            }
+            let ResourceId::Ordinal(block) = res.name else {
+                continue;
+            };
+            tables
+                .entry(res.language)
+                .or_default()
+                .insert_block(block, res.data)?;
+        }
+
+        Ok(Self { tables })
+    }
+
+    /// LANGIDs that carry at least one string table block.
+    pub fn languages(&self) -> impl Iterator<Item = u16> + '_ {
+        self.tables.keys().copied()
+    }
+
+    /// Returns the string table for `langid`, if present.
+    pub fn string_table(&self, langid: u16) -> Option<&StringTable> {
+        self.tables.get(&langid)
+    }
+
+    /// Iterates over every `(langid, table)` pair.
+    pub fn string_tables(&self) -> impl Iterator<Item = (u16, &StringTable)> {
+        self.tables.iter().map(|(lang, table)| (*lang, table))
+    }
+
+    /// Looks up a single string by LANGID and string ID.
+    pub fn string(&self, langid: u16, id: u16) -> Option<&str> {
+        self.tables.get(&langid)?.get(id)
+    }
+
+    /// The LANGID with the most strings; satellites normally carry just one.
+    pub fn primary_language(&self) -> Option<u16> {
+        self.tables
+            .iter()
+            .max_by_key(|(lang, table)| (table.len(), std::cmp::Reverse(**lang)))
+            .map(|(lang, _)| *lang)
+    }
+
+    /// Compares `self` (the reference build) against a translated `other`,
+    /// using the primary language of each DLL.
+    pub fn diff(&self, other: &ResDll) -> StringTableDiff {
+        let empty = StringTable::default();
+        let reference = self.primary_language().and_then(|l| self.string_table(l));
+        let translated = other.primary_language().and_then(|l| other.string_table(l));
+        reference
+            .unwrap_or(&empty)
+            .diff(translated.unwrap_or(&empty))
+    }
+
+    /// Compares the `from` table of `self` against the `to` table of `other`.
+    ///
+    /// Pass the same DLL twice for multi-language images where the reference
+    /// language (e.g. `0x0409`) and its translations live side by side.
+    pub fn diff_languages(&self, from: u16, other: &ResDll, to: u16) -> Option<StringTableDiff> {
+        Some(self.tables.get(&from)?.diff(other.tables.get(&to)?))
+    }
+}
+
+/// Result of [`StringTable::diff`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StringTableDiff {
+    /// String IDs present in the reference but absent from the translation.
+    pub missing: Vec<u16>,
+    /// String IDs present only in the translation.
+    pub extra: Vec<u16>,
+    /// String IDs whose text is identical in both, i.e. likely untranslated.
+    pub unchanged: Vec<u16>,
+}
+
+impl StringTableDiff {
+    pub fn is_empty(&self) -> bool {
+        self.missing.is_empty() && self.extra.is_empty() && self.unchanged.is_empty()
+    }
+}
@@ -0,0 +1,372 @@
+use super::ResDllError;
+
+const IMAGE_DOS_SIGNATURE: u16 = 0x5a4d; // "MZ"
+const IMAGE_NT_SIGNATURE: u32 = 0x0000_4550; // "PE\0\0"
+const IMAGE_NT_OPTIONAL_HDR32_MAGIC: u16 = 0x10b;
+const IMAGE_NT_OPTIONAL_HDR64_MAGIC: u16 = 0x20b;
+const IMAGE_DIRECTORY_ENTRY_RESOURCE: usize = 2;
+const IMAGE_RESOURCE_DATA_IS_DIRECTORY: u32 = 0x8000_0000;
+const IMAGE_RESOURCE_NAME_IS_STRING: u32 = 0x8000_0000;
+
+/// Guards against malformed directories that loop back on themselves.
+const MAX_RESOURCE_ENTRIES: usize = 0x1_0000;
+
+/// A section header, reduced to the fields needed for RVA translation.
+#[derive(Debug, Clone, Copy)]
+struct Section {
+    virtual_address: u32,
+    virtual_size: u32,
+    raw_offset: u32,
+    raw_size: u32,
+}
+
+/// Identifies a resource directory entry by ordinal or by name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResourceId {
+    Ordinal(u16),
+    Name(String),
+}
+
+/// A leaf of the resource tree: `type / name / language → bytes`.
+#[derive(Debug, Clone)]
+pub struct Resource<'a> {
+    pub kind: ResourceId,
+    pub name: ResourceId,
+    pub language: u16,
+    pub data: &'a [u8],
+}
+
+/// A read-only view over a PE image on disk.
+pub struct PeImage<'a> {
+    bytes: &'a [u8],
+    sections: Vec<Section>,
+    resource_rva: u32,
+    resource_size: u32,
+}
+
+impl<'a> PeImage<'a> {
+    /// Parses the DOS, NT and section headers of `bytes`.
+    pub fn parse(bytes: &'a [u8]) -> Result<Self, ResDllError> {
+        if read_u16(bytes, 0)? != IMAGE_DOS_SIGNATURE {
+            return Err(ResDllError::NotPe("missing MZ signature"));
+        }
+
+        let nt = read_u32(bytes, 0x3c)? as usize;
+        if read_u32(bytes, nt)? != IMAGE_NT_SIGNATURE {
+            return Err(ResDllError::NotPe("missing PE signature"));
+        }
+
+        // IMAGE_FILE_HEADER
+        let file_header = nt + 4;
+        let section_count = read_u16(bytes, file_header + 2)? as usize;
+        let optional_size = read_u16(bytes, file_header + 16)? as usize;
+
+        // IMAGE_OPTIONAL_HEADER32/64
+        let optional = file_header + 20;
+        let data_directories = match read_u16(bytes, optional)? {
+            IMAGE_NT_OPTIONAL_HDR32_MAGIC => optional + 96,
+            IMAGE_NT_OPTIONAL_HDR64_MAGIC => optional + 112,
+            _ => return Err(ResDllError::NotPe("unknown optional header magic")),
+        };
+        let directory_count = read_u32(bytes, data_directories - 4)? as usize;
+
+        let (resource_rva, resource_size) = if directory_count > IMAGE_DIRECTORY_ENTRY_RESOURCE {
+            let entry = data_directories + IMAGE_DIRECTORY_ENTRY_RESOURCE * 8;
+            (read_u32(bytes, entry)?, read_u32(bytes, entry + 4)?)
+        } else {
+            (0, 0)
+        };
+
+        let mut sections = Vec::with_capacity(section_count);
+        let table = optional + optional_size;
+        for i in 0..section_count {
+            let header = table + i * 40;
+            sections.push(Section {
+                virtual_size: read_u32(bytes, header + 8)?,
+                virtual_address: read_u32(bytes, header + 12)?,
+                raw_size: read_u32(bytes, header + 16)?,
+                raw_offset: read_u32(bytes, header + 20)?,
+            });
+        }
+
+        Ok(Self {
+            bytes,
+            sections,
+            resource_rva,
+            resource_size,
+        })
+    }
+
+    /// Walks the three-level resource directory and returns every leaf.
+    pub fn resources(&self) -> Result<Vec<Resource<'a>>, ResDllError> {
+        if self.resource_rva == 0 || self.resource_size == 0 {
+            return Ok(Vec::new());
+        }
+
+        let root = self.rva_to_offset(self.resource_rva)?;
+        let mut out = Vec::new();
+        let mut visited = 0usize;
+
+        for (kind, kind_dir) in self.directory_entries(root, 0, &mut visited)? {
+            let Some(kind_dir) = kind_dir.subdirectory() else {
+                continue;
+            };
+            for (name, name_dir) in self.directory_entries(root, kind_dir, &mut visited)? {
+                let Some(name_dir) = name_dir.subdirectory() else {
+                    continue;
+                };
+                for (language, leaf) in self.directory_entries(root, name_dir, &mut visited)? {
+                    let EntryTarget::Data(leaf) = leaf else {
+                        continue;
+                    };
+                    let ResourceId::Ordinal(language) = language else {
+                        continue;
+                    };
+
+                    // IMAGE_RESOURCE_DATA_ENTRY
+                    let entry = root + leaf as usize;
+                    let data_rva = read_u32(self.bytes, entry)?;
+                    let data_size = read_u32(self.bytes, entry + 4)? as usize;
+                    let start = self.rva_to_offset(data_rva)?;
+                    let data = self
+                        .bytes
+                        .get(start..start.saturating_add(data_size))
+                        .ok_or(ResDllError::Truncated { offset: start })?;
+
+                    out.push(Resource {
+                        kind: kind.clone(),
+                        name: name.clone(),
+                        language,
+                        data,
+                    });
+                }
+            }
+        }
+
+        Ok(out)
+    }
+
+    fn directory_entries(
+        &self,
+        root: usize,
+        dir: u32,
+        visited: &mut usize,
+    ) -> Result<Vec<(ResourceId, EntryTarget)>, ResDllError> {
+        // IMAGE_RESOURCE_DIRECTORY
+        let dir = root + dir as usize;
+        let named = read_u16(self.bytes, dir + 12)? as usize;
+        let ids = read_u16(self.bytes, dir + 14)? as usize;
+
+        *visited += named + ids;
+        if *visited > MAX_RESOURCE_ENTRIES {
+            return Err(ResDllError::Malformed("resource directory too large"));
+        }
+
+        let mut entries = Vec::with_capacity(named + ids);
+        for i in 0..named + ids {
+            // IMAGE_RESOURCE_DIRECTORY_ENTRY
+            let entry = dir + 16 + i * 8;
+            let name = read_u32(self.bytes, entry)?;
+            let target = read_u32(self.bytes, entry + 4)?;
+
+            let id = if name & IMAGE_RESOURCE_NAME_IS_STRING != 0 {
+                let at = root + (name & !IMAGE_RESOURCE_NAME_IS_STRING) as usize;
+                let len = read_u16(self.bytes, at)? as usize;
+                ResourceId::Name(read_utf16(self.bytes, at + 2, len)?)
+            } else {
+                ResourceId::Ordinal(name as u16)
+            };
+
+            let target = if target & IMAGE_RESOURCE_DATA_IS_DIRECTORY != 0 {
+                EntryTarget::Directory(target & !IMAGE_RESOURCE_DATA_IS_DIRECTORY)
+            } else {
+                EntryTarget::Data(target)
+            };
+
+            entries.push((id, target));
+        }
+
+        Ok(entries)
+    }
+
+    fn rva_to_offset(&self, rva: u32) -> Result<usize, ResDllError> {
+        let section = self
+            .sections
+            .iter()
+            .find(|s| {
+                let size = s.virtual_size.max(s.raw_size);
+                rva >= s.virtual_address && rva < s.virtual_address.saturating_add(size)
+            })
+            .ok_or(ResDllError::Malformed("RVA outside of any section"))?;
+        (rva - section.virtual_address)
+            .checked_add(section.raw_offset)
+            .map(|offset| offset as usize)
+            .ok_or(ResDllError::Malformed("section data beyond 4 GiB"))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum EntryTarget {
+    Directory(u32),
+    Data(u32),
+}
+
+impl EntryTarget {
+    fn subdirectory(self) -> Option<u32> {
+        match self {
+            EntryTarget::Directory(offset) => Some(offset),
+            EntryTarget::Data(_) => None,
+        }
+    }
+}
+
+pub(crate) fn read_u16(bytes: &[u8], offset: usize) -> Result<u16, ResDllError> {
+    bytes
+        .get(offset..offset.saturating_add(2))
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+        .ok_or(ResDllError::Truncated { offset })
+}
+
+pub(crate) fn read_u32(bytes: &[u8], offset: usize) -> Result<u32, ResDllError> {
+    bytes
+        .get(offset..offset.saturating_add(4))
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or(ResDllError::Truncated { offset })
+}
+
+pub(crate) fn read_utf16(bytes: &[u8], offset: usize, len: usize) -> Result<String, ResDllError> {
+    let raw = bytes
+        .get(offset..offset.saturating_add(len * 2))
+        .ok_or(ResDllError::Truncated { offset })?;
+    let units: Vec<u16> = raw
+        .chunks_exact(2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+        .collect();
+    Ok(String::from_utf16_lossy(&units))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RSRC_RVA: u32 = 0x1000;
+    const RSRC_RAW: usize = 0x200;
+
+    fn put_u16(image: &mut [u8], offset: usize, value: u16) {
+        image[offset..offset + 2].copy_from_slice(&value.to_le_bytes());
+    }
+
+    fn put_u32(image: &mut [u8], offset: usize, value: u32) {
+        image[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+    }
+
+    /// A PE32 image with one `.rsrc` section holding `RCDATA / 1 / 0x409 → "test"`.
+    fn image() -> Vec<u8> {
+        let mut image = vec![0u8; RSRC_RAW + 0x60];
+        put_u16(&mut image, 0, IMAGE_DOS_SIGNATURE);
+        put_u32(&mut image, 0x3c, 0x40);
+        put_u32(&mut image, 0x40, IMAGE_NT_SIGNATURE);
+
+        let file_header = 0x44;
+        put_u16(&mut image, file_header + 2, 1);
+        put_u16(&mut image, file_header + 16, 0xe0);
+
+        let optional = file_header + 20;
+        put_u16(&mut image, optional, IMAGE_NT_OPTIONAL_HDR32_MAGIC);
+        put_u32(&mut image, optional + 92, 16);
+        let resource_dir = optional + 96 + IMAGE_DIRECTORY_ENTRY_RESOURCE * 8;
+        put_u32(&mut image, resource_dir, RSRC_RVA);
+        put_u32(&mut image, resource_dir + 4, 0x60);
+
+        let section = optional + 0xe0;
+        image[section..section + 5].copy_from_slice(b".rsrc");
+        put_u32(&mut image, section + 8, 0x60);
+        put_u32(&mut image, section + 12, RSRC_RVA);
+        put_u32(&mut image, section + 16, 0x60);
+        put_u32(&mut image, section + 20, RSRC_RAW as u32);
+
+        // Type, name and language directories, one ordinal entry each.
+        for (dir, id, target) in [
+            (0x00, 10, IMAGE_RESOURCE_DATA_IS_DIRECTORY | 0x18),
+            (0x18, 1, IMAGE_RESOURCE_DATA_IS_DIRECTORY | 0x30),
+            (0x30, 0x409, 0x48),
+        ] {
+            put_u16(&mut image, RSRC_RAW + dir + 14, 1);
+            put_u32(&mut image, RSRC_RAW + dir + 16, id);
+            put_u32(&mut image, RSRC_RAW + dir + 20, target);
+        }
+        put_u32(&mut image, RSRC_RAW + 0x48, RSRC_RVA + 0x58);
+        put_u32(&mut image, RSRC_RAW + 0x4c, 4);
+        image[RSRC_RAW + 0x58..RSRC_RAW + 0x5c].copy_from_slice(b"test");
+        image
+    }
+
+    #[test]
+    fn reads_resource_leaf() {
+        let image = image();
+        let resources = PeImage::parse(&image).unwrap().resources().unwrap();
+        assert_eq!(resources.len(), 1);
+        let resource = &resources[0];
+        assert_eq!(resource.kind, ResourceId::Ordinal(10));
+        assert_eq!(resource.name, ResourceId::Ordinal(1));
+        assert_eq!(resource.language, 0x409);
+        assert_eq!(resource.data, b"test");
+    }
+
+    #[test]
+    fn rejects_non_pe() {
+        assert!(matches!(
+            PeImage::parse(b"XXXX"),
+            Err(ResDllError::NotPe(_))
+        ));
+        let mut image = image();
+        put_u32(&mut image, 0x40, 0);
+        assert!(matches!(PeImage::parse(&image), Err(ResDllError::NotPe(_))));
+    }
+
+    #[test]
+    fn truncated_headers() {
+        let image = image();
+        assert!(matches!(
+            PeImage::parse(&image[..1]),
+            Err(ResDllError::Truncated { offset: 0 })
+        ));
+        assert!(matches!(
+            PeImage::parse(&image[..0x100]),
+            Err(ResDllError::Truncated { .. })
+        ));
+    }
+
+    #[test]
+    fn truncated_resource_data() {
+        let image = image();
+        let short = &image[..RSRC_RAW + 0x5a];
+        assert!(matches!(
+            PeImage::parse(short).unwrap().resources(),
+            Err(ResDllError::Truncated { .. })
+        ));
+    }
+
+    #[test]
+    fn data_outside_sections() {
+        let mut image = image();
+        put_u32(&mut image, RSRC_RAW + 0x48, 0x5000);
+        assert!(matches!(
+            PeImage::parse(&image).unwrap().resources(),
+            Err(ResDllError::Malformed(_))
+        ));
+    }
+
+    #[test]
+    fn raw_offset_overflow() {
+        let mut image = image();
+        let section = 0x58 + 0xe0;
+        put_u32(&mut image, section + 20, u32::MAX - 0x10);
+        // Point the resource directory past the wrap-around.
+        put_u32(&mut image, 0x58 + 96 + 16, RSRC_RVA + 0x20);
+        assert!(matches!(
+            PeImage::parse(&image).unwrap().resources(),
+            Err(ResDllError::Malformed(_))
+        ));
+    }
+}
@@ -0,0 +1,176 @@
+use std::collections::BTreeMap;
+
+use super::{
+    ResDllError, StringTableDiff,
+    pe::{read_u16, read_utf16},
+};
+
+/// Number of strings packed into a single `RT_STRING` block.
+const STRINGS_PER_BLOCK: u16 = 16;
+
+/// All strings of one language, keyed by string ID.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StringTable {
+    strings: BTreeMap<u16, String>,
+}
+
+impl StringTable {
+    /// Decodes one `RT_STRING` block.
+    ///
+    /// Block `n` holds string IDs `(n - 1) * 16 ..= (n - 1) * 16 + 15`, each stored
+    /// as a length-prefixed UTF-16 run; zero-length entries are unused slots.
+    pub(crate) fn insert_block(&mut self, block: u16, data: &[u8]) -> Result<(), ResDllError> {
+        if block == 0 {
+            return Err(ResDllError::Malformed("string block ordinal 0"));
+        }
+
+        let base = (block - 1)
+            .checked_mul(STRINGS_PER_BLOCK)
+            .ok_or(ResDllError::Malformed("string block ordinal beyond 4096"))?;
+        let mut offset = 0usize;
+
+        for slot in 0..STRINGS_PER_BLOCK {
+            let len = read_u16(data, offset)? as usize;
+            offset += 2;
+            if len > 0 {
+                let text = read_utf16(data, offset, len)?;
+                let id = base
+                    .checked_add(slot)
+                    .ok_or(ResDllError::Malformed("string ID beyond 65535"))?;
+                self.strings.insert(id, text);
+                offset += len * 2;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn get(&self, id: u16) -> Option<&str> {
+        self.strings.get(&id).map(String::as_str)
+    }
+
+    pub fn ids(&self) -> impl Iterator<Item = u16> + '_ {
+        self.strings.keys().copied()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (u16, &str)> {
+        self.strings.iter().map(|(id, s)| (*id, s.as_str()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+
+    /// Compares this (reference) table against a translated one.
+    pub fn diff(&self, translated: &StringTable) -> StringTableDiff {
+        let mut diff = StringTableDiff::default();
+
+        for (id, text) in &self.strings {
+            match translated.strings.get(id) {
+                None => diff.missing.push(*id),
+                Some(other) if other == text => diff.unchanged.push(*id),
+                Some(_) => {}
+            }
+        }
+
+        diff.extra = translated
+            .strings
+            .keys()
+            .filter(|id| !self.strings.contains_key(id))
+            .copied()
+            .collect();
+
+        diff
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A block with `strings` in its first slots and the rest unused.
+    fn block(strings: &[&str]) -> Vec<u8> {
+        let mut data = Vec::new();
+        for slot in 0..usize::from(STRINGS_PER_BLOCK) {
+            let units: Vec<u16> = strings
+                .get(slot)
+                .map_or(Vec::new(), |s| s.encode_utf16().collect());
+            data.extend_from_slice(&(units.len() as u16).to_le_bytes());
+            units
+                .iter()
+                .for_each(|u| data.extend_from_slice(&u.to_le_bytes()));
+        }
+        data
+    }
+
+    fn table(blocks: &[(u16, &[&str])]) -> StringTable {
+        let mut table = StringTable::default();
+        for (ordinal, strings) in blocks {
+            table.insert_block(*ordinal, &block(strings)).unwrap();
+        }
+        table
+    }
+
+    #[test]
+    fn block_ordinal_to_ids() {
+        let table = table(&[(1, &["zero", "", "two"]), (3, &["thirty-two"])]);
+        assert_eq!(table.ids().collect::<Vec<_>>(), [0, 2, 32]);
+        assert_eq!(table.get(2), Some("two"));
+        assert_eq!(table.get(32), Some("thirty-two"));
+        assert_eq!(table.get(1), None);
+
+        let mut last = StringTable::default();
+        let mut strings = vec![""; 16];
+        strings[15] = "last";
+        last.insert_block(4096, &block(&strings)).unwrap();
+        assert_eq!(last.get(u16::MAX), Some("last"));
+    }
+
+    #[test]
+    fn rejects_bad_ordinals() {
+        let mut table = StringTable::default();
+        assert!(matches!(
+            table.insert_block(0, &block(&[])),
+            Err(ResDllError::Malformed(_))
+        ));
+        assert!(matches!(
+            table.insert_block(4097, &block(&["wraps to 0"])),
+            Err(ResDllError::Malformed(_))
+        ));
+        assert!(table.is_empty());
+    }
+
+    #[test]
+    fn truncated_block() {
+        let mut data = block(&["abc"]);
+        data.truncate(6);
+        assert!(matches!(
+            StringTable::default().insert_block(1, &data),
+            Err(ResDllError::Truncated { offset: 2 })
+        ));
+    }
+
+    #[test]
+    fn diff_added_removed_changed() {
+        let reference = table(&[(1, &["OK", "Cancel", "Help"])]);
+        let translated = table(&[(1, &["OK", "Abbrechen"]), (2, &["Neu"])]);
+        let diff = reference.diff(&translated);
+        assert_eq!(
+            diff,
+            StringTableDiff {
+                missing: vec![2],
+                extra: vec![16],
+                unchanged: vec![0],
+            }
+        );
+        // Translated strings appear in no list.
+        assert!(!diff.unchanged.contains(&1) && !diff.missing.contains(&1));
+
+        let fully = table(&[(1, &["Gut", "Abbrechen", "Hilfe"])]);
+        assert!(reference.diff(&fully).is_empty());
+    }
+}
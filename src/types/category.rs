@@ -0,0 +1,221 @@
+use std::{convert::Infallible, fmt, str::FromStr};
+
+use super::{ChannelLanguage, Locale};
+
+/// Directory category a room is listed under, as passed to the frame's `Category` property.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Category {
+    CityChats,
+    Computing,
+    Entertainment,
+    General,
+    Health,
+    Interests,
+    Lifestyles,
+    Music,
+    News,
+    Peers,
+    Religion,
+    Romance,
+    Sports,
+    Teens,
+    Unlisted,
+    /// A code the catalog does not know; kept verbatim.
+    Unknown(String),
+}
+
+impl Category {
+    pub const ALL: [Category; 15] = [
+        Category::CityChats,
+        Category::Computing,
+        Category::Entertainment,
+        Category::General,
+        Category::Health,
+        Category::Interests,
+        Category::Lifestyles,
+        Category::Music,
+        Category::News,
+        Category::Peers,
+        Category::Religion,
+        Category::Romance,
+        Category::Sports,
+        Category::Teens,
+        Category::Unlisted,
+    ];
+
+    /// Two-letter directory code, e.g. `CP` for Computing.
+    pub fn code(&self) -> &str {
+        match self {
+            Category::CityChats => "GE",
+            Category::Computing => "CP",
+            Category::Entertainment => "EA",
+            Category::General => "GN",
+            Category::Health => "HE",
+            Category::Interests => "II",
+            Category::Lifestyles => "LF",
+            Category::Music => "MU",
+            Category::News => "NW",
+            Category::Peers => "PR",
+            Category::Religion => "RL",
+            Category::Romance => "RM",
+            Category::Sports => "SP",
+            Category::Teens => "TN",
+            Category::Unlisted => "UL",
+            Category::Unknown(code) => code,
+        }
+    }
+
+    /// Looks up a known category by its two-letter code (case-insensitive).
+    pub fn from_code(code: &str) -> Option<Self> {
+        let code = code.trim();
+        Self::ALL
+            .into_iter()
+            .find(|c| c.code().eq_ignore_ascii_case(code))
+    }
+
+    pub fn is_known(&self) -> bool {
+        !matches!(self, Category::Unknown(_))
+    }
+
+    /// Display name as shown in the directory of `locale`.
+    ///
+    /// Locales without a translated directory fall back to English; unknown
+    /// categories display their raw code.
+    pub fn display_name(&self, locale: Locale) -> &str {
+        let index = match Self::ALL.iter().position(|c| c == self) {
+            Some(index) => index,
+            None => return self.code(),
+        };
+        NAMES
+            .iter()
+            .find(|(lang, _)| *lang == locale.channel_language())
+            .map_or(&NAMES[0].1, |(_, names)| names)[index]
+    }
+}
+
+use ChannelLanguage as L;
+
+/// Display names indexed like [`Category::ALL`]; the first row is the fallback.
+#[rustfmt::skip]
+static NAMES: &[(ChannelLanguage, [&str; 15])] = &[
+    (L::English, [
+        "City Chats", "Computing", "Entertainment", "General", "Health", "Interests",
+        "Lifestyles", "Music", "News", "Peers", "Religion", "Romance",
+        "Sports & Recreation", "Teens", "Unlisted",
+    ]),
+    (L::French, [
+        "Villes", "Informatique", "Divertissement", "Général", "Santé", "Centres d'intérêt",
+        "Styles de vie", "Musique", "Actualités", "Entre nous", "Religion", "Rencontres",
+        "Sports et loisirs", "Ados", "Non répertorié",
+    ]),
+    (L::German, [
+        "Städte", "Computer", "Unterhaltung", "Allgemein", "Gesundheit", "Interessen",
+        "Lifestyle", "Musik", "Nachrichten", "Gleichgesinnte", "Religion", "Flirt",
+        "Sport & Freizeit", "Teens", "Nicht aufgeführt",
+    ]),
+    (L::Spanish, [
+        "Ciudades", "Informática", "Entretenimiento", "General", "Salud", "Aficiones",
+        "Estilos de vida", "Música", "Noticias", "Grupos", "Religión", "Romance",
+        "Deportes y ocio", "Adolescentes", "No listado",
+    ]),
+    (L::Italian, [
+        "Città", "Informatica", "Intrattenimento", "Generale", "Salute", "Interessi",
+        "Stili di vita", "Musica", "Notizie", "Gruppi", "Religione", "Romanticismo",
+        "Sport e tempo libero", "Teenager", "Non in elenco",
+    ]),
+    (L::Dutch, [
+        "Steden", "Computers", "Amusement", "Algemeen", "Gezondheid", "Interesses",
+        "Lifestyle", "Muziek", "Nieuws", "Leeftijdsgenoten", "Religie", "Romantiek",
+        "Sport & recreatie", "Tieners", "Niet vermeld",
+    ]),
+    (L::PortugueseBrazilian, [
+        "Cidades", "Informática", "Entretenimento", "Geral", "Saúde", "Interesses",
+        "Estilos de vida", "Música", "Notícias", "Grupos", "Religião", "Romance",
+        "Esportes e lazer", "Adolescentes", "Não listada",
+    ]),
+];
+
+impl fmt::Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+impl FromStr for Category {
+    type Err = Infallible;
+
+    /// Accepts a two-letter code or a display name in any catalogued language;
+    /// anything else becomes [`Category::Unknown`].
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(category) = Self::from_code(s) {
+            return Ok(category);
+        }
+
+        let name = s.trim();
+        let by_name = NAMES.iter().find_map(|(_, names)| {
+            names
+                .iter()
+                .position(|n| n.to_lowercase() == name.to_lowercase())
+                .map(|i| Self::ALL[i].clone())
+        });
+
+        Ok(by_name.unwrap_or_else(|| Category::Unknown(name.to_string())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn code_catalog() {
+        let mut codes: Vec<_> = Category::ALL.iter().map(Category::code).collect();
+        codes.sort_unstable();
+        codes.dedup();
+        assert_eq!(codes.len(), Category::ALL.len());
+
+        for category in Category::ALL {
+            assert!(category.is_known());
+            assert_eq!(Category::from_code(category.code()), Some(category.clone()));
+            assert_eq!(category.to_string().parse(), Ok(category));
+        }
+        assert_eq!(Category::from_code(" cp "), Some(Category::Computing));
+    }
+
+    #[test]
+    fn unknown_codes_are_kept() {
+        assert_eq!(Category::from_code("ZZ"), None);
+        let unknown: Category = "ZZ".parse().unwrap();
+        assert_eq!(unknown, Category::Unknown("ZZ".into()));
+        assert!(!unknown.is_known());
+        assert_eq!(unknown.code(), "ZZ");
+        assert_eq!(unknown.to_string(), "ZZ");
+        assert_eq!(unknown.display_name(Locale::EN_US), "ZZ");
+    }
+
+    #[test]
+    fn localized_names() {
+        let de: Locale = "de-at".parse().unwrap();
+        let ja: Locale = "ja-jp".parse().unwrap();
+        assert_eq!(Category::Romance.display_name(de), "Flirt");
+        assert_eq!(
+            Category::Sports.display_name(Locale::EN_US),
+            "Sports & Recreation"
+        );
+        // No Japanese directory names: English is the fallback.
+        assert_eq!(Category::Computing.display_name(ja), "Computing");
+
+        for (language, names) in NAMES {
+            let locale = Locale::all()
+                .find(|l| l.channel_language() == *language)
+                .unwrap();
+            for (category, name) in Category::ALL.iter().zip(names) {
+                assert_eq!(category.display_name(locale), *name);
+                assert_eq!(
+                    name.to_uppercase().parse::<Category>().as_ref(),
+                    Ok(category)
+                );
+            }
+        }
+    }
+}
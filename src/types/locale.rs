@@ -0,0 +1,487 @@
+use std::{fmt, str::FromStr};
+
+/// Error returned when a locale, market or channel language string is not recognised.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseLocaleError {
+    what: &'static str,
+    input: String,
+}
+
+impl ParseLocaleError {
+    fn new(what: &'static str, input: &str) -> Self {
+        Self {
+            what,
+            input: input.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ParseLocaleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown {} `{}`", self.what, self.input)
+    }
+}
+
+impl std::error::Error for ParseLocaleError {}
+
+/// Room language codes used by the MSN directory and the IRCX `LANGUAGE` property.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+#[repr(u8)]
+pub enum ChannelLanguage {
+    #[default]
+    English = 1,
+    French = 2,
+    German = 3,
+    Japanese = 4,
+    Swedish = 5,
+    Dutch = 6,
+    Korean = 7,
+    ChineseSimplified = 8,
+    Portuguese = 9,
+    Finnish = 10,
+    Danish = 11,
+    Russian = 12,
+    Italian = 13,
+    Norwegian = 14,
+    ChineseTraditional = 15,
+    Spanish = 16,
+    Czech = 17,
+    Greek = 18,
+    Hungarian = 19,
+    Polish = 20,
+    Slovene = 21,
+    Turkish = 22,
+    Slovak = 23,
+    PortugueseBrazilian = 24,
+}
+
+impl ChannelLanguage {
+    pub const ALL: [ChannelLanguage; 24] = [
+        ChannelLanguage::English,
+        ChannelLanguage::French,
+        ChannelLanguage::German,
+        ChannelLanguage::Japanese,
+        ChannelLanguage::Swedish,
+        ChannelLanguage::Dutch,
+        ChannelLanguage::Korean,
+        ChannelLanguage::ChineseSimplified,
+        ChannelLanguage::Portuguese,
+        ChannelLanguage::Finnish,
+        ChannelLanguage::Danish,
+        ChannelLanguage::Russian,
+        ChannelLanguage::Italian,
+        ChannelLanguage::Norwegian,
+        ChannelLanguage::ChineseTraditional,
+        ChannelLanguage::Spanish,
+        ChannelLanguage::Czech,
+        ChannelLanguage::Greek,
+        ChannelLanguage::Hungarian,
+        ChannelLanguage::Polish,
+        ChannelLanguage::Slovene,
+        ChannelLanguage::Turkish,
+        ChannelLanguage::Slovak,
+        ChannelLanguage::PortugueseBrazilian,
+    ];
+
+    /// Numeric code as sent to the server.
+    pub fn code(self) -> u8 {
+        self as u8
+    }
+
+    pub fn from_code(code: u8) -> Option<Self> {
+        Self::ALL.into_iter().find(|lang| lang.code() == code)
+    }
+
+    /// English display name as shown in the directory.
+    pub fn name(self) -> &'static str {
+        match self {
+            ChannelLanguage::English => "English",
+            ChannelLanguage::French => "French",
+            ChannelLanguage::German => "German",
+            ChannelLanguage::Japanese => "Japanese",
+            ChannelLanguage::Swedish => "Swedish",
+            ChannelLanguage::Dutch => "Dutch",
+            ChannelLanguage::Korean => "Korean",
+            ChannelLanguage::ChineseSimplified => "Chinese (Simplified)",
+            ChannelLanguage::Portuguese => "Portuguese",
+            ChannelLanguage::Finnish => "Finnish",
+            ChannelLanguage::Danish => "Danish",
+            ChannelLanguage::Russian => "Russian",
+            ChannelLanguage::Italian => "Italian",
+            ChannelLanguage::Norwegian => "Norwegian",
+            ChannelLanguage::ChineseTraditional => "Chinese (Traditional)",
+            ChannelLanguage::Spanish => "Spanish",
+            ChannelLanguage::Czech => "Czech",
+            ChannelLanguage::Greek => "Greek",
+            ChannelLanguage::Hungarian => "Hungarian",
+            ChannelLanguage::Polish => "Polish",
+            ChannelLanguage::Slovene => "Slovene",
+            ChannelLanguage::Turkish => "Turkish",
+            ChannelLanguage::Slovak => "Slovak",
+            ChannelLanguage::PortugueseBrazilian => "Portuguese (Brazil)",
+        }
+    }
+}
+
+impl fmt::Display for ChannelLanguage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.code())
+    }
+}
+
+impl FromStr for ChannelLanguage {
+    type Err = ParseLocaleError;
+
+    /// Accepts the numeric code, the display name or a locale tag.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Ok(code) = s.parse::<u8>() {
+            return Self::from_code(code)
+                .ok_or_else(|| ParseLocaleError::new("channel language", s));
+        }
+        if let Some(lang) = Self::ALL
+            .into_iter()
+            .find(|l| l.name().eq_ignore_ascii_case(s))
+        {
+            return Ok(lang);
+        }
+        s.parse::<Locale>()
+            .map(Locale::channel_language)
+            .map_err(|_| ParseLocaleError::new("channel language", s))
+    }
+}
+
+struct LocaleInfo {
+    tag: &'static str,
+    lcid: u16,
+    name: &'static str,
+    language: ChannelLanguage,
+    /// Market served for this locale; itself when MSN ran a directory for it.
+    market: &'static str,
+}
+
+use ChannelLanguage as L;
+
+#[rustfmt::skip]
+static LOCALES: &[LocaleInfo] = &[
+    LocaleInfo { tag: "en-us", lcid: 0x0409, name: "English (United States)", language: L::English, market: "en-us" },
+    LocaleInfo { tag: "en-gb", lcid: 0x0809, name: "English (United Kingdom)", language: L::English, market: "en-gb" },
+    LocaleInfo { tag: "en-ca", lcid: 0x1009, name: "English (Canada)", language: L::English, market: "en-ca" },
+    LocaleInfo { tag: "en-au", lcid: 0x0c09, name: "English (Australia)", language: L::English, market: "en-au" },
+    LocaleInfo { tag: "en-nz", lcid: 0x1409, name: "English (New Zealand)", language: L::English, market: "en-nz" },
+    LocaleInfo { tag: "en-ie", lcid: 0x1809, name: "English (Ireland)", language: L::English, market: "en-ie" },
+    LocaleInfo { tag: "en-za", lcid: 0x1c09, name: "English (South Africa)", language: L::English, market: "en-za" },
+    LocaleInfo { tag: "en-in", lcid: 0x4009, name: "English (India)", language: L::English, market: "en-in" },
+    LocaleInfo { tag: "en-sg", lcid: 0x4809, name: "English (Singapore)", language: L::English, market: "en-sg" },
+    LocaleInfo { tag: "fr-fr", lcid: 0x040c, name: "French (France)", language: L::French, market: "fr-fr" },
+    LocaleInfo { tag: "fr-ca", lcid: 0x0c0c, name: "French (Canada)", language: L::French, market: "fr-ca" },
+    LocaleInfo { tag: "fr-be", lcid: 0x080c, name: "French (Belgium)", language: L::French, market: "fr-be" },
+    LocaleInfo { tag: "fr-ch", lcid: 0x100c, name: "French (Switzerland)", language: L::French, market: "fr-ch" },
+    LocaleInfo { tag: "de-de", lcid: 0x0407, name: "German (Germany)", language: L::German, market: "de-de" },
+    LocaleInfo { tag: "de-at", lcid: 0x0c07, name: "German (Austria)", language: L::German, market: "de-at" },
+    LocaleInfo { tag: "de-ch", lcid: 0x0807, name: "German (Switzerland)", language: L::German, market: "de-ch" },
+    LocaleInfo { tag: "it-it", lcid: 0x0410, name: "Italian (Italy)", language: L::Italian, market: "it-it" },
+    LocaleInfo { tag: "es-es", lcid: 0x0c0a, name: "Spanish (Spain)", language: L::Spanish, market: "es-es" },
+    LocaleInfo { tag: "es-mx", lcid: 0x080a, name: "Spanish (Mexico)", language: L::Spanish, market: "es-mx" },
+    LocaleInfo { tag: "es-ar", lcid: 0x2c0a, name: "Spanish (Argentina)", language: L::Spanish, market: "es-ar" },
+    LocaleInfo { tag: "es-us", lcid: 0x540a, name: "Spanish (United States)", language: L::Spanish, market: "es-us" },
+    LocaleInfo { tag: "nl-nl", lcid: 0x0413, name: "Dutch (Netherlands)", language: L::Dutch, market: "nl-nl" },
+    LocaleInfo { tag: "nl-be", lcid: 0x0813, name: "Dutch (Belgium)", language: L::Dutch, market: "nl-be" },
+    LocaleInfo { tag: "sv-se", lcid: 0x041d, name: "Swedish (Sweden)", language: L::Swedish, market: "sv-se" },
+    LocaleInfo { tag: "da-dk", lcid: 0x0406, name: "Danish (Denmark)", language: L::Danish, market: "da-dk" },
+    LocaleInfo { tag: "nb-no", lcid: 0x0414, name: "Norwegian (Norway)", language: L::Norwegian, market: "nb-no" },
+    LocaleInfo { tag: "fi-fi", lcid: 0x040b, name: "Finnish (Finland)", language: L::Finnish, market: "fi-fi" },
+    LocaleInfo { tag: "pt-br", lcid: 0x0416, name: "Portuguese (Brazil)", language: L::PortugueseBrazilian, market: "pt-br" },
+    LocaleInfo { tag: "pt-pt", lcid: 0x0816, name: "Portuguese (Portugal)", language: L::Portuguese, market: "pt-br" },
+    LocaleInfo { tag: "ja-jp", lcid: 0x0411, name: "Japanese (Japan)", language: L::Japanese, market: "ja-jp" },
+    LocaleInfo { tag: "ko-kr", lcid: 0x0412, name: "Korean (Korea)", language: L::Korean, market: "ko-kr" },
+    LocaleInfo { tag: "zh-tw", lcid: 0x0404, name: "Chinese (Taiwan)", language: L::ChineseTraditional, market: "zh-tw" },
+    LocaleInfo { tag: "zh-hk", lcid: 0x0c04, name: "Chinese (Hong Kong)", language: L::ChineseTraditional, market: "zh-hk" },
+    LocaleInfo { tag: "zh-cn", lcid: 0x0804, name: "Chinese (PRC)", language: L::ChineseSimplified, market: "zh-cn" },
+    LocaleInfo { tag: "ru-ru", lcid: 0x0419, name: "Russian (Russia)", language: L::Russian, market: "en-us" },
+    LocaleInfo { tag: "pl-pl", lcid: 0x0415, name: "Polish (Poland)", language: L::Polish, market: "en-us" },
+    LocaleInfo { tag: "cs-cz", lcid: 0x0405, name: "Czech (Czech Republic)", language: L::Czech, market: "en-us" },
+    LocaleInfo { tag: "hu-hu", lcid: 0x040e, name: "Hungarian (Hungary)", language: L::Hungarian, market: "en-us" },
+    LocaleInfo { tag: "el-gr", lcid: 0x0408, name: "Greek (Greece)", language: L::Greek, market: "en-us" },
+    LocaleInfo { tag: "tr-tr", lcid: 0x041f, name: "Turkish (Turkey)", language: L::Turkish, market: "en-us" },
+    LocaleInfo { tag: "sk-sk", lcid: 0x041b, name: "Slovak (Slovakia)", language: L::Slovak, market: "en-us" },
+    LocaleInfo { tag: "sl-si", lcid: 0x0424, name: "Slovene (Slovenia)", language: L::Slovene, market: "en-us" },
+];
+
+/// Legacy spellings still found in old frame pages.
+static ALIASES: &[(&str, &str)] = &[("no-no", "nb-no"), ("en-uk", "en-gb"), ("es-xl", "es-mx")];
+
+fn find_tag(tag: &str) -> Option<usize> {
+    let tag = tag.trim().replace('_', "-").to_ascii_lowercase();
+    let tag = ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == tag)
+        .map_or(tag.as_str(), |(_, canonical)| canonical);
+    LOCALES.iter().position(|info| info.tag == tag)
+}
+
+/// A UI locale such as `en-us`, as accepted by the frame's `Locale` property.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Locale(u8);
+
+impl Locale {
+    pub const EN_US: Locale = Locale(0);
+
+    fn info(self) -> &'static LocaleInfo {
+        &LOCALES[self.0 as usize]
+    }
+
+    /// Every locale known to the directory.
+    pub fn all() -> impl Iterator<Item = Locale> {
+        (0..LOCALES.len()).map(|i| Locale(i as u8))
+    }
+
+    /// Lower-case `language-region` tag, e.g. `en-us`.
+    pub fn tag(self) -> &'static str {
+        self.info().tag
+    }
+
+    pub fn name(self) -> &'static str {
+        self.info().name
+    }
+
+    /// Windows locale identifier, e.g. `0x0409` for `en-us`.
+    pub fn lcid(self) -> u32 {
+        self.info().lcid as u32
+    }
+
+    /// Looks up a locale by LCID; sort-order bits are ignored.
+    pub fn from_lcid(lcid: u32) -> Option<Self> {
+        let langid = match (lcid & 0xffff) as u16 {
+            0x040a => 0x0c0a, // es-es, traditional sort
+            langid => langid,
+        };
+        LOCALES
+            .iter()
+            .position(|info| info.lcid == langid)
+            .map(|i| Locale(i as u8))
+    }
+
+    pub fn channel_language(self) -> ChannelLanguage {
+        self.info().language
+    }
+
+    /// The market whose directory serves this locale.
+    pub fn market(self) -> Market {
+        let index = find_tag(self.info().market).expect("market table refers to a known locale");
+        Market(Locale(index as u8))
+    }
+}
+
+impl Default for Locale {
+    fn default() -> Self {
+        Locale::EN_US
+    }
+}
+
+impl fmt::Display for Locale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.tag())
+    }
+}
+
+impl FromStr for Locale {
+    type Err = ParseLocaleError;
+
+    /// Accepts `en-us`, `EN-US` and `en_US`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        find_tag(s)
+            .map(|i| Locale(i as u8))
+            .ok_or_else(|| ParseLocaleError::new("locale", s))
+    }
+}
+
+/// An MSN market (`mkt=`) that ran its own chat directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Market(Locale);
+
+impl Market {
+    pub const EN_US: Market = Market(Locale::EN_US);
+
+    /// Every market that ran a directory.
+    pub fn all() -> impl Iterator<Item = Market> {
+        Locale::all().filter_map(Market::from_locale)
+    }
+
+    /// Returns `Some` only when `locale` is itself a market.
+    pub fn from_locale(locale: Locale) -> Option<Self> {
+        (locale.info().market == locale.tag()).then_some(Market(locale))
+    }
+
+    pub fn tag(self) -> &'static str {
+        self.0.tag()
+    }
+
+    pub fn locale(self) -> Locale {
+        self.0
+    }
+}
+
+impl Default for Market {
+    fn default() -> Self {
+        Market::EN_US
+    }
+}
+
+impl fmt::Display for Market {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.tag())
+    }
+}
+
+impl FromStr for Market {
+    type Err = ParseLocaleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        find_tag(s)
+            .and_then(|i| Market::from_locale(Locale(i as u8)))
+            .ok_or_else(|| ParseLocaleError::new("market", s))
+    }
+}
+
+/// The `Locale`, `Market` and `ChannelLanguage` trio of a frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct LocaleSettings {
+    pub locale: Locale,
+    pub market: Market,
+    pub channel_language: ChannelLanguage,
+}
+
+impl LocaleSettings {
+    /// Derives a consistent trio from a single locale.
+    pub fn from_locale(locale: Locale) -> Self {
+        Self {
+            locale,
+            market: locale.market(),
+            channel_language: locale.channel_language(),
+        }
+    }
+
+    /// Parses the three raw frame strings and checks that they agree.
+    pub fn parse(
+        locale: &str,
+        market: &str,
+        channel_language: &str,
+    ) -> Result<Self, ParseLocaleError> {
+        let settings = Self {
+            locale: locale.parse()?,
+            market: market.parse()?,
+            channel_language: channel_language.parse()?,
+        };
+        if settings.is_consistent() {
+            Ok(settings)
+        } else {
+            Err(ParseLocaleError::new(
+                "locale combination",
+                &format!("{locale}/{market}/{channel_language}"),
+            ))
+        }
+    }
+
+    /// True when the market and channel language are the ones `locale` implies.
+    pub fn is_consistent(&self) -> bool {
+        *self == Self::from_locale(self.locale)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locale_round_trips() {
+        for locale in Locale::all() {
+            assert_eq!(locale.tag().parse(), Ok(locale));
+            assert_eq!(locale.to_string(), locale.tag());
+            assert_eq!(Locale::from_lcid(locale.lcid()), Some(locale));
+        }
+        assert_eq!(Locale::all().count(), LOCALES.len());
+    }
+
+    #[test]
+    fn locale_spellings() {
+        let de: Locale = "de-de".parse().unwrap();
+        assert_eq!("DE_de".parse(), Ok(de));
+        assert_eq!(" de-DE ".parse(), Ok(de));
+        assert_eq!("no-no".parse::<Locale>().unwrap().tag(), "nb-no");
+        assert_eq!("en-uk".parse::<Locale>().unwrap().tag(), "en-gb");
+        assert_eq!(
+            "xx-yy".parse::<Locale>(),
+            Err(ParseLocaleError::new("locale", "xx-yy"))
+        );
+        assert_eq!(
+            "xx-yy".parse::<Locale>().unwrap_err().to_string(),
+            "unknown locale `xx-yy`"
+        );
+    }
+
+    #[test]
+    fn lcid_lookup() {
+        assert_eq!(Locale::from_lcid(0x0409), Some(Locale::EN_US));
+        // Sort-order bits and the traditional Spanish sort map to es-es.
+        assert_eq!(Locale::from_lcid(0x1_0407).unwrap().tag(), "de-de");
+        assert_eq!(Locale::from_lcid(0x040a).unwrap().tag(), "es-es");
+        assert_eq!(Locale::from_lcid(0x0000), None);
+    }
+
+    #[test]
+    fn markets() {
+        for market in Market::all() {
+            assert_eq!(market.tag().parse(), Ok(market));
+            assert_eq!(market.to_string(), market.tag());
+            assert_eq!(market.locale().market(), market);
+        }
+        let ru: Locale = "ru-ru".parse().unwrap();
+        assert_eq!(ru.market(), Market::EN_US);
+        assert_eq!(Market::from_locale(ru), None);
+        assert_eq!(
+            "ru-ru".parse::<Market>(),
+            Err(ParseLocaleError::new("market", "ru-ru"))
+        );
+        let pt: Locale = "pt-pt".parse().unwrap();
+        assert_eq!(pt.market().tag(), "pt-br");
+    }
+
+    #[test]
+    fn channel_languages() {
+        for language in ChannelLanguage::ALL {
+            assert_eq!(language.to_string().parse(), Ok(language));
+            assert_eq!(language.name().parse(), Ok(language));
+            assert_eq!(ChannelLanguage::from_code(language.code()), Some(language));
+        }
+        assert_eq!(
+            "chinese (simplified)".parse(),
+            Ok(ChannelLanguage::ChineseSimplified)
+        );
+        assert_eq!("ja-jp".parse(), Ok(ChannelLanguage::Japanese));
+        assert_eq!(ChannelLanguage::from_code(0), None);
+        assert_eq!(
+            "25".parse::<ChannelLanguage>(),
+            Err(ParseLocaleError::new("channel language", "25"))
+        );
+        assert!("Klingon".parse::<ChannelLanguage>().is_err());
+    }
+
+    #[test]
+    fn settings_consistency() {
+        let settings = LocaleSettings::parse("fr-ca", "fr-ca", "2").unwrap();
+        assert_eq!(
+            settings,
+            LocaleSettings::from_locale("fr-ca".parse().unwrap())
+        );
+        assert_eq!(
+            LocaleSettings::parse("sv-se", "en-us", "Swedish"),
+            Err(ParseLocaleError::new(
+                "locale combination",
+                "sv-se/en-us/Swedish"
+            ))
+        );
+        assert!(LocaleSettings::parse("ru-ru", "en-us", "Russian").is_ok());
+        assert!(LocaleSettings::default().is_consistent());
+    }
+}
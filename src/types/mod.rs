@@ -0,0 +1,9 @@
+pub mod category;
+pub mod locale;
+pub mod modes;
+pub mod role;
+
+pub use category::Category;
+pub use locale::{ChannelLanguage, Locale, LocaleSettings, Market, ParseLocaleError};
+pub use modes::{ChannelMode, ChannelModes, CreationModes, ModeError};
+pub use role::{ParseUserRoleError, Privilege, Privileges, UserRole};
@@ -0,0 +1,446 @@
+use std::{collections::BTreeSet, fmt, str::FromStr};
+
+use super::ChannelLanguage;
+
+/// Upper bound accepted for the `l` (user limit) mode.
+pub const MAX_USER_LIMIT: u16 = 500;
+
+/// Errors raised while parsing or validating channel modes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ModeError {
+    UnknownMode(char),
+    MissingArgument(char),
+    InvalidLimit(String),
+    InvalidKey(String),
+    InvalidLanguage(String),
+    UnexpectedArgument(String),
+    /// Two modes that may not be set together, e.g. `p` and `s`.
+    Conflict(char, char),
+}
+
+impl fmt::Display for ModeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ModeError::UnknownMode(c) => write!(f, "unknown channel mode `{c}`"),
+            ModeError::MissingArgument(c) => write!(f, "mode `{c}` requires an argument"),
+            ModeError::InvalidLimit(s) => write!(f, "invalid user limit `{s}`"),
+            ModeError::InvalidKey(s) => write!(f, "invalid member key `{s}`"),
+            ModeError::InvalidLanguage(s) => write!(f, "invalid channel language `{s}`"),
+            ModeError::UnexpectedArgument(s) => write!(f, "unexpected mode argument `{s}`"),
+            ModeError::Conflict(a, b) => write!(f, "modes `{a}` and `{b}` cannot be combined"),
+        }
+    }
+}
+
+impl std::error::Error for ModeError {}
+
+/// A parameterless IRCX channel mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ChannelMode {
+    InviteOnly,
+    Hidden,
+    Moderated,
+    NoExternal,
+    Private,
+    Secret,
+    TopicOp,
+    Knock,
+    NoWhisper,
+    Auditorium,
+    /// MSN servers treat `f` as the profanity filter.
+    ProfanityFilter,
+    RegisteredOnly,
+}
+
+impl ChannelMode {
+    pub const ALL: [ChannelMode; 12] = [
+        ChannelMode::InviteOnly,
+        ChannelMode::Hidden,
+        ChannelMode::Moderated,
+        ChannelMode::NoExternal,
+        ChannelMode::Private,
+        ChannelMode::Secret,
+        ChannelMode::TopicOp,
+        ChannelMode::Knock,
+        ChannelMode::NoWhisper,
+        ChannelMode::Auditorium,
+        ChannelMode::ProfanityFilter,
+        ChannelMode::RegisteredOnly,
+    ];
+
+    pub fn letter(self) -> char {
+        match self {
+            ChannelMode::InviteOnly => 'i',
+            ChannelMode::Hidden => 'h',
+            ChannelMode::Moderated => 'm',
+            ChannelMode::NoExternal => 'n',
+            ChannelMode::Private => 'p',
+            ChannelMode::Secret => 's',
+            ChannelMode::TopicOp => 't',
+            ChannelMode::Knock => 'u',
+            ChannelMode::NoWhisper => 'w',
+            ChannelMode::Auditorium => 'x',
+            ChannelMode::ProfanityFilter => 'f',
+            ChannelMode::RegisteredOnly => 'r',
+        }
+    }
+
+    pub fn from_letter(c: char) -> Option<Self> {
+        Self::ALL.into_iter().find(|m| m.letter() == c)
+    }
+}
+
+/// The visibility modes; at most one may be set.
+const VISIBILITY: [ChannelMode; 3] = [
+    ChannelMode::Private,
+    ChannelMode::Hidden,
+    ChannelMode::Secret,
+];
+
+/// A set of channel modes with their `k` (member key) and `l` (limit) arguments.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ChannelModes {
+    pub flags: BTreeSet<ChannelMode>,
+    pub member_key: Option<String>,
+    pub user_limit: Option<u16>,
+}
+
+impl ChannelModes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with(mut self, mode: ChannelMode) -> Self {
+        self.flags.insert(mode);
+        self
+    }
+
+    pub fn with_limit(mut self, limit: u16) -> Self {
+        self.user_limit = Some(limit);
+        self
+    }
+
+    pub fn with_key(mut self, key: impl Into<String>) -> Self {
+        self.member_key = Some(key.into());
+        self
+    }
+
+    pub fn contains(&self, mode: ChannelMode) -> bool {
+        self.flags.contains(&mode)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.flags.is_empty() && self.member_key.is_none() && self.user_limit.is_none()
+    }
+
+    /// Checks argument formats and mutually exclusive modes.
+    pub fn validate(&self) -> Result<(), ModeError> {
+        let visible: Vec<_> = VISIBILITY.iter().filter(|m| self.contains(**m)).collect();
+        if let [a, b, ..] = visible.as_slice() {
+            return Err(ModeError::Conflict(a.letter(), b.letter()));
+        }
+        if let Some(limit) = self.user_limit
+            && (limit == 0 || limit > MAX_USER_LIMIT)
+        {
+            return Err(ModeError::InvalidLimit(limit.to_string()));
+        }
+        if let Some(key) = &self.member_key {
+            validate_key(key)?;
+        }
+        Ok(())
+    }
+
+    /// Mode letters without arguments, e.g. `+mntl`.
+    pub fn letters(&self) -> String {
+        let mut out = String::from("+");
+        out.extend(self.flags.iter().map(|m| m.letter()));
+        if self.user_limit.is_some() {
+            out.push('l');
+        }
+        if self.member_key.is_some() {
+            out.push('k');
+        }
+        out
+    }
+
+    /// Mode arguments in the order their letters appear in [`ChannelModes::letters`].
+    pub fn arguments(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if let Some(limit) = self.user_limit {
+            args.push(limit.to_string());
+        }
+        if let Some(key) = &self.member_key {
+            args.push(key.clone());
+        }
+        args
+    }
+
+    /// Parses a mode string followed by its arguments, consuming only what it needs.
+    ///
+    /// Returns the parsed modes and any arguments left over.
+    pub fn parse_with_args<'a, I>(letters: &str, args: I) -> Result<(Self, Vec<&'a str>), ModeError>
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        let mut args = args.into_iter();
+        let mut modes = Self::default();
+
+        for c in letters.trim_start_matches('+').chars() {
+            match c {
+                'l' => {
+                    let arg = args.next().ok_or(ModeError::MissingArgument('l'))?;
+                    let limit = arg
+                        .parse()
+                        .map_err(|_| ModeError::InvalidLimit(arg.to_string()))?;
+                    modes.user_limit = Some(limit);
+                }
+                'k' => {
+                    let arg = args.next().ok_or(ModeError::MissingArgument('k'))?;
+                    modes.member_key = Some(arg.to_string());
+                }
+                c => {
+                    let mode = ChannelMode::from_letter(c).ok_or(ModeError::UnknownMode(c))?;
+                    modes.flags.insert(mode);
+                }
+            }
+        }
+
+        Ok((modes, args.collect()))
+    }
+}
+
+impl fmt::Display for ChannelModes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.letters())?;
+        for arg in self.arguments() {
+            write!(f, " {arg}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for ChannelModes {
+    type Err = ModeError;
+
+    /// Parses and validates a mode string with its arguments, e.g. `+mntl 50`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut tokens = s.split_whitespace();
+        let letters = tokens.next().unwrap_or("");
+        let (modes, rest) = Self::parse_with_args(letters, tokens)?;
+        if let Some(extra) = rest.first() {
+            return Err(ModeError::UnexpectedArgument(extra.to_string()));
+        }
+        modes.validate()?;
+        Ok(modes)
+    }
+}
+
+fn validate_key(key: &str) -> Result<(), ModeError> {
+    let valid = !key.is_empty()
+        && key.len() <= 31
+        && key.bytes().all(|b| b.is_ascii_graphic() && b != b',');
+    if valid {
+        Ok(())
+    } else {
+        Err(ModeError::InvalidKey(key.to_string()))
+    }
+}
+
+/// The frame's `CreationModes` value: modes applied when `CreateRoom` creates a room.
+///
+/// Serialized as `<letters> [limit] [key] [language]`, e.g. `+mntl 50 1`; the
+/// optional trailing language is the numeric [`ChannelLanguage`] code.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct CreationModes {
+    pub modes: ChannelModes,
+    pub language: Option<ChannelLanguage>,
+}
+
+impl CreationModes {
+    pub fn new(modes: ChannelModes) -> Self {
+        Self {
+            modes,
+            language: None,
+        }
+    }
+
+    pub fn with_language(mut self, language: ChannelLanguage) -> Self {
+        self.language = Some(language);
+        self
+    }
+
+    pub fn moderated(&self) -> bool {
+        self.modes.contains(ChannelMode::Moderated)
+    }
+
+    pub fn private(&self) -> bool {
+        self.modes.contains(ChannelMode::Private)
+    }
+
+    pub fn hidden(&self) -> bool {
+        self.modes.contains(ChannelMode::Hidden)
+    }
+
+    pub fn secret(&self) -> bool {
+        self.modes.contains(ChannelMode::Secret)
+    }
+
+    pub fn profanity_filter(&self) -> bool {
+        self.modes.contains(ChannelMode::ProfanityFilter)
+    }
+
+    pub fn max_users(&self) -> Option<u16> {
+        self.modes.user_limit
+    }
+
+    pub fn validate(&self) -> Result<(), ModeError> {
+        self.modes.validate()
+    }
+
+    /// Mode parameters of an IRCX `CREATE <channel> <modes> [args...] [language]`
+    /// command.
+    pub fn create_params(&self) -> Vec<String> {
+        let mut params = vec![self.modes.letters()];
+        params.extend(self.modes.arguments());
+        params.extend(self.language.map(|l| l.to_string()));
+        params
+    }
+}
+
+impl fmt::Display for CreationModes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.modes)?;
+        if let Some(language) = self.language {
+            write!(f, " {language}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for CreationModes {
+    type Err = ModeError;
+
+    /// Parses and validates a `CreationModes` string.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut tokens = s.split_whitespace();
+        let letters = tokens.next().unwrap_or("");
+        let (modes, rest) = ChannelModes::parse_with_args(letters, tokens)?;
+
+        let language = match rest.as_slice() {
+            [] => None,
+            [code] => Some(
+                code.parse::<u8>()
+                    .ok()
+                    .and_then(ChannelLanguage::from_code)
+                    .ok_or_else(|| ModeError::InvalidLanguage(code.to_string()))?,
+            ),
+            [_, extra, ..] => return Err(ModeError::UnexpectedArgument(extra.to_string())),
+        };
+
+        let creation = Self { modes, language };
+        creation.validate()?;
+        Ok(creation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn letters_round_trip() {
+        for mode in ChannelMode::ALL {
+            assert_eq!(ChannelMode::from_letter(mode.letter()), Some(mode));
+        }
+        assert_eq!(ChannelMode::from_letter('l'), None);
+        assert_eq!(ChannelMode::from_letter('k'), None);
+    }
+
+    #[test]
+    fn modes_round_trip() {
+        let modes: ChannelModes = "+nmlk 50 secret".parse().unwrap();
+        assert_eq!(
+            modes,
+            ChannelModes::new()
+                .with(ChannelMode::Moderated)
+                .with(ChannelMode::NoExternal)
+                .with_limit(50)
+                .with_key("secret")
+        );
+        assert_eq!(modes.to_string(), "+mnlk 50 secret");
+        assert_eq!(modes.to_string().parse(), Ok(modes));
+
+        // Arguments are taken in letter order.
+        let modes: ChannelModes = "+kl secret 50".parse().unwrap();
+        assert_eq!(modes.to_string(), "+lk 50 secret");
+
+        let empty: ChannelModes = "".parse().unwrap();
+        assert!(empty.is_empty());
+        assert_eq!(empty.to_string(), "+");
+    }
+
+    #[test]
+    fn parse_with_args_leaves_the_rest() {
+        let (modes, rest) = ChannelModes::parse_with_args("+tl", ["10", "Alice", "Bob"]).unwrap();
+        assert_eq!(
+            modes,
+            ChannelModes::new()
+                .with(ChannelMode::TopicOp)
+                .with_limit(10)
+        );
+        assert_eq!(rest, ["Alice", "Bob"]);
+    }
+
+    #[test]
+    fn mode_errors() {
+        let parse = |s: &str| s.parse::<ChannelModes>();
+        assert_eq!(parse("+mq"), Err(ModeError::UnknownMode('q')));
+        assert_eq!(parse("+ml"), Err(ModeError::MissingArgument('l')));
+        assert_eq!(parse("+k"), Err(ModeError::MissingArgument('k')));
+        assert_eq!(
+            parse("+l many"),
+            Err(ModeError::InvalidLimit("many".into()))
+        );
+        assert_eq!(parse("+l 0"), Err(ModeError::InvalidLimit("0".into())));
+        assert_eq!(parse("+l 501"), Err(ModeError::InvalidLimit("501".into())));
+        assert_eq!(parse("+k a,b"), Err(ModeError::InvalidKey("a,b".into())));
+        assert_eq!(
+            parse("+m 50"),
+            Err(ModeError::UnexpectedArgument("50".into()))
+        );
+        assert_eq!(parse("+ps"), Err(ModeError::Conflict('p', 's')));
+        assert_eq!(parse("+hs"), Err(ModeError::Conflict('h', 's')));
+        assert_eq!(
+            ModeError::Conflict('p', 's').to_string(),
+            "modes `p` and `s` cannot be combined"
+        );
+    }
+
+    #[test]
+    fn creation_modes() {
+        let creation: CreationModes = "+mfl 50 3".parse().unwrap();
+        assert!(creation.moderated() && creation.profanity_filter());
+        assert!(!creation.private() && !creation.hidden() && !creation.secret());
+        assert_eq!(creation.max_users(), Some(50));
+        assert_eq!(creation.language.map(ChannelLanguage::code), Some(3));
+        assert_eq!(creation.to_string(), "+mfl 50 3");
+        assert_eq!(creation.create_params(), ["+mfl", "50", "3"]);
+        assert_eq!(creation.to_string().parse(), Ok(creation));
+
+        let plain = CreationModes::default();
+        assert_eq!(plain.create_params(), ["+"]);
+
+        assert_eq!(
+            "+m 99".parse::<CreationModes>(),
+            Err(ModeError::InvalidLanguage("99".into()))
+        );
+        assert_eq!(
+            "+m 1 2".parse::<CreationModes>(),
+            Err(ModeError::UnexpectedArgument("2".into()))
+        );
+        assert_eq!(
+            "+l 900".parse::<CreationModes>(),
+            Err(ModeError::InvalidLimit("900".into()))
+        );
+    }
+}
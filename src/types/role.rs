@@ -0,0 +1,308 @@
+use std::{fmt, str::FromStr};
+
+/// Error returned when a user role string is not recognised.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseUserRoleError(String);
+
+impl fmt::Display for ParseUserRoleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown user role `{}`", self.0)
+    }
+}
+
+impl std::error::Error for ParseUserRoleError {}
+
+/// Role of a user in a room, ordered from least to most privileged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub enum UserRole {
+    /// Auditorium audience member; may only read.
+    Spectator,
+    #[default]
+    Participant,
+    Host,
+    Owner,
+    /// MSN community staff.
+    Guide,
+    /// Server operator.
+    Sysop,
+}
+
+impl UserRole {
+    pub const ALL: [UserRole; 6] = [
+        UserRole::Spectator,
+        UserRole::Participant,
+        UserRole::Host,
+        UserRole::Owner,
+        UserRole::Guide,
+        UserRole::Sysop,
+    ];
+
+    /// Value as stored in the frame's `UserRole` property.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            UserRole::Spectator => "spectator",
+            UserRole::Participant => "participant",
+            UserRole::Host => "host",
+            UserRole::Owner => "owner",
+            UserRole::Guide => "guide",
+            UserRole::Sysop => "sysop",
+        }
+    }
+
+    /// Role implied by an IRCX member prefix in a NAMES reply.
+    ///
+    /// Voice (`+`) and no prefix are both plain participants.
+    pub fn from_prefix(prefix: Option<char>) -> Self {
+        match prefix {
+            Some('.') => UserRole::Owner,
+            Some('@') => UserRole::Host,
+            _ => UserRole::Participant,
+        }
+    }
+
+    /// IRCX member prefix shown in front of the nickname, if any.
+    pub fn prefix(self) -> Option<char> {
+        match self {
+            UserRole::Owner | UserRole::Guide | UserRole::Sysop => Some('.'),
+            UserRole::Host => Some('@'),
+            UserRole::Participant | UserRole::Spectator => None,
+        }
+    }
+
+    pub fn privileges(self) -> Privileges {
+        use Privilege::*;
+
+        let participant = Privileges::from_iter([Speak, Whisper]);
+        let host = participant.union(Privileges::from_iter([
+            SetTopic,
+            Kick,
+            Ban,
+            Gag,
+            Voice,
+            ChangeModes,
+            ManageAccess,
+        ]));
+        let owner = host.union(Privileges::from_iter([GrantHost, GrantOwner, ChangeProps]));
+
+        match self {
+            UserRole::Spectator => Privileges::NONE,
+            UserRole::Participant => participant,
+            UserRole::Host => host,
+            UserRole::Owner | UserRole::Guide => owner,
+            UserRole::Sysop => Privileges::ALL,
+        }
+    }
+
+    pub fn may(self, privilege: Privilege) -> bool {
+        self.privileges().contains(privilege)
+    }
+
+    pub fn may_kick(self) -> bool {
+        self.may(Privilege::Kick)
+    }
+
+    pub fn may_set_topic(self) -> bool {
+        self.may(Privilege::SetTopic)
+    }
+
+    pub fn may_change_modes(self) -> bool {
+        self.may(Privilege::ChangeModes)
+    }
+
+    pub fn may_whisper(self) -> bool {
+        self.may(Privilege::Whisper)
+    }
+
+    /// True when `self` may use `privilege` against a user holding `target`.
+    ///
+    /// Moderation only flows downwards: hosts cannot act on owners and nobody
+    /// but a sysop can act on staff.
+    pub fn may_act_on(self, target: UserRole, privilege: Privilege) -> bool {
+        self.may(privilege) && (self > target || self == UserRole::Sysop)
+    }
+}
+
+impl fmt::Display for UserRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for UserRole {
+    type Err = ParseUserRoleError;
+
+    /// Accepts the role name (case-insensitive) and a few legacy aliases.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let role = match s.trim().to_ascii_lowercase().as_str() {
+            "spectator" | "audience" => UserRole::Spectator,
+            "participant" | "member" | "user" | "" => UserRole::Participant,
+            "host" | "op" => UserRole::Host,
+            "owner" => UserRole::Owner,
+            "guide" => UserRole::Guide,
+            "sysop" | "admin" => UserRole::Sysop,
+            _ => return Err(ParseUserRoleError(s.to_string())),
+        };
+        Ok(role)
+    }
+}
+
+/// A single moderation or participation right.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Privilege {
+    Speak,
+    Whisper,
+    SetTopic,
+    Kick,
+    Ban,
+    Gag,
+    Voice,
+    ChangeModes,
+    ManageAccess,
+    GrantHost,
+    ChangeProps,
+    GrantOwner,
+}
+
+impl Privilege {
+    pub const ALL: [Privilege; 12] = [
+        Privilege::Speak,
+        Privilege::Whisper,
+        Privilege::SetTopic,
+        Privilege::Kick,
+        Privilege::Ban,
+        Privilege::Gag,
+        Privilege::Voice,
+        Privilege::ChangeModes,
+        Privilege::ManageAccess,
+        Privilege::GrantHost,
+        Privilege::ChangeProps,
+        Privilege::GrantOwner,
+    ];
+
+    fn bit(self) -> u16 {
+        1 << self as u16
+    }
+}
+
+/// A set of [`Privilege`]s.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Privileges(u16);
+
+impl Privileges {
+    pub const NONE: Privileges = Privileges(0);
+    pub const ALL: Privileges = Privileges((1 << Privilege::ALL.len()) - 1);
+
+    pub fn contains(self, privilege: Privilege) -> bool {
+        self.0 & privilege.bit() != 0
+    }
+
+    pub fn insert(&mut self, privilege: Privilege) {
+        self.0 |= privilege.bit();
+    }
+
+    pub fn remove(&mut self, privilege: Privilege) {
+        self.0 &= !privilege.bit();
+    }
+
+    pub fn union(self, other: Privileges) -> Privileges {
+        Privileges(self.0 | other.0)
+    }
+
+    pub fn iter(self) -> impl Iterator<Item = Privilege> {
+        Privilege::ALL
+            .into_iter()
+            .filter(move |p| self.contains(*p))
+    }
+}
+
+impl FromIterator<Privilege> for Privileges {
+    fn from_iter<I: IntoIterator<Item = Privilege>>(iter: I) -> Self {
+        let mut set = Privileges::NONE;
+        for privilege in iter {
+            set.insert(privilege);
+        }
+        set
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roles_are_ordered_by_privilege() {
+        assert!(UserRole::ALL.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(UserRole::default(), UserRole::Participant);
+
+        for role in UserRole::ALL {
+            assert_eq!(role.as_str().parse(), Ok(role));
+            assert_eq!(role.to_string().to_uppercase().parse(), Ok(role));
+        }
+        assert_eq!("op".parse(), Ok(UserRole::Host));
+        assert_eq!("".parse(), Ok(UserRole::Participant));
+        assert_eq!(
+            "chanop".parse::<UserRole>(),
+            Err(ParseUserRoleError("chanop".into()))
+        );
+    }
+
+    #[test]
+    fn privileges() {
+        assert_eq!(UserRole::Spectator.privileges(), Privileges::NONE);
+        assert!(UserRole::Participant.may_whisper());
+        assert!(!UserRole::Participant.may_kick());
+        assert!(UserRole::Host.may_kick() && UserRole::Host.may_set_topic());
+        assert!(!UserRole::Host.may(Privilege::GrantOwner));
+        assert!(UserRole::Owner.may(Privilege::ChangeProps));
+        assert_eq!(UserRole::Guide.privileges(), UserRole::Owner.privileges());
+        assert_eq!(UserRole::Sysop.privileges(), Privileges::ALL);
+        assert!(Privilege::ALL.iter().all(|p| UserRole::Sysop.may(*p)));
+
+        // Each role keeps everything the role below it has.
+        for pair in UserRole::ALL.windows(2) {
+            let (lower, higher) = (pair[0], pair[1]);
+            assert!(lower.privileges().iter().all(|p| higher.may(p)));
+        }
+    }
+
+    #[test]
+    fn moderation_flows_downwards() {
+        use UserRole::*;
+
+        assert!(Host.may_act_on(Participant, Privilege::Kick));
+        assert!(!Host.may_act_on(Host, Privilege::Kick));
+        assert!(!Host.may_act_on(Owner, Privilege::Kick));
+        assert!(Owner.may_act_on(Host, Privilege::GrantOwner));
+        assert!(!Owner.may_act_on(Guide, Privilege::Kick));
+        assert!(Sysop.may_act_on(Sysop, Privilege::Kick));
+        // Outranking the target is not enough without the privilege.
+        assert!(!Participant.may_act_on(Spectator, Privilege::Kick));
+    }
+
+    #[test]
+    fn prefixes() {
+        assert_eq!(UserRole::from_prefix(Some('.')), UserRole::Owner);
+        assert_eq!(UserRole::from_prefix(Some('@')), UserRole::Host);
+        assert_eq!(UserRole::from_prefix(Some('+')), UserRole::Participant);
+        assert_eq!(UserRole::from_prefix(None), UserRole::Participant);
+
+        assert_eq!(UserRole::Sysop.prefix(), Some('.'));
+        assert_eq!(UserRole::Spectator.prefix(), None);
+        for role in [UserRole::Participant, UserRole::Host, UserRole::Owner] {
+            assert_eq!(UserRole::from_prefix(role.prefix()), role);
+        }
+    }
+
+    #[test]
+    fn privilege_sets() {
+        let mut set = Privileges::from_iter([Privilege::Kick, Privilege::Ban]);
+        assert!(set.contains(Privilege::Ban));
+        set.remove(Privilege::Ban);
+        set.insert(Privilege::Speak);
+        assert_eq!(
+            set.iter().collect::<Vec<_>>(),
+            [Privilege::Speak, Privilege::Kick]
+        );
+        assert_eq!(Privileges::ALL.iter().count(), Privilege::ALL.len());
+    }
+}
@@ -5,9 +5,14 @@ use windows::{
     }
 };
 
-use crate::bindings::{
-    guids::{self, CLSID_MSNChatFrame, IID_IChatFrame},
-    ichat_frame::{IChatFrame, IChatFrameVtbl},
+use crate::{
+    audit::AuditRecord,
+    bindings::{
+        guids::{self, CLSID_MSNChatFrame, IID_IChatFrame},
+        ichat_frame::{IChatFrame, IChatFrameVtbl},
+    },
+    ircx::{ChannelProps, Conversation, Invitation, InvitationCode, PassportCredentials, RoomSpec},
+    types::{LocaleSettings, UserRole},
 };
 
 #[derive(Clone)]
@@ -250,6 +255,25 @@ impl ChatFrame {
         com_put_bstr!(self, put_ChannelLanguage, val)
     }
 
+    /// Sets `Topic`, `WelcomeMsg` (`ONJOIN`) and `ChannelLanguage` from channel properties;
+    /// unset ones are cleared.
+    pub fn set_channel_props(&self, props: &ChannelProps) -> windows::core::Result<()> {
+        self.set_topic(props.topic.as_deref())?;
+        self.set_welcome_msg(props.on_join.as_deref())?;
+        self.set_channel_language(props.language.map(|l| l.to_string()).as_deref())
+    }
+
+    /// Reads the properties the frame configures; empty or unknown values are `None`.
+    pub fn channel_props(&self) -> windows::core::Result<ChannelProps> {
+        let non_empty = |s: String| (!s.is_empty()).then_some(s);
+        Ok(ChannelProps {
+            topic: non_empty(self.get_topic()?),
+            on_join: non_empty(self.get_welcome_msg()?),
+            language: self.get_channel_language()?.parse().ok(),
+            ..ChannelProps::default()
+        })
+    }
+
     pub fn get_invitation_code(&self) -> windows::core::Result<String> {
         com_get_bstr!(self, get_InvitationCode)
     }
@@ -265,6 +289,17 @@ impl ChatFrame {
         com_put_bstr!(self, put_NicknameToInvite, val)
     }
 
+    /// Reads `InvitationCode`; `None` if it is empty or malformed.
+    pub fn invitation_code(&self) -> windows::core::Result<Option<InvitationCode>> {
+        Ok(self.get_invitation_code()?.parse().ok())
+    }
+
+    /// Sets `NicknameToInvite` and `InvitationCode` from `invitation`.
+    pub fn set_invitation(&self, invitation: &Invitation) -> windows::core::Result<()> {
+        self.set_nickname_to_invite(Some(&invitation.nick))?;
+        self.set_invitation_code(invitation.code.as_ref().map(InvitationCode::as_str))
+    }
+
     pub fn get_msnreg_cookie(&self) -> windows::core::Result<String> {
         com_get_bstr!(self, get_MSNREGCookie)
     }
@@ -272,6 +307,31 @@ impl ChatFrame {
         com_put_bstr!(self, put_MSNREGCookie, val)
     }
 
+    /// Sets `PassportTicket`, `PassportProfile` and `MSNREGCookie` from validated credentials.
+    pub fn set_passport_credentials(
+        &self,
+        creds: &PassportCredentials,
+    ) -> windows::core::Result<()> {
+        self.set_passport_ticket(Some(creds.ticket()))?;
+        self.set_passport_profile(Some(creds.profile()))?;
+        self.set_msnreg_cookie(creds.regcookie())
+    }
+
+    /// Reads the Passport properties back; `None` if the ticket or profile is unset or
+    /// malformed. A malformed `MSNREGCookie` is left out rather than discarding both.
+    pub fn passport_credentials(&self) -> windows::core::Result<Option<PassportCredentials>> {
+        let Ok(creds) =
+            PassportCredentials::new(self.get_passport_ticket()?, self.get_passport_profile()?)
+        else {
+            return Ok(None);
+        };
+        let cookie = self.get_msnreg_cookie()?;
+        if cookie.is_empty() {
+            return Ok(Some(creds));
+        }
+        Ok(Some(creds.clone().with_regcookie(cookie).unwrap_or(creds)))
+    }
+
     pub fn get_creation_modes(&self) -> windows::core::Result<String> {
         com_get_bstr!(self, get_CreationModes)
     }
@@ -279,6 +339,17 @@ impl ChatFrame {
         com_put_bstr!(self, put_CreationModes, val)
     }
 
+    /// Configures the frame to create `spec` on open: `RoomName`, `Category`, `Topic`,
+    /// `ChannelLanguage`, `CreationModes`, and `CreateRoom` set to `1`.
+    pub fn set_room_spec(&self, spec: &RoomSpec) -> windows::core::Result<()> {
+        self.set_room_name(Some(&spec.name))?;
+        self.set_category(Some(spec.category.code()))?;
+        self.set_topic(spec.topic.as_deref())?;
+        self.set_channel_language(spec.language.map(|l| l.to_string()).as_deref())?;
+        self.set_creation_modes(Some(&spec.creation_modes().to_string()))?;
+        self.set_create_room(Some("1"))
+    }
+
     pub fn get_msn_profile(&self) -> windows::core::Result<String> {
         com_get_bstr!(self, get_MSNProfile)
     }
@@ -293,6 +364,13 @@ impl ChatFrame {
         com_put_bstr!(self, put_Market, val)
     }
 
+    /// Sets `Locale`, `Market` and `ChannelLanguage` together so they never disagree.
+    pub fn set_locale_settings(&self, settings: &LocaleSettings) -> windows::core::Result<()> {
+        self.set_locale(Some(settings.locale.tag()))?;
+        self.set_market(Some(settings.market.tag()))?;
+        self.set_channel_language(Some(&settings.channel_language.to_string()))
+    }
+
     pub fn get_whisper_content(&self) -> windows::core::Result<String> {
         com_get_bstr!(self, get_WhisperContent)
     }
@@ -300,6 +378,15 @@ impl ChatFrame {
         com_put_bstr!(self, put_WhisperContent, val)
     }
 
+    /// Seeds `WhisperContent` with a stored conversation so a reopened whisper window
+    /// keeps its history.
+    pub fn set_whisper_conversation(
+        &self,
+        conversation: &Conversation,
+    ) -> windows::core::Result<()> {
+        self.set_whisper_content(Some(&conversation.whisper_content()))
+    }
+
     pub fn get_user_role(&self) -> windows::core::Result<String> {
         com_get_bstr!(self, get_UserRole)
     }
@@ -307,6 +394,11 @@ impl ChatFrame {
         com_put_bstr!(self, put_UserRole, val)
     }
 
+    /// Reads `UserRole` as a typed [`UserRole`]; `None` if the control reports an unknown role.
+    pub fn user_role(&self) -> windows::core::Result<Option<UserRole>> {
+        Ok(self.get_user_role()?.parse().ok())
+    }
+
     pub fn get_audit_message(&self) -> windows::core::Result<String> {
         com_get_bstr!(self, get_AuditMessage)
     }
@@ -314,6 +406,16 @@ impl ChatFrame {
         com_put_bstr!(self, put_AuditMessage, val)
     }
 
+    /// Parses `AuditMessage`; `None` if it is empty or not in [`AuditRecord`] form.
+    pub fn audit_record(&self) -> windows::core::Result<Option<AuditRecord>> {
+        Ok(self.get_audit_message()?.parse().ok())
+    }
+
+    /// Writes `record` to `AuditMessage` in the form [`audit_record`](Self::audit_record) reads.
+    pub fn set_audit_record(&self, record: &AuditRecord) -> windows::core::Result<()> {
+        self.set_audit_message(Some(&record.to_string()))
+    }
+
     pub fn get_subscriber_info(&self) -> windows::core::Result<String> {
         com_get_bstr!(self, get_SubscriberInfo)
     }
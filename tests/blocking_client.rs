@@ -0,0 +1,53 @@
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::TcpListener,
+    thread,
+    time::Duration,
+};
+
+use msnchat_bindings::{
+    client::{ChatEvent, Client, ClientOptions},
+    ircx::{SessionConfig, SessionEvent},
+};
+
+#[test]
+fn plain_irc_fallback_and_ping_timeout() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let server = thread::spawn(move || {
+        let (socket, _) = listener.accept().unwrap();
+        let mut writer = socket.try_clone().unwrap();
+        let mut lines = BufReader::new(socket).lines();
+        let mut next = || lines.next().unwrap().unwrap();
+
+        assert!(next().starts_with("IRCVERS "));
+        writer
+            .write_all(b":srv 421 * IRCVERS :Unknown command\r\n")
+            .unwrap();
+        assert_eq!(next(), "NICK Ferris");
+        assert!(next().starts_with("USER "));
+        writer.write_all(b":srv 001 Ferris :Welcome\r\n").unwrap();
+
+        // Answer nothing else: the client pings, then gives up.
+        assert_eq!(next(), "PING keepalive");
+        assert!(lines.next().is_none());
+    });
+
+    let options = ClientOptions {
+        ping_interval: Duration::from_millis(100),
+        ping_timeout: Duration::from_millis(100),
+        ..ClientOptions::default()
+    };
+    let mut client =
+        Client::connect_with(SessionConfig::new(addr.to_string(), "Ferris"), options).unwrap();
+    let events: Vec<ChatEvent> = client.events().collect();
+    drop(client);
+
+    assert!(events.iter().any(|e| matches!(
+        e,
+        ChatEvent::Session(SessionEvent::Registered { nick }) if nick == "Ferris"
+    )));
+    assert!(matches!(events.last(), Some(ChatEvent::Error(_))));
+    server.join().unwrap();
+}
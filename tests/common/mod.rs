@@ -0,0 +1,16 @@
+use msnchat_bindings::{
+    client::{ChatEvent, Client},
+    ircx::SessionEvent,
+};
+
+/// Reads events until `found` accepts one, failing on client errors or EOF.
+pub fn wait_for(client: &mut Client, mut found: impl FnMut(&SessionEvent) -> bool) -> SessionEvent {
+    for event in client.events() {
+        match event {
+            ChatEvent::Session(event) if found(&event) => return event,
+            ChatEvent::Session(_) => {}
+            ChatEvent::Error(err) => panic!("client failed: {err}"),
+        }
+    }
+    panic!("connection closed before the expected event");
+}
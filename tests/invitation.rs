@@ -0,0 +1,142 @@
+#![cfg(feature = "mock")]
+
+mod common;
+
+use std::time::Duration;
+
+use common::wait_for;
+use msnchat_bindings::{
+    client::Client,
+    ircx::{
+        Command, Invitation, Invitations, InviteError, KnockQueue, KnockState, Numeric,
+        SessionConfig, SessionError, SessionEvent,
+    },
+    mock::MockServer,
+    types::UserRole,
+};
+
+fn connect(server: &MockServer, nick: &str) -> Client {
+    let config = SessionConfig::new(server.address(), nick).with_room("Lobby");
+    Client::connect(config).unwrap()
+}
+
+fn refused_with(numeric: Numeric) -> impl FnMut(&SessionEvent) -> bool {
+    move |e| matches!(e, SessionEvent::Error(SessionError::Server(err)) if err.numeric == numeric)
+}
+
+/// Alice creates the room as owner and makes it invite-only with knocking.
+fn locked_room(server: &MockServer) -> (Client, String) {
+    let mut alice = connect(server, "Alice");
+    wait_for(&mut alice, |e| matches!(e, SessionEvent::Joined { .. }));
+    let channel = alice.session().channel().unwrap().to_owned();
+    alice
+        .send(&Command::Mode {
+            target: channel.clone(),
+            modes: Some("+iu".into()),
+            args: Vec::new(),
+        })
+        .unwrap();
+    wait_for(&mut alice, |e| {
+        matches!(
+            e,
+            SessionEvent::Message {
+                command: Command::Mode { .. },
+                ..
+            }
+        )
+    });
+    (alice, channel)
+}
+
+#[test]
+fn knock_accepted_with_invitation_code() {
+    let server = MockServer::spawn().unwrap();
+    let (mut alice, channel) = locked_room(&server);
+    let mut knocks = KnockQueue::new(&channel);
+    let mut invitations = Invitations::new();
+
+    let mut bob = connect(&server, "Bob");
+    wait_for(&mut bob, refused_with(Numeric::InviteOnlyChan));
+    wait_for(&mut alice, |e| knocks.handle_event(e).is_some());
+    let knock = knocks.get("Bob").unwrap();
+    assert_eq!(knock.reason, Numeric::InviteOnlyChan);
+    assert_eq!(knock.mask, "*!*@localhost");
+    assert_eq!(knocks.pending().len(), 1);
+
+    assert_eq!(
+        knocks.accept("Bob", UserRole::Participant, None),
+        Err(InviteError::NotPermitted(UserRole::Participant))
+    );
+    let code = invitations.issue(&channel, "Bob").code;
+    let invitation = knocks.accept("Bob", UserRole::Owner, code).unwrap();
+    assert_eq!(knocks.state("Bob"), Some(KnockState::Accepted));
+    assert!(knocks.pending().is_empty());
+    for command in invitation.commands() {
+        alice.send(&command).unwrap();
+    }
+
+    let mut received = Vec::new();
+    wait_for(&mut bob, |e| {
+        received.extend(Invitation::from_event(e));
+        received.iter().any(|i| i.code.is_some())
+    });
+    assert_eq!(received.len(), 2);
+    assert!(received.iter().all(|i| i.channel == channel));
+    assert_eq!(received[0].from.as_deref(), Some("Alice"));
+    let code = received[1].code.clone().unwrap();
+    invitations.redeem(code.as_str(), &channel, "Bob").unwrap();
+    assert_eq!(
+        invitations.redeem(code.as_str(), &channel, "Bob"),
+        Err(InviteError::UnknownCode)
+    );
+
+    bob.send(&Command::Join {
+        channels: channel.clone(),
+        keys: None,
+    })
+    .unwrap();
+    wait_for(&mut bob, |e| matches!(e, SessionEvent::Joined { .. }));
+    wait_for(&mut alice, |e| {
+        knocks.handle_event(e);
+        knocks.state("Bob").is_none()
+    });
+}
+
+#[test]
+fn knock_denied_and_banned() {
+    let server = MockServer::spawn().unwrap();
+    let (mut alice, channel) = locked_room(&server);
+    let mut knocks = KnockQueue::new(&channel);
+
+    let mut carol = connect(&server, "Carol");
+    wait_for(&mut carol, refused_with(Numeric::InviteOnlyChan));
+    wait_for(&mut alice, |e| knocks.handle_event(e).is_some());
+
+    let ban = knocks
+        .deny_and_ban("Carol", UserRole::Owner, Some(Duration::from_secs(600)))
+        .unwrap();
+    assert_eq!(knocks.state("Carol"), Some(KnockState::Denied));
+    assert_eq!(
+        knocks.deny("Carol", UserRole::Owner),
+        Err(InviteError::NoKnock("Carol".into()))
+    );
+    for command in &ban {
+        alice.send(command).unwrap();
+    }
+    // 801 confirms the ACCESS entry before Carol tries again.
+    wait_for(&mut alice, |e| match e {
+        SessionEvent::Message {
+            command: Command::Other { command, .. },
+            ..
+        } => command == "801",
+        _ => false,
+    });
+
+    carol
+        .send(&Command::Join {
+            channels: channel,
+            keys: None,
+        })
+        .unwrap();
+    wait_for(&mut carol, refused_with(Numeric::BannedFromChan));
+}
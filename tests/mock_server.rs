@@ -0,0 +1,184 @@
+#![cfg(feature = "mock")]
+
+mod common;
+
+use common::wait_for;
+use msnchat_bindings::{
+    client::Client,
+    ircx::{Command, Numeric, SessionConfig, SessionError, SessionEvent, SessionState},
+    mock::{Action, MockServer, Scenario},
+};
+
+fn join(server: &MockServer, nick: &str) -> Client {
+    let config = SessionConfig::new(server.address(), nick).with_room("Lobby");
+    let mut client = Client::connect(config).unwrap();
+    wait_for(&mut client, |e| matches!(e, SessionEvent::Joined { .. }));
+    client
+}
+
+#[test]
+fn gatekeeper_join_and_messages() {
+    let server = MockServer::spawn().unwrap();
+    let mut alice = join(&server, "Alice");
+    let mut bob = join(&server, "Bob");
+    assert!(matches!(alice.session().state(), SessionState::Joined));
+
+    let channel = alice.session().channel().unwrap().to_owned();
+    alice
+        .send(&Command::Privmsg {
+            target: channel.clone(),
+            text: "hello".into(),
+        })
+        .unwrap();
+    alice
+        .send(&Command::Whisper {
+            channel,
+            targets: "Bob".into(),
+            text: "psst".into(),
+        })
+        .unwrap();
+
+    let said = |want: &'static str| {
+        move |e: &SessionEvent| match e {
+            SessionEvent::Message {
+                command: Command::Privmsg { text, .. } | Command::Whisper { text, .. },
+                ..
+            } => text == want,
+            _ => false,
+        }
+    };
+    wait_for(&mut bob, said("hello"));
+    wait_for(&mut bob, said("psst"));
+
+    alice.quit(None).unwrap();
+    bob.quit(None).unwrap();
+    assert!(
+        server
+            .received()
+            .iter()
+            .any(|l| l.starts_with("AUTH GateKeeper S "))
+    );
+}
+
+#[test]
+fn scripted_join_failure() {
+    let server = MockServer::builder()
+        .scenario(Scenario::new().once(
+            "JOIN",
+            Action::Error(Numeric::ChannelIsFull, "Cannot join channel (+l)".into()),
+        ))
+        .spawn()
+        .unwrap();
+
+    let config = SessionConfig::new(server.address(), "Carol").with_room("Lobby");
+    let mut client = Client::connect(config).unwrap();
+    let event = wait_for(&mut client, |e| matches!(e, SessionEvent::Error(_)));
+
+    assert!(matches!(
+        event,
+        SessionEvent::Error(SessionError::Server(err)) if err.numeric == Numeric::ChannelIsFull
+    ));
+    assert!(matches!(client.session().state(), SessionState::Registered));
+}
+
+fn numeric(code: Numeric) -> impl Fn(&SessionEvent) -> bool {
+    move |e| match e {
+        SessionEvent::Message { command, .. } => command.numeric() == Some(code.code()),
+        SessionEvent::Error(SessionError::Server(err)) => err.numeric == code,
+        _ => false,
+    }
+}
+
+#[test]
+fn wildcard_bind_authenticates() {
+    let server = MockServer::builder()
+        .bind("0.0.0.0:0")
+        .unwrap()
+        .spawn()
+        .unwrap();
+    assert!(server.local_addr().ip().is_unspecified());
+    assert_eq!(
+        server.address(),
+        format!("127.0.0.1:{}", server.local_addr().port())
+    );
+
+    // The client hashes the address it dialled, not the wildcard one.
+    let mut alice = join(&server, "Alice");
+    alice.quit(None).unwrap();
+}
+
+#[test]
+fn grant_overrides_deny() {
+    let server = MockServer::spawn().unwrap();
+    let mut alice = join(&server, "Alice");
+    for (level, mask) in [("DENY", "*!*@*"), ("GRANT", "Bob!*@*")] {
+        alice
+            .send(&Command::Access {
+                target: "%#Lobby".into(),
+                operation: Some("ADD".into()),
+                args: vec![level.into(), mask.into()],
+            })
+            .unwrap();
+        wait_for(&mut alice, numeric(Numeric::AccessAdd));
+    }
+
+    let mut bob = join(&server, "Bob");
+    assert!(matches!(bob.session().state(), SessionState::Joined));
+
+    let config = SessionConfig::new(server.address(), "Carol").with_room("Lobby");
+    let mut carol = Client::connect(config).unwrap();
+    wait_for(&mut carol, numeric(Numeric::BannedFromChan));
+    bob.quit(None).unwrap();
+}
+
+#[test]
+fn unknown_mode_is_not_broadcast() {
+    let server = MockServer::spawn().unwrap();
+    let mut alice = join(&server, "Alice");
+    let mut bob = join(&server, "Bob");
+    let mode = |modes: &str| Command::Mode {
+        target: "%#Lobby".into(),
+        modes: Some(modes.into()),
+        args: Vec::new(),
+    };
+
+    alice.send(&mode("+mQ")).unwrap();
+    wait_for(&mut alice, numeric(Numeric::UnknownMode));
+    alice.send(&mode("+t")).unwrap();
+
+    // Bob's first MODE is the valid one, and the rejected `+m` was not applied.
+    let event = wait_for(&mut bob, |e| {
+        matches!(
+            e,
+            SessionEvent::Message {
+                command: Command::Mode { .. },
+                ..
+            }
+        )
+    });
+    let SessionEvent::Message {
+        command: Command::Mode { modes, .. },
+        ..
+    } = event
+    else {
+        unreachable!();
+    };
+    assert_eq!(modes.as_deref(), Some("+t"));
+
+    alice
+        .send(&Command::Mode {
+            target: "%#Lobby".into(),
+            modes: None,
+            args: Vec::new(),
+        })
+        .unwrap();
+    let event = wait_for(&mut alice, numeric(Numeric::ChannelModeIs));
+    let SessionEvent::Message {
+        command: Command::Other { params, .. },
+        ..
+    } = event
+    else {
+        unreachable!();
+    };
+    assert_eq!(params[2], "+t");
+}
@@ -0,0 +1,108 @@
+#![cfg(feature = "tokio")]
+
+use msnchat_bindings::{
+    client::{self, ChatEvent},
+    ircx::{Command, SessionConfig, SessionEvent, escape::escape, gatekeeper::encode_header},
+};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, tcp::OwnedReadHalf},
+};
+use tokio_stream::StreamExt;
+
+#[tokio::test]
+async fn handshake_join_and_quit() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let server = tokio::spawn(async move {
+        let (socket, _) = listener.accept().await.unwrap();
+        let (reader, mut writer) = socket.into_split();
+        let mut reader = BufReader::new(reader);
+
+        assert!(
+            next_line(&mut reader)
+                .await
+                .starts_with("IRCVERS IRC8 MSN-OCX!")
+        );
+        writer
+            .write_all(b":srv 800 * 1 0 GateKeeper 512 *\r\n")
+            .await
+            .unwrap();
+
+        assert!(
+            next_line(&mut reader)
+                .await
+                .starts_with("AUTH GateKeeper I :GKSSP")
+        );
+        let mut challenge = encode_header(3, 2).to_vec();
+        challenge.extend_from_slice(b"\0\x01 ,\r\n\\x");
+        let mut reply = b"AUTH GateKeeper S :".to_vec();
+        reply.extend_from_slice(&escape(&challenge));
+        reply.extend_from_slice(b"\r\n");
+        writer.write_all(&reply).await.unwrap();
+
+        assert!(
+            next_line(&mut reader)
+                .await
+                .starts_with("AUTH GateKeeper S :GKSSP")
+        );
+        writer
+            .write_all(b"AUTH GateKeeper * 0123456789ABCDEF0123456789ABCDEF@GateKeeper 0\r\n")
+            .await
+            .unwrap();
+
+        assert_eq!(next_line(&mut reader).await, "NICK Ferris\r\n");
+        assert!(next_line(&mut reader).await.starts_with("USER "));
+        writer
+            .write_all(b":srv 001 Ferris :Welcome\r\n")
+            .await
+            .unwrap();
+
+        assert_eq!(next_line(&mut reader).await, "JOIN %#The\\bLobby\r\n");
+        writer
+            .write_all(b":Ferris!a@gatekeeper JOIN H,U,GX :%#The\\bLobby\r\n")
+            .await
+            .unwrap();
+
+        assert_eq!(
+            next_line(&mut reader).await,
+            "PRIVMSG %#The\\bLobby :hello\r\n"
+        );
+        assert_eq!(next_line(&mut reader).await, "QUIT :bye\r\n");
+    });
+
+    let config = SessionConfig::new(addr.to_string(), "Ferris").with_room("The Lobby");
+    let (handle, events) = client::connect(config).await.unwrap();
+    tokio::pin!(events);
+
+    let mut joined = false;
+    while let Some(event) = events.next().await {
+        match event {
+            ChatEvent::Session(SessionEvent::Joined { channel }) => {
+                assert_eq!(channel, "%#The\\bLobby");
+                joined = true;
+                handle
+                    .send(Command::Privmsg {
+                        target: channel,
+                        text: "hello".to_owned(),
+                    })
+                    .unwrap();
+                handle.quit(Some("bye")).unwrap();
+            }
+            ChatEvent::Session(SessionEvent::Closed { .. }) => break,
+            ChatEvent::Session(SessionEvent::Error(err)) => panic!("{err}"),
+            ChatEvent::Error(err) => panic!("{err}"),
+            ChatEvent::Session(_) => {}
+        }
+    }
+
+    assert!(joined);
+    server.await.unwrap();
+}
+
+async fn next_line(reader: &mut BufReader<OwnedReadHalf>) -> String {
+    let mut line = Vec::new();
+    reader.read_until(b'\n', &mut line).await.unwrap();
+    String::from_utf8_lossy(&line).into_owned()
+}